@@ -0,0 +1,555 @@
+//! An assembler for CHIP-8 programs.
+//!
+//! The syntax is the one the disassembler produces, so a listing from
+//! `disasm::disassemble` assembles back to the original ROM:
+//!
+//! ```text
+//! ; Comments run from a semicolon to the end of the line
+//! SPEED = 4                   ; constants, also written SPEED equ 4
+//! include "sprites.s"         ; paths are relative to the including file
+//!
+//! start:
+//!     LD V0, SPEED
+//!     LD I, ship
+//!     DRW V0, V1, 4
+//!     JP start
+//! ship:
+//!     db 0x18, 0x3C, 0x7E, 0xFF
+//!     dw 0x1234, start + 2
+//! ```
+//!
+//! Mnemonics and register names are case-insensitive; labels and constants
+//! are not. Numbers are decimal, hex with a `0x` prefix or binary with a
+//! `0b` prefix, and operands may add and subtract labels, constants and
+//! numbers.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use disasm::DEFAULT_ORIGIN;
+use instruction::Instruction;
+
+/// How deeply include files may nest, to catch files including themselves.
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// How deeply constants may refer to other constants, to catch cycles.
+const MAX_CONSTANT_DEPTH: usize = 16;
+
+/// Name given to source passed to `assemble` in error messages.
+pub(crate) const INPUT_NAME: &str = "<input>";
+
+/// An error in the program being assembled.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AsmError {
+    /// The file the error is in.
+    pub file: String,
+
+    /// The line number of the error, starting from 1, or 0 if the error
+    /// isn't on any particular line.
+    pub line: usize,
+
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.line == 0 {
+            write!(f, "{}: {}", self.file, self.message)
+        } else {
+            write!(f, "{}:{}: {}", self.file, self.line, self.message)
+        }
+    }
+}
+
+impl Error for AsmError {}
+
+/// Assemble `source` into a ROM to be loaded at 0x200. Include files are
+/// found relative to the current directory.
+pub fn assemble(source: &str) -> Result<Vec<u8>, AsmError> {
+    let mut assembler = Assembler::new();
+    assembler.parse(source, INPUT_NAME, Path::new(""), 0)?;
+    assembler.emit()
+}
+
+/// Assemble the file at `path` into a ROM to be loaded at 0x200.
+pub fn assemble_file<P: AsRef<Path>>(path: P) -> Result<Vec<u8>, AsmError> {
+    let path = path.as_ref();
+    let mut assembler = Assembler::new();
+    assembler.include(path, &Location::file(path), 0)?;
+    assembler.emit()
+}
+
+/// Where a statement came from.
+#[derive(Clone, Debug)]
+struct Location {
+    file: String,
+    line: usize,
+}
+
+impl Location {
+    fn file(path: &Path) -> Location {
+        Location { file: path.display().to_string(), line: 0 }
+    }
+
+    fn error<S: Into<String>>(&self, message: S) -> AsmError {
+        AsmError {
+            file: self.file.clone(),
+            line: self.line,
+            message: message.into(),
+        }
+    }
+}
+
+/// A line of source that produces output.
+struct Statement {
+    location: Location,
+    kind: Kind,
+}
+
+enum Kind {
+    Instruction { mnemonic: String, operands: Vec<String> },
+    Bytes(Vec<String>),
+    Words(Vec<String>),
+}
+
+/// A parsed instruction operand.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Operand {
+    V(u8),
+    Range(u8, u8),
+    I,
+    IndirectI,
+    Dt,
+    St,
+    K,
+    F,
+    Hf,
+    B,
+    R,
+    Long(i64),
+    Value(i64),
+}
+
+/// The two passes of assembly. The first reads the source, following
+/// includes, and assigns an address to every label. The second evaluates
+/// operands and emits the ROM.
+struct Assembler {
+    statements: Vec<Statement>,
+    labels: HashMap<String, u16>,
+    constants: HashMap<String, (String, Location)>,
+    addr: usize,
+}
+
+impl Assembler {
+    fn new() -> Assembler {
+        Assembler {
+            statements: Vec::new(),
+            labels: HashMap::new(),
+            constants: HashMap::new(),
+            addr: DEFAULT_ORIGIN as usize,
+        }
+    }
+
+    /// Read and parse the file at `path`, included from `from`.
+    fn include(&mut self, path: &Path, from: &Location,
+               depth: usize) -> Result<(), AsmError> {
+        if depth > MAX_INCLUDE_DEPTH {
+            return Err(from.error("includes nested too deeply"));
+        }
+        let source = fs::read_to_string(path)
+            .map_err(|err| {
+                from.error(format!("couldn't read {}: {}", path.display(), err))
+            })?;
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        self.parse(&source, &path.display().to_string(), dir, depth)
+    }
+
+    /// First pass over `source`, read from `file` in `dir`.
+    fn parse(&mut self, source: &str, file: &str, dir: &Path,
+             depth: usize) -> Result<(), AsmError> {
+        for (n, line) in source.lines().enumerate() {
+            let location = Location { file: file.to_string(), line: n + 1 };
+            let mut text = line.split(';').next().unwrap_or("").trim();
+
+            // A label may share its line with a statement
+            if let Some(colon) = text.find(':') {
+                let name = text[..colon].trim();
+                if !is_identifier(name) {
+                    return Err(location.error(format!("bad label {}", name)));
+                }
+                self.define_label(name, &location)?;
+                text = text[colon + 1..].trim();
+            }
+            if text.is_empty() {
+                continue;
+            }
+
+            let (word, rest) = split_word(text);
+
+            // NAME = VALUE or NAME equ VALUE
+            let (next, value) = split_word(rest);
+            let constant = if let Some(value) = rest.strip_prefix('=') {
+                Some(value)
+            } else if next.eq_ignore_ascii_case("equ") {
+                Some(value)
+            } else {
+                None
+            };
+            if let Some(value) = constant {
+                self.define_constant(word, value.trim(), &location)?;
+                continue;
+            }
+
+            match word.to_lowercase().as_str() {
+                "include" => {
+                    let name = rest.trim_matches('"');
+                    if name.is_empty() {
+                        return Err(location.error("include requires a file"));
+                    }
+                    self.include(&dir.join(name), &location, depth + 1)?;
+                },
+                "db" => {
+                    let values = operands(rest);
+                    self.advance(values.len(), &location)?;
+                    self.statements.push(Statement {
+                        location,
+                        kind: Kind::Bytes(values),
+                    });
+                },
+                "dw" => {
+                    let values = operands(rest);
+                    self.advance(values.len() * 2, &location)?;
+                    self.statements.push(Statement {
+                        location,
+                        kind: Kind::Words(values),
+                    });
+                },
+                mnemonic => {
+                    let operands = operands(rest);
+                    let long = operands.get(1).is_some_and(|operand| {
+                        operand.to_uppercase().starts_with("LONG ")
+                    });
+                    self.advance(if long { 4 } else { 2 }, &location)?;
+                    self.statements.push(Statement {
+                        location,
+                        kind: Kind::Instruction {
+                            mnemonic: mnemonic.to_uppercase(),
+                            operands,
+                        },
+                    });
+                },
+            }
+        }
+        Ok(())
+    }
+
+    fn define_label(&mut self, name: &str,
+                    location: &Location) -> Result<(), AsmError> {
+        if self.labels.contains_key(name) || self.constants.contains_key(name) {
+            return Err(location.error(format!("{} is already defined", name)));
+        }
+        self.labels.insert(name.to_string(), self.addr as u16);
+        Ok(())
+    }
+
+    fn define_constant(&mut self, name: &str, value: &str,
+                       location: &Location) -> Result<(), AsmError> {
+        if !is_identifier(name) {
+            return Err(location.error(format!("bad constant name {}", name)));
+        }
+        if self.labels.contains_key(name) || self.constants.contains_key(name) {
+            return Err(location.error(format!("{} is already defined", name)));
+        }
+        self.constants.insert(name.to_string(),
+                              (value.to_string(), location.clone()));
+        Ok(())
+    }
+
+    /// Reserve `len` bytes for the statement at `location`.
+    fn advance(&mut self, len: usize,
+               location: &Location) -> Result<(), AsmError> {
+        self.addr += len;
+        if self.addr > 0x10000 {
+            return Err(location.error("program doesn't fit in memory"));
+        }
+        Ok(())
+    }
+
+    /// Second pass, producing the ROM.
+    fn emit(&self) -> Result<Vec<u8>, AsmError> {
+        let mut rom = Vec::new();
+        for statement in &self.statements {
+            let location = &statement.location;
+            match statement.kind {
+                Kind::Instruction { ref mnemonic, ref operands } => {
+                    let operands = operands.iter()
+                        .map(|operand| self.operand(operand, location))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    let instruction = build(mnemonic, &operands)
+                        .map_err(|message| location.error(message))?;
+                    rom.extend(instruction.encode());
+                },
+                Kind::Bytes(ref values) => {
+                    for value in values {
+                        let value = self.eval(value, location, 0)?;
+                        rom.push(byte(value)
+                                 .map_err(|message| location.error(message))?);
+                    }
+                },
+                Kind::Words(ref values) => {
+                    for value in values {
+                        let value = self.eval(value, location, 0)?;
+                        let word = fit(value, -0x8000, 0xFFFF, "a word")
+                            .map_err(|message| location.error(message))?;
+                        rom.extend_from_slice(&(word as u16).to_be_bytes());
+                    }
+                },
+            }
+        }
+        Ok(rom)
+    }
+
+    fn operand(&self, text: &str,
+               location: &Location) -> Result<Operand, AsmError> {
+        let upper = text.to_uppercase();
+        let operand = match upper.as_str() {
+            "I" => Operand::I,
+            "[I]" => Operand::IndirectI,
+            "DT" => Operand::Dt,
+            "ST" => Operand::St,
+            "K" => Operand::K,
+            "F" => Operand::F,
+            "HF" => Operand::Hf,
+            "B" => Operand::B,
+            "R" => Operand::R,
+            _ => {
+                if let Some(reg) = register(&upper) {
+                    return Ok(Operand::V(reg));
+                }
+                let mut range = upper.splitn(2, '-')
+                    .map(|reg| register(reg.trim()));
+                if let (Some(Some(x)), Some(Some(y))) = (range.next(),
+                                                         range.next()) {
+                    return Ok(Operand::Range(x, y));
+                }
+                if upper.starts_with("LONG ") {
+                    let value = self.eval(&text[5..], location, 0)?;
+                    return Ok(Operand::Long(value));
+                }
+                Operand::Value(self.eval(text, location, 0)?)
+            },
+        };
+        Ok(operand)
+    }
+
+    /// Evaluate a sum of numbers, labels and constants.
+    fn eval(&self, text: &str, location: &Location,
+            depth: usize) -> Result<i64, AsmError> {
+        let mut total = 0;
+        let mut sign = 1;
+        let mut term = String::new();
+        for c in text.chars().chain(Some('+')) {
+            if c != '+' && c != '-' {
+                term.push(c);
+                continue;
+            }
+            if term.trim().is_empty() {
+                // A unary sign
+                if c == '-' {
+                    sign = -sign;
+                }
+                continue;
+            }
+            total += sign * self.term(term.trim(), location, depth)?;
+            term.clear();
+            sign = if c == '-' { -1 } else { 1 };
+        }
+        if text.trim().is_empty() || !term.trim().is_empty() || sign != 1 {
+            return Err(location.error(format!("missing value in {:?}", text)));
+        }
+        Ok(total)
+    }
+
+    fn term(&self, text: &str, location: &Location,
+            depth: usize) -> Result<i64, AsmError> {
+        if text.starts_with(|c: char| c.is_ascii_digit()) {
+            return parse_number(text)
+                .ok_or_else(|| location.error(format!("bad number {}", text)));
+        }
+        if let Some(&addr) = self.labels.get(text) {
+            return Ok(addr as i64);
+        }
+        match self.constants.get(text) {
+            Some(_) if depth >= MAX_CONSTANT_DEPTH => {
+                Err(location.error(format!("constant {} is defined in terms \
+                                            of itself", text)))
+            },
+            Some((value, defined_at)) => {
+                self.eval(value, defined_at, depth + 1)
+            },
+            None => {
+                Err(location.error(format!("undefined label or constant {}",
+                                           text)))
+            },
+        }
+    }
+}
+
+/// Build the instruction for `mnemonic` applied to `operands`.
+fn build(mnemonic: &str, operands: &[Operand]) -> Result<Instruction, String> {
+    use self::Operand::*;
+
+    let instruction = match (mnemonic, operands) {
+        ("CLS", []) => Instruction::Cls,
+        ("RET", []) => Instruction::Ret,
+        ("SCD", &[Value(n)]) => Instruction::ScdNib(nibble(n)?),
+        ("SCR", []) => Instruction::Scr,
+        ("SCL", []) => Instruction::Scl,
+        ("EXIT", []) => Instruction::Exit,
+        ("LOW", []) => Instruction::Low,
+        ("HIGH", []) => Instruction::High,
+        ("JP", &[Value(nnn)]) => Instruction::JpAddr(addr(nnn)?),
+        ("JP", &[V(0), Value(nnn)]) => Instruction::JpV0Addr(addr(nnn)?),
+        ("CALL", &[Value(nnn)]) => Instruction::CallAddr(addr(nnn)?),
+        ("SE", &[V(x), Value(nn)]) => Instruction::SeVxByte(x, byte(nn)?),
+        ("SE", &[V(x), V(y)]) => Instruction::SeVxVy(x, y),
+        ("SNE", &[V(x), Value(nn)]) => Instruction::SneVxByte(x, byte(nn)?),
+        ("SNE", &[V(x), V(y)]) => Instruction::SneVxVy(x, y),
+        ("LD", &[V(x), Value(nn)]) => Instruction::LdVxByte(x, byte(nn)?),
+        ("LD", &[V(x), V(y)]) => Instruction::LdVxVy(x, y),
+        ("LD", &[I, Value(nnn)]) => Instruction::LdIndexAddr(addr(nnn)?),
+        ("LD", &[I, Long(nnnn)]) => {
+            Instruction::LdIndexLong(fit(nnnn, 0, 0xFFFF, "16 bits")? as u16)
+        },
+        ("LD", &[V(x), Dt]) => Instruction::LdVxDt(x),
+        ("LD", &[V(x), K]) => Instruction::LdVxKey(x),
+        ("LD", &[Dt, V(x)]) => Instruction::LdDtVx(x),
+        ("LD", &[St, V(x)]) => Instruction::LdStVx(x),
+        ("LD", &[F, V(x)]) => Instruction::LdIndexVxSprite(x),
+        ("LD", &[Hf, V(x)]) => Instruction::LdIndexVxBigSprite(x),
+        ("LD", &[B, V(x)]) => Instruction::LdBcdVx(x),
+        ("LD", &[IndirectI, V(x)]) => Instruction::LdIndexImmVx(x),
+        ("LD", &[V(x), IndirectI]) => Instruction::LdVxIndexImm(x),
+        ("LD", &[IndirectI, Range(x, y)]) => Instruction::LdIndexVxVy(x, y),
+        ("LD", &[Range(x, y), IndirectI]) => Instruction::LdVxVyIndex(x, y),
+        ("LD", &[R, V(x)]) => Instruction::LdRplVx(x),
+        ("LD", &[V(x), R]) => Instruction::LdVxRpl(x),
+        ("ADD", &[V(x), Value(nn)]) => Instruction::AddVxByte(x, byte(nn)?),
+        ("ADD", &[V(x), V(y)]) => Instruction::AddVxVy(x, y),
+        ("ADD", &[I, V(x)]) => Instruction::AddIndexVx(x),
+        ("OR", &[V(x), V(y)]) => Instruction::OrVxVy(x, y),
+        ("AND", &[V(x), V(y)]) => Instruction::AndVxVy(x, y),
+        ("XOR", &[V(x), V(y)]) => Instruction::XorVxVy(x, y),
+        ("SUB", &[V(x), V(y)]) => Instruction::SubVxVy(x, y),
+        ("SUBN", &[V(x), V(y)]) => Instruction::SubnVxVy(x, y),
+        ("SHR", &[V(x)]) => Instruction::ShrVx(x, x),
+        ("SHR", &[V(x), V(y)]) => Instruction::ShrVx(x, y),
+        ("SHL", &[V(x)]) => Instruction::ShlVx(x, x),
+        ("SHL", &[V(x), V(y)]) => Instruction::ShlVx(x, y),
+        ("RND", &[V(x), Value(nn)]) => Instruction::RndVxByte(x, byte(nn)?),
+        ("DRW", &[V(x), V(y), Value(n)]) => {
+            Instruction::DrwVxVyNib(x, y, nibble(n)?)
+        },
+        ("SKP", &[V(x)]) => Instruction::SkpVx(x),
+        ("SKNP", &[V(x)]) => Instruction::SknpVx(x),
+        ("PLANE", &[Value(n)]) => Instruction::PlaneNib(nibble(n)?),
+        ("AUDIO", []) => Instruction::LdPatternIndex,
+        ("PITCH", &[V(x)]) => Instruction::LdPitchVx(x),
+        _ => {
+            return Err(match forms(mnemonic) {
+                Some(forms) => format!("bad operands for {}; expected {}",
+                                       mnemonic, forms),
+                None => format!("unknown instruction {}", mnemonic),
+            });
+        },
+    };
+    Ok(instruction)
+}
+
+/// The operand forms each mnemonic accepts, for error messages.
+fn forms(mnemonic: &str) -> Option<&'static str> {
+    let forms = match mnemonic {
+        "CLS" | "RET" | "SCR" | "SCL" | "EXIT" | "LOW" | "HIGH" | "AUDIO" => {
+            "no operands"
+        },
+        "SCD" | "PLANE" => "N",
+        "JP" => "ADDR or V0, ADDR",
+        "CALL" => "ADDR",
+        "SE" | "SNE" | "ADD" => "Vx, BYTE or Vx, Vy",
+        "LD" => "one of the forms listed by the disassembler, e.g. \
+                 LD Vx, BYTE or LD I, ADDR",
+        "OR" | "AND" | "XOR" | "SUB" | "SUBN" => "Vx, Vy",
+        "SHR" | "SHL" => "Vx or Vx, Vy",
+        "RND" => "Vx, BYTE",
+        "DRW" => "Vx, Vy, N",
+        "SKP" | "SKNP" | "PITCH" => "Vx",
+        _ => return None,
+    };
+    Some(forms)
+}
+
+/// Check that `value` lies within `min..=max`.
+fn fit(value: i64, min: i64, max: i64, what: &str) -> Result<i64, String> {
+    if value < min || value > max {
+        return Err(format!("value {} doesn't fit in {}", value, what));
+    }
+    Ok(value)
+}
+
+fn byte(value: i64) -> Result<u8, String> {
+    fit(value, -0x80, 0xFF, "a byte").map(|value| value as u8)
+}
+
+fn addr(value: i64) -> Result<u16, String> {
+    fit(value, 0, 0xFFF, "12 bits").map(|value| value as u16)
+}
+
+fn nibble(value: i64) -> Result<u8, String> {
+    fit(value, 0, 0xF, "4 bits").map(|value| value as u8)
+}
+
+/// Parse V0-VF, already in upper case.
+fn register(text: &str) -> Option<u8> {
+    if text.len() == 2 && text.starts_with('V') {
+        u8::from_str_radix(&text[1..], 16).ok()
+    } else {
+        None
+    }
+}
+
+fn parse_number(text: &str) -> Option<i64> {
+    let lower = text.to_lowercase();
+    if let Some(hex) = lower.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16).ok()
+    } else if let Some(binary) = lower.strip_prefix("0b") {
+        i64::from_str_radix(binary, 2).ok()
+    } else {
+        lower.parse().ok()
+    }
+}
+
+fn is_identifier(text: &str) -> bool {
+    let mut chars = text.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_') &&
+        chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+/// Split the first word off `text`.
+fn split_word(text: &str) -> (&str, &str) {
+    let text = text.trim();
+    match text.find(|c: char| c.is_whitespace() || c == '=') {
+        Some(end) => (&text[..end], text[end..].trim()),
+        None => (text, ""),
+    }
+}
+
+/// Split a comma-separated operand list.
+fn operands(text: &str) -> Vec<String> {
+    if text.trim().is_empty() {
+        return Vec::new();
+    }
+    text.split(',').map(|operand| operand.trim().to_string()).collect()
+}
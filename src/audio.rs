@@ -0,0 +1,205 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use timing::TIMER_HZ;
+
+/// Default output sample rate, in Hz.
+pub const DEFAULT_SAMPLE_RATE: u32 = 44100;
+
+/// Default pitch of the buzzer, in Hz.
+pub const DEFAULT_TONE_HZ: f32 = 440.0;
+
+/// Default amplitude of the buzzer, from 0.0 to 1.0.
+pub const DEFAULT_VOLUME: f32 = 0.25;
+
+/// What the buzzer plays while the sound timer is running.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tone {
+    /// The classic fixed-pitch beep.
+    Beep,
+
+    /// An XO-CHIP 128-bit pattern, played one bit per sample at a rate set
+    /// by `pitch` (see `pattern_rate`).
+    Pattern { pattern: [u8; 16], pitch: u8 },
+}
+
+/// Playback rate of an XO-CHIP audio pattern at `pitch`, in bits per second.
+pub fn pattern_rate(pitch: u8) -> f32 {
+    4000.0 * 2.0f32.powf((pitch as f32 - 64.0) / 48.0)
+}
+
+/// A destination for the buzzer's output.
+///
+/// The host calls `frame` once per 60 Hz frame, after `Chip8::run_frame`,
+/// passing `Chip8::tone`. The sink plays the tone for the duration of the
+/// frame, or silence if there is none.
+pub trait AudioSink {
+    /// Output one 60 Hz frame of audio.
+    fn frame(&mut self, tone: Option<Tone>);
+}
+
+/// A square-wave tone generator.
+#[derive(Clone, Debug)]
+pub struct SquareWave {
+    phase: f32,
+    phase_inc: f32,
+    volume: f32,
+}
+
+impl SquareWave {
+    /// Construct a generator producing a `frequency` Hz tone at
+    /// `sample_rate` samples per second.
+    pub fn new(frequency: f32, sample_rate: u32, volume: f32) -> SquareWave {
+        SquareWave {
+            phase: 0.0,
+            phase_inc: frequency / sample_rate as f32,
+            volume,
+        }
+    }
+
+    /// Produce the next sample, from -volume to volume.
+    pub fn next_sample(&mut self) -> f32 {
+        let sample = if self.phase < 0.5 { self.volume } else { -self.volume };
+        self.phase = (self.phase + self.phase_inc) % 1.0;
+        sample
+    }
+
+    /// Fill `out` with consecutive samples.
+    pub fn fill(&mut self, out: &mut [f32]) {
+        for sample in out.iter_mut() {
+            *sample = self.next_sample();
+        }
+    }
+}
+
+/// Generates samples for whichever `Tone` the buzzer is playing.
+#[derive(Clone, Debug)]
+pub struct ToneGenerator {
+    square: SquareWave,
+    sample_rate: u32,
+    volume: f32,
+    position: f32,
+    tone: Option<Tone>,
+}
+
+impl ToneGenerator {
+    /// Construct a silent generator producing `sample_rate` samples per
+    /// second.
+    pub fn new(sample_rate: u32, volume: f32) -> ToneGenerator {
+        ToneGenerator {
+            square: SquareWave::new(DEFAULT_TONE_HZ, sample_rate, volume),
+            sample_rate,
+            volume,
+            position: 0.0,
+            tone: None,
+        }
+    }
+
+    /// Change the tone being played.
+    pub fn set_tone(&mut self, tone: Option<Tone>) {
+        self.tone = tone;
+    }
+
+    /// Produce the next sample, from -volume to volume.
+    pub fn next_sample(&mut self) -> f32 {
+        match self.tone {
+            None => 0.0,
+            Some(Tone::Beep) => self.square.next_sample(),
+            Some(Tone::Pattern { pattern, pitch }) => {
+                let bit = self.position as usize % 128;
+                let high = pattern[bit / 8] & (0x80 >> (bit % 8)) != 0;
+                self.position = (self.position +
+                                 pattern_rate(pitch) / self.sample_rate as f32)
+                                % 128.0;
+                if high { self.volume } else { -self.volume }
+            },
+        }
+    }
+
+    /// Fill `out` with consecutive samples.
+    pub fn fill(&mut self, out: &mut [f32]) {
+        for sample in out.iter_mut() {
+            *sample = self.next_sample();
+        }
+    }
+}
+
+/// An `AudioSink` that records the buzzer into memory and can save it as a
+/// 16-bit mono WAV file. Useful for checking sound output without an audio
+/// device.
+pub struct WavSink {
+    generator: ToneGenerator,
+    sample_rate: u32,
+    samples: Vec<i16>,
+}
+
+impl WavSink {
+    /// Construct a sink recording at `sample_rate` samples per second.
+    pub fn new(sample_rate: u32) -> WavSink {
+        WavSink {
+            generator: ToneGenerator::new(sample_rate, DEFAULT_VOLUME),
+            sample_rate,
+            samples: Vec::new(),
+        }
+    }
+
+    /// The samples recorded so far.
+    pub fn samples(&self) -> &[i16] {
+        &self.samples
+    }
+
+    /// Number of samples making up one 60 Hz frame.
+    pub fn samples_per_frame(&self) -> usize {
+        (self.sample_rate / TIMER_HZ) as usize
+    }
+
+    /// Write the recording to `out` in WAV format.
+    pub fn write<W: Write>(&self, mut out: W) -> io::Result<()> {
+        let data_len = (self.samples.len() * 2) as u32;
+
+        // RIFF header
+        out.write_all(b"RIFF")?;
+        out.write_all(&(36 + data_len).to_le_bytes())?;
+        out.write_all(b"WAVE")?;
+
+        // Format chunk: PCM, mono, 16 bits per sample
+        out.write_all(b"fmt ")?;
+        out.write_all(&16u32.to_le_bytes())?;
+        out.write_all(&1u16.to_le_bytes())?;
+        out.write_all(&1u16.to_le_bytes())?;
+        out.write_all(&self.sample_rate.to_le_bytes())?;
+        out.write_all(&(self.sample_rate * 2).to_le_bytes())?;
+        out.write_all(&2u16.to_le_bytes())?;
+        out.write_all(&16u16.to_le_bytes())?;
+
+        // Data chunk
+        out.write_all(b"data")?;
+        out.write_all(&data_len.to_le_bytes())?;
+        for sample in &self.samples {
+            out.write_all(&sample.to_le_bytes())?;
+        }
+        out.flush()
+    }
+
+    /// Save the recording to the WAV file at `path`.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.write(BufWriter::new(File::create(path)?))
+    }
+}
+
+impl Default for WavSink {
+    fn default() -> WavSink {
+        WavSink::new(DEFAULT_SAMPLE_RATE)
+    }
+}
+
+impl AudioSink for WavSink {
+    fn frame(&mut self, tone: Option<Tone>) {
+        self.generator.set_tone(tone);
+        for _ in 0..self.samples_per_frame() {
+            let sample = self.generator.next_sample() * i16::MAX as f32;
+            self.samples.push(sample as i16);
+        }
+    }
+}
@@ -0,0 +1,392 @@
+extern crate chip8;
+extern crate env_logger;
+extern crate signal_hook;
+
+use std::env;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::process;
+
+use chip8::{config, dump, state, Chip8, Chip8Error};
+use chip8::debugger::repl::Repl;
+use chip8::movie::Movie;
+use signal_hook::consts::SIGINT;
+
+const USAGE: &str = "\
+Usage: chip8-headless [OPTIONS] PROGRAM
+
+Run PROGRAM without a display, then print its frame buffer to stdout and its
+registers to stderr. PROGRAM is a ROM or an Octo cartridge GIF. Known ROMs are
+looked up in a built-in database, and programs are run with the speed, quirks
+and font recommended there or stored in the cartridge, unless given as options.
+
+Options:
+    --frames N           Run for N 60 Hz frames (default 60)
+    --cycles N           Run for N instructions instead of a number of frames
+    --ips N              Execute N instructions per second
+    --no-database        Don't look PROGRAM up in the ROM database
+    --quirks PRESET      Emulate an interpreter: vip, chip48, schip or modern
+    --quirk FLAG=on|off  Override one quirk: shift, load_store, load_store_x,
+                         jump, clip or vf_reset
+    --font FONT          Digits for FX29 and FX30: vip, dream6800, eti660,
+                         fishnchips, schip (default), or a file holding the
+                         80 bytes of the small digits, optionally followed by
+                         the 160 bytes of the large ones
+    --keys SCRIPT        Press and release keys at given frames. SCRIPT is a
+                         comma-separated list of FRAME:+KEY (press) and
+                         FRAME:-KEY (release) events, e.g. 10:+5,12:-5
+    --dump FORMAT        Frame buffer format: ascii (default), pbm or png
+    --output FILE        Write the frame buffer to FILE instead of stdout
+    --load-state FILE    Resume from a state saved for PROGRAM
+    --save-state FILE    Save the machine's state to FILE when it stops
+    --seed N             Seed the random number generator with N
+    --play FILE          Take the keys from the movie FILE, running for its
+                         length unless --frames or --cycles is given
+    --debug              Start in the interactive debugger, reading commands
+                         from stdin. Ctrl-C stops a running step or
+                         continue. The frame buffer and registers are
+                         printed when the debugger quits
+
+Exit status is 0 on success, 1 on bad usage or I/O errors and 2 if the
+program faulted.";
+
+/// Exit status when the emulated program faults.
+const EXIT_FAULT: i32 = 2;
+
+/// How long to run the program for.
+enum Duration {
+    Frames(u64),
+    Cycles(u64),
+}
+
+/// Frame buffer output formats.
+enum DumpFormat {
+    Ascii,
+    Pbm,
+    Png,
+}
+
+/// A scripted key press or release.
+struct KeyEvent {
+    frame: u64,
+    key: u8,
+    pressed: bool,
+}
+
+/// Command line options.
+struct Options {
+    program: String,
+    duration: Option<Duration>,
+    settings: config::Options,
+    keys: Vec<KeyEvent>,
+    dump: DumpFormat,
+    output: Option<String>,
+    load_state: Option<String>,
+    save_state: Option<String>,
+    seed: Option<u64>,
+    play: Option<String>,
+    debug: bool,
+}
+
+/// Parse the command line, exiting with a usage message if it is invalid.
+fn parse_args() -> Options {
+    let mut program = None;
+    let mut duration = None;
+    let mut settings = config::Options::default();
+    let mut keys = Vec::new();
+    let mut dump = DumpFormat::Ascii;
+    let mut output = None;
+    let mut load_state = None;
+    let mut save_state = None;
+    let mut seed = None;
+    let mut play = None;
+    let mut debug = false;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match settings.parse(&arg, &mut args) {
+            Ok(true) => continue,
+            Ok(false) => {},
+            Err(err) => usage_error(&err),
+        }
+        match arg.as_str() {
+            "--frames" => {
+                duration = Some(Duration::Frames(number(&arg, args.next())));
+            },
+            "--cycles" => {
+                duration = Some(Duration::Cycles(number(&arg, args.next())));
+            },
+            "--keys" => {
+                keys = match args.next().map(|script| parse_keys(&script)) {
+                    Some(Ok(keys)) => keys,
+                    Some(Err(err)) => usage_error(&err),
+                    None => usage_error("--keys requires a SCRIPT"),
+                };
+            },
+            "--dump" => {
+                dump = match args.next().as_deref() {
+                    Some("ascii") => DumpFormat::Ascii,
+                    Some("pbm") => DumpFormat::Pbm,
+                    Some("png") => DumpFormat::Png,
+                    _ => usage_error("--dump requires ascii, pbm or png"),
+                };
+            },
+            "--output" => {
+                output = match args.next() {
+                    Some(file) => Some(file),
+                    None => usage_error("--output requires a FILE"),
+                };
+            },
+            "--load-state" => {
+                load_state = match args.next() {
+                    Some(file) => Some(file),
+                    None => usage_error("--load-state requires a FILE"),
+                };
+            },
+            "--save-state" => {
+                save_state = match args.next() {
+                    Some(file) => Some(file),
+                    None => usage_error("--save-state requires a FILE"),
+                };
+            },
+            "--seed" => seed = Some(number(&arg, args.next())),
+            "--play" => {
+                play = match args.next() {
+                    Some(file) => Some(file),
+                    None => usage_error("--play requires a FILE"),
+                };
+            },
+            "--debug" => debug = true,
+            _ if arg.starts_with("--") => {
+                usage_error(&format!("unknown option {}", arg));
+            },
+            _ if program.is_none() => program = Some(arg),
+            _ => usage_error("only one PROGRAM may be given"),
+        }
+    }
+
+    // A movie replays a run from power-on with its own seed
+    if play.is_some() && !keys.is_empty() {
+        usage_error("--play and --keys can't be used together");
+    }
+    if play.is_some() && load_state.is_some() {
+        usage_error("movies can't start from a saved state");
+    }
+    if play.is_some() && seed.is_some() {
+        usage_error("--seed has no effect when playing a movie");
+    }
+
+    // The debugger's commands decide how long the program runs and which
+    // keys are pressed
+    if debug && (play.is_some() || !keys.is_empty() || duration.is_some()) {
+        usage_error("--debug can't be used with --play, --keys, --frames or \
+                     --cycles");
+    }
+
+    let program = match program {
+        Some(program) => program,
+        None => usage_error("no PROGRAM given"),
+    };
+    Options {
+        program,
+        duration,
+        settings,
+        keys,
+        dump,
+        output,
+        load_state,
+        save_state,
+        seed,
+        play,
+        debug,
+    }
+}
+
+/// Parse the numeric value of option `option`.
+fn number<T: std::str::FromStr>(option: &str, value: Option<String>) -> T {
+    match value.and_then(|value| value.parse().ok()) {
+        Some(number) => number,
+        None => usage_error(&format!("{} requires a number", option)),
+    }
+}
+
+/// Parse a `--keys` script into events ordered by frame.
+fn parse_keys(script: &str) -> Result<Vec<KeyEvent>, String> {
+    let mut events = Vec::new();
+    for event in script.split(',').filter(|event| !event.is_empty()) {
+        let bad_event = || format!("bad key event {}; expected FRAME:+KEY or \
+                                    FRAME:-KEY", event);
+        let mut parts = event.splitn(2, ':');
+        let frame = parts.next()
+            .and_then(|frame| frame.parse().ok())
+            .ok_or_else(bad_event)?;
+        let action = parts.next().ok_or_else(bad_event)?;
+        let pressed = match action.chars().next() {
+            Some('+') => true,
+            Some('-') => false,
+            _ => return Err(bad_event()),
+        };
+        let key = u8::from_str_radix(&action[1..], 16)
+            .ok()
+            .filter(|&key| key <= 0xF)
+            .ok_or_else(bad_event)?;
+        events.push(KeyEvent { frame, key, pressed });
+    }
+    events.sort_by_key(|event| event.frame);
+    Ok(events)
+}
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("chip8-headless: {}\n{}", message, USAGE);
+    process::exit(1);
+}
+
+fn fatal(message: &str) -> ! {
+    eprintln!("chip8-headless: {}", message);
+    process::exit(1);
+}
+
+/// Write the frame buffer in the requested format.
+fn write_dump<W: Write>(chip8: &Chip8, format: &DumpFormat,
+                        mut out: W) -> io::Result<()> {
+    let fb = chip8.framebuffer();
+    match *format {
+        DumpFormat::Ascii => {
+            out.write_all(dump::ascii(fb, chip8.width()).as_bytes())?;
+            out.flush()
+        },
+        DumpFormat::Pbm => dump::write_pbm(fb, chip8.width(), chip8.height(), out),
+        DumpFormat::Png => dump::write_png(fb, chip8.width(), chip8.height(), out),
+    }
+}
+
+/// Number of instructions to run for, or `None` if the requested number of
+/// frames at `per_frame` instructions each doesn't fit in 64 bits.
+fn cycle_budget(options: &Options, per_frame: u64,
+                movie: Option<&Movie>) -> Option<u64> {
+    let default_frames = movie.map_or(60, |movie| movie.len() as u64);
+    match options.duration {
+        Some(Duration::Frames(frames)) => frames.checked_mul(per_frame),
+        Some(Duration::Cycles(cycles)) => Some(cycles),
+        None => default_frames.checked_mul(per_frame),
+    }
+}
+
+/// Run the program for `cycles` instructions, applying scripted key events
+/// or the keys recorded in `movie`. Returns the fault that stopped it, if any.
+fn run(chip8: &mut Chip8, options: &Options, cycles: u64,
+       movie: Option<&Movie>) -> Option<Chip8Error> {
+    // Run one instruction at a time so a cycle budget can end mid-frame,
+    // applying key events and ticking the timers on frame boundaries
+    let per_frame = chip8.instructions_per_frame() as u64;
+    let mut keys = options.keys.iter().peekable();
+
+    for cycle in 0..cycles {
+        let frame = cycle / per_frame;
+        if cycle % per_frame == 0 {
+            while let Some(event) = keys.next_if(|event| event.frame <= frame) {
+                if event.pressed {
+                    chip8.press_key(event.key);
+                } else {
+                    chip8.release_key(event.key);
+                }
+            }
+            if let Some(keys) = movie.and_then(|movie| {
+                movie.frame(frame as usize)
+            }) {
+                chip8.set_keypad(&keys);
+            }
+        }
+
+        if let Err(err) = chip8.execute_cycle() {
+            return Some(err);
+        }
+        if chip8.has_exited() {
+            break;
+        }
+
+        if (cycle + 1) % per_frame == 0 {
+            chip8.tick_timers();
+        }
+    }
+    None
+}
+
+fn main() {
+    let options = parse_args();
+
+    env_logger::init().unwrap();
+
+    let mut chip8 = match config::load(&options.program, &options.settings) {
+        Ok((chip8, _)) => chip8,
+        Err(err) => {
+            fatal(&format!("couldn't load {}: {}", options.program, err))
+        },
+    };
+
+    if let Some(seed) = options.seed {
+        chip8.set_seed(seed);
+    }
+
+    let movie = options.play.as_ref().map(|file| {
+        let movie = match Movie::load(file) {
+            Ok(movie) => movie,
+            Err(err) => fatal(&format!("couldn't load movie {}: {}", file, err)),
+        };
+        if let Err(err) = movie.start(&mut chip8) {
+            fatal(&format!("couldn't play movie {}: {}", file, err));
+        }
+        movie
+    });
+
+    if let Some(ref file) = options.load_state {
+        if let Err(err) = state::load_file(&mut chip8, file) {
+            fatal(&format!("couldn't load state {}: {}", file, err));
+        }
+    }
+
+    let fault = if options.debug {
+        let mut repl = Repl::new();
+        let interrupt = repl.debugger().interrupt_flag();
+        if let Err(err) = signal_hook::flag::register(SIGINT, interrupt) {
+            fatal(&format!("couldn't handle SIGINT: {}", err));
+        }
+        let stdin = io::stdin();
+        if let Err(err) = repl.run(&mut chip8, stdin.lock(), io::stdout()) {
+            fatal(&format!("debugger I/O error: {}", err));
+        }
+        None
+    } else {
+        let per_frame = chip8.instructions_per_frame() as u64;
+        let cycles = match cycle_budget(&options, per_frame, movie.as_ref()) {
+            Some(cycles) => cycles,
+            None => usage_error(&format!("--frames is too many at {} \
+                                          instructions per frame",
+                                         per_frame)),
+        };
+        run(&mut chip8, &options, cycles, movie.as_ref())
+    };
+
+    let written = match options.output {
+        Some(ref file) => File::create(file)
+            .and_then(|file| write_dump(&chip8, &options.dump,
+                                        BufWriter::new(file))),
+        None => write_dump(&chip8, &options.dump, io::stdout()),
+    };
+    if let Err(err) = written {
+        fatal(&format!("couldn't write frame buffer: {}", err));
+    }
+
+    if let Some(ref file) = options.save_state {
+        if let Err(err) = state::save_file(&chip8, file) {
+            fatal(&format!("couldn't save state {}: {}", file, err));
+        }
+    }
+
+    eprintln!("{:#?}", chip8);
+
+    if let Some(err) = fault {
+        eprintln!("chip8-headless: {}", err);
+        process::exit(EXIT_FAULT);
+    }
+}
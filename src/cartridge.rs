@@ -0,0 +1,217 @@
+//! Octo cartridges: GIF images carrying a program and its settings.
+//!
+//! Octo shares programs as animated GIFs picturing a cartridge. The payload
+//! is hidden in the low two bits of each pixel's palette index, four pixels
+//! to a byte with the most significant bits first, running on through every
+//! frame. It is a 32-bit big-endian length followed by that many bytes of
+//! UTF-8 JSON, `{"program": ..., "options": {...}}`, where the options hold
+//! the tick rate, colours, font and quirk flags the program was written for.
+//!
+//! Octo stores the program as source code in its own language, which is
+//! compiled with `octo`.
+
+use std::error::Error;
+use std::fmt;
+
+use gif::{ColorOutput, DecodeOptions, DecodingError};
+use serde_json::{self, Value};
+
+use asm::AsmError;
+use chip8::Chip8;
+use font::Font;
+use octo::{self, OctoError};
+use palette::Palette;
+use quirks::Quirks;
+
+/// Bytes a cartridge starts with, one for each version of the GIF format.
+pub const SIGNATURES: &[&[u8]] = &[b"GIF87a", b"GIF89a"];
+
+/// Largest cartridge image accepted, in bytes. Octo's images of the largest
+/// XO-CHIP programs are well under this.
+pub const MAX_SIZE: usize = 4 * 1024 * 1024;
+
+/// An error raised while decoding a cartridge.
+#[derive(Debug)]
+pub enum CartridgeError {
+    /// The image isn't a valid GIF.
+    Gif(DecodingError),
+
+    /// The image is larger than `MAX_SIZE`.
+    TooLarge,
+
+    /// The image is too small to hold the payload it claims to.
+    Truncated,
+
+    /// The payload isn't a JSON object with a program.
+    BadPayload(String),
+
+    /// The program couldn't be compiled.
+    Program(AsmError),
+
+    /// The program uses `feature`, a part of Octo's language that `octo`
+    /// doesn't support, on line `line`.
+    Unsupported { line: usize, feature: String },
+}
+
+impl fmt::Display for CartridgeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CartridgeError::Gif(ref err) => write!(f, "bad GIF: {}", err),
+            CartridgeError::TooLarge => {
+                write!(f, "cartridge is larger than {} bytes", MAX_SIZE)
+            },
+            CartridgeError::Truncated => write!(f, "truncated cartridge"),
+            CartridgeError::BadPayload(ref message) => {
+                write!(f, "bad cartridge payload: {}", message)
+            },
+            CartridgeError::Program(ref err) => {
+                write!(f, "couldn't compile the cartridge's program: {}", err)
+            },
+            CartridgeError::Unsupported { line, ref feature } => {
+                write!(f, "the cartridge's program uses {} on line {}, an \
+                           unsupported Octo feature", feature, line)
+            },
+        }
+    }
+}
+
+impl Error for CartridgeError {}
+
+impl From<DecodingError> for CartridgeError {
+    fn from(err: DecodingError) -> CartridgeError {
+        CartridgeError::Gif(err)
+    }
+}
+
+/// Settings embedded in a cartridge. Anything the cartridge leaves out is
+/// `None`, and the quirk flags default to Octo's.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OctoOptions {
+    pub instructions_per_frame: Option<u32>,
+    pub quirks: Quirks,
+    pub palette: Option<Palette>,
+    pub font: Option<Font>,
+}
+
+impl OctoOptions {
+    /// Read the options object of a cartridge payload.
+    fn from_json(options: &Value) -> OctoOptions {
+        let flag = |name: &str| options[name].as_bool().unwrap_or(false);
+        // Octo's load/store quirk leaves the index register unchanged, the
+        // opposite of `Quirks::load_store`
+        let quirks = Quirks {
+            shift: flag("shiftQuirks"),
+            load_store: !flag("loadStoreQuirks"),
+            load_store_x: false,
+            jump: flag("jumpQuirks"),
+            clip: flag("clipQuirks"),
+            vf_reset: flag("logicQuirks"),
+        };
+
+        let colours: Option<Vec<&str>> = ["backgroundColor", "fillColor",
+                                          "fillColor2", "blendColor"]
+            .iter()
+            .map(|&name| options[name].as_str())
+            .collect();
+        let palette = colours
+            .and_then(|colours| Palette::parse(&colours.join(",")).ok());
+
+        let font = options["fontStyle"].as_str().and_then(|style| {
+            match style {
+                "octo" | "schip" => Some(Font::schip()),
+                "vip" => Some(Font::vip()),
+                "dream_6800" => Some(Font::dream6800()),
+                "eti_660" => Some(Font::eti660()),
+                "fish" => Some(Font::fishnchips()),
+                _ => None,
+            }
+        });
+
+        OctoOptions {
+            instructions_per_frame: options["tickrate"].as_u64()
+                .filter(|&rate| rate > 0 && rate <= u32::MAX as u64)
+                .map(|rate| rate as u32),
+            quirks,
+            palette,
+            font,
+        }
+    }
+
+    /// Configure `chip8` to run the cartridge's program. The palette is left
+    /// to the frontend.
+    pub fn apply(&self, chip8: &mut Chip8) {
+        if let Some(instructions) = self.instructions_per_frame {
+            chip8.set_instructions_per_frame(instructions);
+        }
+        chip8.set_quirks(self.quirks);
+        if let Some(ref font) = self.font {
+            chip8.load_font(font);
+        }
+    }
+}
+
+/// A decoded cartridge.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cartridge {
+    /// Source code of the program.
+    pub program: String,
+    pub options: OctoOptions,
+}
+
+impl Cartridge {
+    /// Decode the cartridge image `bytes`.
+    pub fn decode(bytes: &[u8]) -> Result<Cartridge, CartridgeError> {
+        let mut options = DecodeOptions::new();
+        options.set_color_output(ColorOutput::Indexed);
+        let mut decoder = options.read_info(bytes)?;
+
+        let mut payload = Vec::new();
+        let mut byte = 0x0;
+        let mut bits = 0;
+        while let Some(frame) = decoder.read_next_frame()? {
+            for &pixel in frame.buffer.iter() {
+                byte = byte << 2 | (pixel & 0x3);
+                bits += 2;
+                if bits == 8 {
+                    payload.push(byte);
+                    byte = 0x0;
+                    bits = 0;
+                }
+            }
+        }
+
+        if payload.len() < 4 {
+            return Err(CartridgeError::Truncated);
+        }
+        let len = payload[..4].iter()
+            .fold(0, |len, &byte| len << 8 | byte as usize);
+        let json = payload.get(4..4 + len).ok_or(CartridgeError::Truncated)?;
+        let json: Value = serde_json::from_slice(json)
+            .map_err(|err| CartridgeError::BadPayload(err.to_string()))?;
+
+        let program = match json["program"].as_str() {
+            Some(program) => program.to_string(),
+            None => {
+                let message = "no program".to_string();
+                return Err(CartridgeError::BadPayload(message));
+            },
+        };
+        let options = OctoOptions::from_json(&json["options"]);
+        Ok(Cartridge { program, options })
+    }
+
+    /// Compile the program into a ROM.
+    pub fn rom(&self) -> Result<Vec<u8>, CartridgeError> {
+        octo::compile(&self.program).map_err(|err| match err {
+            OctoError::Syntax(err) => CartridgeError::Program(err),
+            OctoError::Unsupported { line, feature } => {
+                CartridgeError::Unsupported { line, feature }
+            },
+        })
+    }
+}
+
+/// Whether `bytes` look like a cartridge rather than a raw ROM.
+pub fn is_cartridge(bytes: &[u8]) -> bool {
+    SIGNATURES.iter().any(|signature| bytes.starts_with(signature))
+}
@@ -1,16 +1,83 @@
-extern crate log;
-extern crate rand;
-extern crate sdl2;
-
+use audio::Tone;
+use cartridge::{self, Cartridge, CartridgeError, OctoOptions};
+use dump;
+use error::{Chip8Error, RomError};
+use font::{Font, BIG_FONT_SIZE, BIG_GLYPH_SIZE, SMALL_FONT_SIZE,
+           SMALL_GLYPH_SIZE};
+use instruction::Instruction;
+use keypad::{KeyWait, Keypad, NUM_KEYS};
+use quirks::Quirks;
+use rand;
+use rng::Rng;
+use state::{self, Reader, RomHash, StateError, Writer};
+use timing::{self, DEFAULT_INSTRUCTIONS_PER_SECOND};
 use std::fmt;
-use std::error::Error;
 use std::fs::File;
 use std::io::Read;
+use std::path::Path;
+
+/// Size of the address space. XO-CHIP programs can use all 64 KB; classic
+/// programs only address the first 4 KB. The PC and index register wrap
+/// around at the top.
+pub const MEMORY_SIZE: usize = 0x10000;
+
+/// Size of the address space of classic CHIP-8 and SUPER-CHIP programs,
+/// which their ROMs must fit in unless `Chip8::set_extended_memory` is used.
+pub const CLASSIC_MEMORY_SIZE: usize = 0x1000;
+
+/// Address ROMs are loaded at and execution starts from, unless changed with
+/// `Chip8::set_load_address`.
+pub const DEFAULT_LOAD_ADDRESS: u16 = 0x200;
+
+/// Width of the screen in low-resolution mode.
+pub const LORES_WIDTH: usize = 64;
+
+/// Height of the screen in low-resolution mode.
+pub const LORES_HEIGHT: usize = 32;
+
+/// Width of the screen in SUPER-CHIP high-resolution mode.
+pub const HIRES_WIDTH: usize = 128;
+
+/// Height of the screen in SUPER-CHIP high-resolution mode.
+pub const HIRES_HEIGHT: usize = 64;
+
+/// Address of the small 4x5 hex font.
+pub const FONT_START: usize = 0x50;
+
+/// Address of the large 8x10 SUPER-CHIP hex font.
+pub const BIG_FONT_START: usize = 0xA0;
+
+/// Number of SUPER-CHIP RPL user flags.
+pub const NUM_RPL_FLAGS: usize = 16;
+
+/// Number of XO-CHIP bitplanes.
+pub const NUM_PLANES: usize = 2;
+
+/// Size of the XO-CHIP audio pattern buffer, in bytes.
+pub const PATTERN_SIZE: usize = 16;
+
+/// Initial XO-CHIP audio pitch, which plays the pattern at 4000 bits/second.
+pub const DEFAULT_PITCH: u8 = 64;
+
+/// Whether a memory access reads or writes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+/// A range of memory read or written by an instruction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryAccess {
+    pub addr: usize,
+    pub len: usize,
+    pub access: Access,
+}
 
 /// The Chip8
 pub struct Chip8 {
     // Addressable memory
-    memory: [u8; 4096],
+    memory: Vec<u8>,
 
     // Program counter
     pc: u16,
@@ -30,21 +97,69 @@ pub struct Chip8 {
     dt: u8,
     st: u8,
 
-    // Frame buffer
-    pub fb: [u8; 64 * 32],
+    // Frame buffer, sized for the current resolution. Bit N of each pixel is
+    // its value in XO-CHIP bitplane N.
+    fb: Vec<u8>,
+
+    // Bitmask of the XO-CHIP bitplanes selected for drawing
+    planes: u8,
+
+    // SUPER-CHIP high-resolution mode flag
+    hires: bool,
+
+    // Set once the program executes 00FD
+    exited: bool,
+
+    // SUPER-CHIP RPL user flags, saved and restored by FX75/FX85
+    rpl: [u8; NUM_RPL_FLAGS],
+
+    // XO-CHIP audio pattern, once one has been loaded by F002
+    pattern: Option<[u8; PATTERN_SIZE]>,
+
+    // XO-CHIP audio pitch, set by FX3A
+    pitch: u8,
 
     // Redraw flag
-    pub redraw: bool,
+    redraw: bool,
+
+    // Hex keypad
+    keypad: Keypad,
+
+    // Pending FX0A key wait, if the CPU is halted on one
+    key_wait: Option<KeyWait>,
+
+    // Instructions executed per 60 Hz frame by `run_frame`
+    instructions_per_frame: u32,
+
+    // Interpreter behaviours to emulate
+    quirks: Quirks,
+
+    // Memory accessed by the most recently executed instruction
+    accesses: Vec<MemoryAccess>,
 
-    pub keyboard: u8,
+    // Hash of the loaded ROM, which save states are tied to
+    rom_hash: RomHash,
+
+    // Address the ROM is loaded at
+    load_address: u16,
+
+    // Whether ROMs may fill the whole address space rather than the classic
+    // 4 KB
+    extended_memory: bool,
+
+    // Generator for CXNN, and the seed it was last seeded with
+    rng: Rng,
+    seed: u64,
 }
 
 impl Chip8 {
-    /// Construct a new Chip8.
-    pub fn new() -> Chip8 {
+    /// Construct a new Chip8 emulating the given interpreter `quirks`. The
+    /// random number generator is seeded randomly; see `set_seed`.
+    pub fn new(quirks: Quirks) -> Chip8 {
+        let seed = rand::random();
         Chip8 {
-            memory: [0x0; 4096],
-            pc: 0x200,
+            memory: vec![0x0; MEMORY_SIZE],
+            pc: DEFAULT_LOAD_ADDRESS,
             instr: 0x0,
             v: [0x0; 16],
             index: 0x0,
@@ -52,291 +167,899 @@ impl Chip8 {
             sp: 0x0,
             dt: 0x0,
             st: 0x0,
-            fb: [0x0; 64 * 32],
+            fb: vec![0x0; LORES_WIDTH * LORES_HEIGHT],
+            planes: 0x1,
+            hires: false,
+            exited: false,
+            rpl: [0x0; NUM_RPL_FLAGS],
+            pattern: None,
+            pitch: DEFAULT_PITCH,
             redraw: false,
-            keyboard: 0x0,
+            keypad: Keypad::new(),
+            key_wait: None,
+            instructions_per_frame:
+                timing::instructions_per_frame(DEFAULT_INSTRUCTIONS_PER_SECOND),
+            quirks,
+            accesses: Vec::new(),
+            rom_hash: state::hash_rom(&[]),
+            load_address: DEFAULT_LOAD_ADDRESS,
+            extended_memory: false,
+            rng: Rng::new(seed),
+            seed,
         }
     }
 
-    /// Load the font set into memory, starting at address 0x0.
+    /// Load the default font set; see `load_font`.
     pub fn load_font_set(&mut self) {
-        let font_set = [
-            0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
-            0x20, 0x60, 0x20, 0x20, 0x70, // 1
-            0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
-            0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
-            0x90, 0x90, 0xF0, 0x10, 0x10, // 4
-            0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
-            0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
-            0xF0, 0x10, 0x20, 0x40, 0x40, // 7
-            0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
-            0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
-            0xF0, 0x90, 0xF0, 0x90, 0x90, // A
-            0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
-            0xF0, 0x80, 0x80, 0x80, 0xF0, // C
-            0xE0, 0x90, 0x90, 0x90, 0xE0, // D
-            0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
-            0xF0, 0x80, 0xF0, 0x80, 0x80  // F
-        ];
-
-        let font_start = 0x50;
-        for (i, byte) in font_set.bytes().enumerate() {
-            self.memory[font_start + i] = byte.unwrap();
-        }
-    }
-
-    /// Load the contents of `program` into Chip8's memory. 
-    pub fn load_program(&mut self, program: String) {
-        // Attempt to load the program from a file
-        let bin = match File::open(&program) {
-            Ok(bin) => bin,
-            Err(err) => panic!("Couldn't open {}: {}",
-                               program, err.description()),
+        self.load_font(&Font::default());
+    }
+
+    /// Load the small digits of `font` into memory at `FONT_START` and the
+    /// large digits at `BIG_FONT_START`.
+    pub fn load_font(&mut self, font: &Font) {
+        self.memory[FONT_START..FONT_START + SMALL_FONT_SIZE]
+            .copy_from_slice(&font.small);
+        self.memory[BIG_FONT_START..BIG_FONT_START + BIG_FONT_SIZE]
+            .copy_from_slice(&font.big);
+    }
+
+    /// The font in memory, as loaded by `load_font`.
+    pub fn font(&self) -> Font {
+        let small = &self.memory[FONT_START..FONT_START + SMALL_FONT_SIZE];
+        let big = &self.memory[BIG_FONT_START..BIG_FONT_START + BIG_FONT_SIZE];
+        let mut font = Font::default();
+        font.small.copy_from_slice(small);
+        font.big.copy_from_slice(big);
+        font
+    }
+
+    /// Load `rom` into Chip8's memory at the load address and start
+    /// execution there. Fails without touching memory if the ROM is empty or
+    /// doesn't fit below `CLASSIC_MEMORY_SIZE`, or the end of memory with
+    /// `set_extended_memory`.
+    pub fn load_rom(&mut self, rom: &[u8]) -> Result<(), RomError> {
+        let start = self.load_address as usize;
+        let max = self.rom_capacity();
+        if rom.is_empty() {
+            return Err(RomError::Empty);
+        }
+        if rom.len() > max {
+            return Err(RomError::TooLarge { size: rom.len(), max });
+        }
+        // Instructions are two bytes, so this is likely truncated, though
+        // data at the end of a ROM can make it odd legitimately
+        if !rom.len().is_multiple_of(2) {
+            warn!("ROM has an odd length of {} bytes", rom.len());
+        }
+
+        self.memory[start..start + rom.len()].copy_from_slice(rom);
+        self.rom_hash = state::hash_rom(rom);
+        self.pc = self.load_address;
+        Ok(())
+    }
+
+    /// Read a ROM from `reader` and load it as `load_rom` does.
+    ///
+    /// If the input is an Octo cartridge, its program is loaded with extended
+    /// memory, as XO-CHIP programs may need, and the settings it holds are
+    /// applied, then returned so the frontend can apply the ones that concern
+    /// it, such as the palette.
+    pub fn load_rom_from_reader<R: Read>(&mut self, mut reader: R)
+        -> Result<Option<OctoOptions>, RomError> {
+        // Read at most one byte more than fits, to detect oversized ROMs
+        // without reading the whole of an arbitrarily large input, but
+        // enough to recognize a cartridge
+        let max = self.rom_capacity().max(cartridge::SIGNATURES[0].len());
+        let mut bytes = Vec::new();
+        reader.by_ref().take(max as u64 + 1).read_to_end(&mut bytes)?;
+        if !cartridge::is_cartridge(&bytes) {
+            return self.load_rom(&bytes).map(|()| None);
+        }
+
+        // Cartridge images are larger than the programs they hold, so get a
+        // limit of their own
+        let rest = cartridge::MAX_SIZE.saturating_sub(bytes.len());
+        reader.take(rest as u64 + 1).read_to_end(&mut bytes)?;
+        if bytes.len() > cartridge::MAX_SIZE {
+            return Err(CartridgeError::TooLarge.into());
+        }
+
+        let cartridge = Cartridge::decode(&bytes)?;
+        self.extended_memory = true;
+        self.load_rom(&cartridge.rom()?)?;
+        cartridge.options.apply(self);
+        Ok(Some(cartridge.options))
+    }
+
+    /// Read a ROM or Octo cartridge from the file at `path` and load it as
+    /// `load_rom_from_reader` does. Files named `*.xo8` hold XO-CHIP programs
+    /// and are loaded with extended memory.
+    pub fn load_rom_file<P: AsRef<Path>>(&mut self, path: P)
+        -> Result<Option<OctoOptions>, RomError> {
+        let path = path.as_ref();
+        if path.extension().is_some_and(|extension| extension == "xo8") {
+            self.extended_memory = true;
+        }
+        self.load_rom_from_reader(File::open(path)?)
+    }
+
+    /// Whether ROMs may fill the whole address space.
+    pub fn extended_memory(&self) -> bool {
+        self.extended_memory
+    }
+
+    /// Let ROMs fill XO-CHIP's 64 KB address space rather than the 4 KB of
+    /// classic interpreters.
+    pub fn set_extended_memory(&mut self, extended: bool) {
+        self.extended_memory = extended;
+    }
+
+    /// Number of bytes a ROM may take up from the load address.
+    fn rom_capacity(&self) -> usize {
+        let size = if self.extended_memory {
+            MEMORY_SIZE
+        } else {
+            CLASSIC_MEMORY_SIZE
         };
+        size.saturating_sub(self.load_address as usize)
+    }
+
+    /// Address ROMs are loaded at.
+    pub fn load_address(&self) -> u16 {
+        self.load_address
+    }
+
+    /// Load ROMs at `addr` instead of `DEFAULT_LOAD_ADDRESS`, and start
+    /// execution there. Some interpreters, such as the ETI-660's, load
+    /// programs at 0x600.
+    pub fn set_load_address(&mut self, addr: u16) {
+        self.load_address = addr;
+        self.pc = addr;
+    }
+
+    /// Hash of the most recently loaded ROM.
+    pub fn rom_hash(&self) -> &RomHash {
+        &self.rom_hash
+    }
+
+    /// The seed the random number generator was last seeded with.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Reseed the random number generator. Two machines given the same seed,
+    /// ROM and input behave identically.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = Rng::new(seed);
+    }
+
+    /// Snapshot the whole machine as a state file. Quirks and speed are
+    /// settings of the host rather than machine state, so aren't included.
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut out = Writer::new(&self.rom_hash);
+        out.bytes(&self.memory);
+        out.u16(self.pc);
+        out.u16(self.instr);
+        out.bytes(&self.v);
+        out.u16(self.index);
+        for &addr in self.stack.iter() {
+            out.u16(addr);
+        }
+        out.u8(self.sp);
+        out.u8(self.dt);
+        out.u8(self.st);
+        out.bool(self.hires);
+        out.bytes(&self.fb);
+        out.u8(self.planes);
+        out.bool(self.exited);
+        out.bytes(&self.rpl);
+        out.bool(self.pattern.is_some());
+        out.bytes(&self.pattern.unwrap_or([0x0; PATTERN_SIZE]));
+        out.u8(self.pitch);
+        for key in 0..NUM_KEYS as u8 {
+            out.bool(self.keypad.is_pressed(key));
+        }
+        out.bool(self.key_wait.is_some());
+        let wait = self.key_wait.unwrap_or(KeyWait { reg: 0x0, key: None });
+        out.u8(wait.reg);
+        out.bool(wait.key.is_some());
+        out.u8(wait.key.unwrap_or(0x0));
+        out.u64(self.rng.state());
+        out.finish()
+    }
+
+    /// Restore the machine from a state file written by `save_state`.
+    ///
+    /// The state is rejected if it was saved while a different ROM was
+    /// running, and the machine is left untouched if it can't be loaded.
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), StateError> {
+        let mut input = Reader::new(state, &self.rom_hash)?;
+        let mut loaded = Chip8::new(self.quirks);
+        loaded.instructions_per_frame = self.instructions_per_frame;
+        loaded.rom_hash = self.rom_hash;
+        loaded.load_address = self.load_address;
+        loaded.extended_memory = self.extended_memory;
+        loaded.seed = self.seed;
+
+        loaded.memory.copy_from_slice(input.bytes(MEMORY_SIZE)?);
+        loaded.pc = input.u16()?;
+        loaded.instr = input.u16()?;
+        loaded.v.copy_from_slice(input.bytes(16)?);
+        loaded.index = input.u16()?;
+        for addr in loaded.stack.iter_mut() {
+            *addr = input.u16()?;
+        }
+        loaded.sp = input.u8()?;
+        if loaded.sp as usize > loaded.stack.len() {
+            return Err(StateError::Corrupt("stack pointer out of range"));
+        }
+        loaded.dt = input.u8()?;
+        loaded.st = input.u8()?;
+        loaded.set_resolution(input.bool()?);
+        let len = loaded.fb.len();
+        loaded.fb.copy_from_slice(input.bytes(len)?);
+        loaded.planes = input.u8()? & 0x3;
+        loaded.exited = input.bool()?;
+        loaded.rpl.copy_from_slice(input.bytes(NUM_RPL_FLAGS)?);
+        let has_pattern = input.bool()?;
+        let mut pattern = [0x0; PATTERN_SIZE];
+        pattern.copy_from_slice(input.bytes(PATTERN_SIZE)?);
+        loaded.pattern = if has_pattern { Some(pattern) } else { None };
+        loaded.pitch = input.u8()?;
+        for key in 0..NUM_KEYS as u8 {
+            if input.bool()? {
+                loaded.keypad.press(key);
+            }
+        }
+        let waiting = input.bool()?;
+        let reg = input.u8()?;
+        let has_key = input.bool()?;
+        let key = input.u8()?;
+        if reg > 0xF || key > 0xF {
+            return Err(StateError::Corrupt("bad key wait"));
+        }
+        if waiting {
+            let key = if has_key { Some(key) } else { None };
+            loaded.key_wait = Some(KeyWait { reg, key });
+        }
+        loaded.rng = Rng::from_state(input.u64()?)
+            .ok_or(StateError::Corrupt("bad random number generator state"))?;
+        input.finish()?;
+
+        *self = loaded;
+        self.redraw = true;
+        Ok(())
+    }
+
+    /// The frame buffer, one byte per pixel in row-major order. Its size is
+    /// `width() * height()`. Each pixel is a colour index from 0 to 3: bit 0
+    /// is its value in the first bitplane and bit 1 its value in the second,
+    /// which only XO-CHIP programs draw to.
+    pub fn framebuffer(&self) -> &[u8] {
+        &self.fb
+    }
+
+    /// Width of the screen in the current resolution.
+    pub fn width(&self) -> usize {
+        if self.hires { HIRES_WIDTH } else { LORES_WIDTH }
+    }
+
+    /// Height of the screen in the current resolution.
+    pub fn height(&self) -> usize {
+        if self.hires { HIRES_HEIGHT } else { LORES_HEIGHT }
+    }
+
+    /// Whether the SUPER-CHIP high-resolution mode is active.
+    pub fn is_hires(&self) -> bool {
+        self.hires
+    }
+
+    /// Whether the program has exited with 00FD. No further instructions are
+    /// executed once it has.
+    pub fn has_exited(&self) -> bool {
+        self.exited
+    }
+
+    /// The RPL user flags. Hosts can persist these between runs so programs
+    /// that use FX75/FX85 to keep high scores find them again.
+    pub fn rpl_flags(&self) -> &[u8; NUM_RPL_FLAGS] {
+        &self.rpl
+    }
+
+    /// Restore previously persisted RPL user flags.
+    pub fn set_rpl_flags(&mut self, flags: &[u8; NUM_RPL_FLAGS]) {
+        self.rpl = *flags;
+    }
+
+    /// Whether the frame buffer has changed since the last `clear_redraw`.
+    pub fn needs_redraw(&self) -> bool {
+        self.redraw
+    }
+
+    /// Acknowledge that the frontend has drawn the current frame buffer.
+    pub fn clear_redraw(&mut self) {
+        self.redraw = false;
+    }
+
+    /// Press the keypad key `key` (0x0 to 0xF).
+    pub fn press_key(&mut self, key: u8) {
+        if key as usize >= NUM_KEYS {
+            return;
+        }
+        self.keypad.press(key);
+
+        // The first key pressed during an FX0A wait is the one it reports
+        if let Some(ref mut wait) = self.key_wait {
+            if wait.key.is_none() {
+                wait.key = Some(key);
+            }
+        }
+    }
+
+    /// Release the keypad key `key` (0x0 to 0xF).
+    pub fn release_key(&mut self, key: u8) {
+        self.keypad.release(key);
+
+        // Releasing the key pressed during an FX0A wait completes it
+        if let Some(wait) = self.key_wait {
+            if wait.key == Some(key) {
+                self.v[wait.reg as usize] = key;
+                self.key_wait = None;
+                self.pc = self.pc.wrapping_add(0x2);
+            }
+        }
+    }
+
+    /// Press and release keys so the keypad matches `keypad`.
+    pub fn set_keypad(&mut self, keypad: &Keypad) {
+        for key in 0..NUM_KEYS as u8 {
+            match (self.keypad.is_pressed(key), keypad.is_pressed(key)) {
+                (false, true) => self.press_key(key),
+                (true, false) => self.release_key(key),
+                _ => {},
+            }
+        }
+    }
+
+    /// The pending FX0A key wait, if the CPU is halted on one.
+    pub fn key_wait(&self) -> Option<KeyWait> {
+        self.key_wait
+    }
+
+    /// Whether the keypad key `key` is currently held down.
+    pub fn is_key_pressed(&self, key: u8) -> bool {
+        self.keypad.is_pressed(key)
+    }
+
+    /// The current keypad state.
+    pub fn keypad(&self) -> &Keypad {
+        &self.keypad
+    }
+
+    /// The program counter.
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    /// The most recently executed instruction.
+    pub fn instr(&self) -> u16 {
+        self.instr
+    }
+
+    /// The general purpose registers V[0] to V[F].
+    pub fn registers(&self) -> &[u8; 16] {
+        &self.v
+    }
+
+    /// The index register.
+    pub fn index(&self) -> u16 {
+        self.index
+    }
+
+    /// The stack pointer, which is also the current call depth.
+    pub fn sp(&self) -> u8 {
+        self.sp
+    }
+
+    /// The return addresses on the stack, outermost call first.
+    pub fn stack(&self) -> &[u16] {
+        &self.stack[..self.sp as usize]
+    }
+
+    /// The whole address space.
+    pub fn memory(&self) -> &[u8] {
+        &self.memory
+    }
+
+    /// The instruction word at `addr`, or `None` if it lies outside memory.
+    pub fn word_at(&self, addr: u16) -> Option<u16> {
+        let addr = addr as usize;
+        if addr + 1 >= self.memory.len() {
+            return None;
+        }
+        Some((self.memory[addr] as u16) << 8 | self.memory[addr + 1] as u16)
+    }
+
+    /// Memory read or written by the most recently executed instruction,
+    /// other than the instruction itself.
+    pub fn last_memory_accesses(&self) -> &[MemoryAccess] {
+        &self.accesses
+    }
+
+    /// Current value of the delay timer.
+    pub fn delay_timer(&self) -> u8 {
+        self.dt
+    }
+
+    /// Current value of the sound timer.
+    pub fn sound_timer(&self) -> u8 {
+        self.st
+    }
+
+    /// Whether the buzzer should currently be sounding.
+    pub fn sound_active(&self) -> bool {
+        self.st > 0
+    }
 
-        // Copy bytes into Chip8's memory
-        let mut pc_init = self.pc;
-        for byte in bin.bytes() {
-            self.memory[pc_init as usize] = byte.unwrap();
-            pc_init += 1;
+    /// What the buzzer should currently be playing, or `None` if it is
+    /// silent. This is the classic beep unless the program has loaded an
+    /// XO-CHIP audio pattern.
+    pub fn tone(&self) -> Option<Tone> {
+        if !self.sound_active() {
+            return None;
         }
+        Some(match self.pattern {
+            Some(pattern) => Tone::Pattern { pattern, pitch: self.pitch },
+            None => Tone::Beep,
+        })
     }
 
     /// Print the contents of the frame buffer.
     pub fn print_fb(&mut self) {
-        for i in 0..32 {
-            for j in 0..64 {
-                if self.fb[((i * 64) + j) as usize] == 1 {
-                    print!("█");
-                } else {
-                    print!("_");
-                }
-            }
-            println!("");
+        print!("{}", dump::ascii(&self.fb, self.width()));
+    }
+
+    /// The interpreter behaviours being emulated.
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    /// Change the interpreter behaviours being emulated.
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+
+    /// Number of instructions `run_frame` executes per frame.
+    pub fn instructions_per_frame(&self) -> u32 {
+        self.instructions_per_frame
+    }
+
+    /// Set the number of instructions `run_frame` executes per frame.
+    pub fn set_instructions_per_frame(&mut self, instructions: u32) {
+        self.instructions_per_frame = instructions.max(1);
+    }
+
+    /// Set the CPU speed in instructions per second. The timers are
+    /// unaffected and always count down at 60 Hz.
+    pub fn set_instructions_per_second(&mut self, instructions: u32) {
+        self.instructions_per_frame = timing::instructions_per_frame(instructions);
+    }
+
+    /// Emulate one 60 Hz frame: execute `instructions_per_frame` instructions
+    /// and then count the timers down once.
+    ///
+    /// If an instruction faults, the error is returned immediately and the
+    /// timers are not updated.
+    pub fn run_frame(&mut self) -> Result<(), Chip8Error> {
+        for _ in 0..self.instructions_per_frame {
+            self.execute_cycle()?;
+        }
+        self.tick_timers();
+        Ok(())
+    }
+
+    /// Count the delay and sound timers down by one. Call this at 60 Hz when
+    /// driving the CPU with `execute_cycle` rather than `run_frame`.
+    pub fn tick_timers(&mut self) {
+        if self.dt > 0 {
+            self.dt -= 1;
+        }
+        if self.st > 0 {
+            self.st -= 1;
         }
     }
 
-    /// Execute a single instruction, emulating a CPU cycle.
-    pub fn execute_cycle(&mut self) {
-        // Fetch instruction
-        self.instr = (self.memory[self.pc as usize] as u16) << 8 |
-                    self.memory[(self.pc + 1) as usize] as u16;
-        // Get opcode, which is the first byte of the instruction
-        let opcode = self.instr & 0xF000;
+    /// Execute a single instruction, emulating a CPU cycle. The timers are not
+    /// updated; see `tick_timers`.
+    ///
+    /// If the instruction faults, the error is returned and the machine is
+    /// left as it was before the instruction was fetched.
+    pub fn execute_cycle(&mut self) -> Result<(), Chip8Error> {
+        self.accesses.clear();
+
+        // Nothing executes while halted on FX0A or after 00FD
+        if self.key_wait.is_some() || self.exited {
+            return Ok(());
+        }
 
-        match opcode {
-            0x0000 => {
-                match self.instr {
-                    0x00E0 => self.cls(),
-                    0x00EE => self.ret(),
-                    _ => panic!("Unrecognized instruction: {:#06X}",
-                                  self.instr),
-                }
+        // Fetch instruction, wrapping around the top of memory as the PC
+        // does. Four bytes cover F000 NNNN.
+        let mut bytes = [0x0; 4];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = self.memory[self.pc.wrapping_add(i as u16) as usize];
+        }
+        let instr = (bytes[0] as u16) << 8 | bytes[1] as u16;
+        let instruction = match Instruction::decode_bytes(&bytes) {
+            Ok(instruction) => instruction,
+            Err(_) => {
+                return Err(Chip8Error::UnknownOpcode { pc: self.pc, instr });
             },
-            0x1000 => self.jp_addr(),
-            0x2000 => self.call_addr(),
-            0x3000 => self.se_vx_byte(),
-            0x4000 => self.sne_vx_byte(),
-            0x5000 => self.se_vx_vy(),
-            0x6000 => self.ld_vx_byte(),
-            0x7000 => self.add_vx_byte(),
-            0x8000 => {
-                match self.instr & 0x000F {
-                    0x0000 => self.ld_vx_vy(),
-                    0x0001 => self.or_vx_vy(),
-                    0x0002 => self.and_vx_vy(),
-                    0x0003 => self.xor_vx_vy(),
-                    0x0004 => self.add_vx_vy(),
-                    0x0005 => self.sub_vx_vy(),
-                    0x0006 => self.shr_vx(),
-                    0x0007 => self.subn_vx_vy(),
-                    0x000E => self.shl_vx(),
-                    _ => panic!("{:#06X}: Unrecognized instruction",
-                                  self.instr),
-                };
+        };
+        self.instr = instr;
+        debug!("{:#06X}: {}", self.instr, instruction);
+
+        self.execute(instruction)
+    }
+
+    /// Execute a decoded instruction.
+    fn execute(&mut self, instruction: Instruction) -> Result<(), Chip8Error> {
+        match instruction {
+            Instruction::ScdNib(n) => self.scd_nib(n),
+            Instruction::Cls => self.cls(),
+            Instruction::Ret => self.ret()?,
+            Instruction::Scr => self.scr(),
+            Instruction::Scl => self.scl(),
+            Instruction::Exit => self.exit(),
+            Instruction::Low => self.low(),
+            Instruction::High => self.high(),
+            Instruction::JpAddr(addr) => self.jp_addr(addr),
+            Instruction::CallAddr(addr) => self.call_addr(addr)?,
+            Instruction::SeVxByte(x, byte) => self.se_vx_byte(x as usize, byte),
+            Instruction::SneVxByte(x, byte) => {
+                self.sne_vx_byte(x as usize, byte)
             },
-            0x9000 => self.sne_vx_vy(),
-            0xA000 => self.ld_index_addr(),
-            0xB000 => self.jp_v0_addr(),
-            0xC000 => self.rnd_vx_byte(),
-            0xD000 => self.drw_vx_vy_nib(),
-            0xE000 => {
-                match self.instr & 0x00FF {
-                    0x009E => self.skp_vx(),
-                    0x00A1 => self.sknp_vx(),
-                    _ => panic!("{:#06X}: Unrecognized instruction",
-                                  self.instr),
-                }
+            Instruction::SeVxVy(x, y) => self.se_vx_vy(x as usize, y as usize),
+            Instruction::LdIndexVxVy(x, y) => {
+                self.ld_index_vx_vy(x as usize, y as usize)?
             },
-            0xF000 => {
-                match self.instr & 0x00FF {
-                    0x0007 => self.ld_vx_dt(),
-                    0x000A => self.ld_vx_key(),
-                    0x0015 => self.ld_dt_vx(),
-                    0x0018 => self.ld_st_vx(),
-                    0x001E => self.add_index_vx(),
-                    0x0029 => self.ld_index_vx_sprite(),
-                    0x0033 => self.ld_bcd_vx(),
-                    0x0055 => self.ld_index_imm_vx(),
-                    0x0065 => self.ld_vx_index_imm(),
-                    _ => panic!("{:#06X}: Unrecognized instruction",
-                                  self.instr),
-                }
+            Instruction::LdVxVyIndex(x, y) => {
+                self.ld_vx_vy_index(x as usize, y as usize)?
+            },
+            Instruction::LdVxByte(x, byte) => self.ld_vx_byte(x as usize, byte),
+            Instruction::AddVxByte(x, byte) => {
+                self.add_vx_byte(x as usize, byte)
+            },
+            Instruction::LdVxVy(x, y) => self.ld_vx_vy(x as usize, y as usize),
+            Instruction::OrVxVy(x, y) => self.or_vx_vy(x as usize, y as usize),
+            Instruction::AndVxVy(x, y) => self.and_vx_vy(x as usize, y as usize),
+            Instruction::XorVxVy(x, y) => self.xor_vx_vy(x as usize, y as usize),
+            Instruction::AddVxVy(x, y) => self.add_vx_vy(x as usize, y as usize),
+            Instruction::SubVxVy(x, y) => self.sub_vx_vy(x as usize, y as usize),
+            Instruction::ShrVx(x, y) => self.shr_vx(x as usize, y as usize),
+            Instruction::SubnVxVy(x, y) => {
+                self.subn_vx_vy(x as usize, y as usize)
+            },
+            Instruction::ShlVx(x, y) => self.shl_vx(x as usize, y as usize),
+            Instruction::SneVxVy(x, y) => self.sne_vx_vy(x as usize, y as usize),
+            Instruction::LdIndexAddr(addr) => self.ld_index_addr(addr),
+            Instruction::JpV0Addr(addr) => self.jp_v0_addr(addr),
+            Instruction::RndVxByte(x, byte) => self.rnd_vx_byte(x as usize, byte),
+            Instruction::DrwVxVyNib(x, y, n) => {
+                self.drw_vx_vy_nib(x as usize, y as usize, n)?
             },
-            _ => {
-                panic!("{:#06X}: Unrecognized opcode", self.instr);
+            Instruction::SkpVx(x) => self.skp_vx(x as usize),
+            Instruction::SknpVx(x) => self.sknp_vx(x as usize),
+            Instruction::LdIndexLong(addr) => self.ld_index_long(addr),
+            Instruction::PlaneNib(n) => self.plane_nib(n),
+            Instruction::LdPatternIndex => self.ld_pattern_index()?,
+            Instruction::LdVxDt(x) => self.ld_vx_dt(x as usize),
+            Instruction::LdVxKey(x) => self.ld_vx_key(x as usize),
+            Instruction::LdDtVx(x) => self.ld_dt_vx(x as usize),
+            Instruction::LdStVx(x) => self.ld_st_vx(x as usize),
+            Instruction::AddIndexVx(x) => self.add_index_vx(x as usize),
+            Instruction::LdIndexVxSprite(x) => {
+                self.ld_index_vx_sprite(x as usize)
             },
+            Instruction::LdIndexVxBigSprite(x) => {
+                self.ld_index_vx_big_sprite(x as usize)
+            },
+            Instruction::LdBcdVx(x) => self.ld_bcd_vx(x as usize)?,
+            Instruction::LdPitchVx(x) => self.ld_pitch_vx(x as usize),
+            Instruction::LdIndexImmVx(x) => self.ld_index_imm_vx(x as usize)?,
+            Instruction::LdVxIndexImm(x) => self.ld_vx_index_imm(x as usize)?,
+            Instruction::LdRplVx(x) => self.ld_rpl_vx(x as usize),
+            Instruction::LdVxRpl(x) => self.ld_vx_rpl(x as usize),
         }
 
-        // Update timers
-        if self.dt > 0 {
-            self.dt -= 1;
+        Ok(())
+    }
+
+    /// Skip the instruction following the current one. XO-CHIP's F000 NNNN is
+    /// four bytes long, so skipping it advances the PC by an extra word.
+    fn skip_next(&mut self) {
+        let next = self.pc.wrapping_add(0x2);
+        let next_is_long = self.memory[next as usize] == 0xF0 &&
+                           self.memory[next.wrapping_add(0x1) as usize] == 0x00;
+        self.pc = self.pc.wrapping_add(if next_is_long { 0x6 } else { 0x4 });
+    }
+
+    /// Check that `len` bytes starting at `addr` lie within memory and record
+    /// the access for `last_memory_accesses`.
+    fn access_memory(&mut self, addr: usize, len: usize,
+                     access: Access) -> Result<(), Chip8Error> {
+        self.check_memory(addr, len)?;
+        self.accesses.push(MemoryAccess { addr, len, access });
+        Ok(())
+    }
+
+    /// Check that `len` bytes starting at `addr` lie within memory.
+    fn check_memory(&self, addr: usize, len: usize) -> Result<(), Chip8Error> {
+        if addr + len > self.memory.len() {
+            return Err(Chip8Error::MemoryOutOfBounds {
+                pc: self.pc,
+                addr: addr.max(self.memory.len()),
+            });
+        }
+        Ok(())
+    }
+
+    /// Advance the index register past registers 0 to `reg` after FX55 or
+    /// FX65, as the `load_store` quirks dictate.
+    fn advance_index(&mut self, reg: usize) {
+        if self.quirks.load_store {
+            let step = if self.quirks.load_store_x { reg } else { reg + 0x1 };
+            self.index = self.index.wrapping_add(step as u16);
         }
     }
 
     /// Instruction: 0x00E0
     ///
-    /// Clear the display.
+    /// Clear the selected bitplanes of the display.
     fn cls(&mut self) {
-        self.fb = [0; 64 * 32];
-        self.pc += 0x2;
-        debug!("{:#06X}: CLS", self.instr);
+        let planes = self.planes;
+        for pixel in self.fb.iter_mut() {
+            *pixel &= !planes;
+        }
+        self.redraw = true;
+        self.pc = self.pc.wrapping_add(0x2);
+    }
+
+    /// Instruction: 0x00CN
+    ///
+    /// Scroll the selected bitplanes of the display down by N pixels.
+    fn scd_nib(&mut self, rows: u8) {
+        self.scroll(0, rows as isize);
+        self.pc = self.pc.wrapping_add(0x2);
+    }
+
+    /// Instruction: 0x00FB
+    ///
+    /// Scroll the selected bitplanes of the display right by 4 pixels.
+    fn scr(&mut self) {
+        self.scroll(4, 0);
+        self.pc = self.pc.wrapping_add(0x2);
+    }
+
+    /// Instruction: 0x00FC
+    ///
+    /// Scroll the selected bitplanes of the display left by 4 pixels.
+    fn scl(&mut self) {
+        self.scroll(-4, 0);
+        self.pc = self.pc.wrapping_add(0x2);
+    }
+
+    /// Move the selected bitplanes by (`dx`, `dy`) pixels, filling the
+    /// uncovered area with blank pixels.
+    fn scroll(&mut self, dx: isize, dy: isize) {
+        let width = self.width() as isize;
+        let height = self.height() as isize;
+        let planes = self.planes;
+        let old = self.fb.clone();
+
+        for y in 0..height {
+            for x in 0..width {
+                let (src_x, src_y) = (x - dx, y - dy);
+                let moved = if src_x >= 0 && src_x < width &&
+                               src_y >= 0 && src_y < height {
+                    old[(src_y * width + src_x) as usize] & planes
+                } else {
+                    0
+                };
+                let index = (y * width + x) as usize;
+                self.fb[index] = (old[index] & !planes) | moved;
+            }
+        }
+        self.redraw = true;
+    }
+
+    /// Instruction: 0x00FD
+    ///
+    /// Exit the interpreter. The PC is left pointing at this instruction.
+    fn exit(&mut self) {
+        self.exited = true;
+    }
+
+    /// Instruction: 0x00FE
+    ///
+    /// Switch to low-resolution (64x32) mode and clear the display.
+    fn low(&mut self) {
+        self.set_resolution(false);
+        self.pc = self.pc.wrapping_add(0x2);
+    }
+
+    /// Instruction: 0x00FF
+    ///
+    /// Switch to high-resolution (128x64) mode and clear the display.
+    fn high(&mut self) {
+        self.set_resolution(true);
+        self.pc = self.pc.wrapping_add(0x2);
+    }
+
+    /// Switch resolution, resizing and clearing the frame buffer.
+    fn set_resolution(&mut self, hires: bool) {
+        self.hires = hires;
+        self.fb = vec![0x0; self.width() * self.height()];
+        self.redraw = true;
     }
 
     /// Instruction: 0x00EE
     ///
     /// Return from a subroutine.
-    fn ret(&mut self) {
-        self.pc = self.stack[self.sp as usize];
+    fn ret(&mut self) -> Result<(), Chip8Error> {
+        if self.sp == 0 {
+            return Err(Chip8Error::StackUnderflow { pc: self.pc });
+        }
         self.sp -= 0x1;
-        self.pc += 0x2;
-        debug!("{:#06X}: RET", self.instr);
+        self.pc = self.stack[self.sp as usize];
+        self.pc = self.pc.wrapping_add(0x2);
+        Ok(())
     }
 
     /// Instruction: 0x1NNN
     ///
     /// Jump to location 0xNNN.
-    fn jp_addr(&mut self) {
-        self.pc = self.instr & 0x0FFF;
-        debug!("{:#06X}: JP {:#06X}", self.instr, self.instr & 0x0FFF);
+    fn jp_addr(&mut self, addr: u16) {
+        self.pc = addr;
     }
 
     /// Instruction: 0x2NNN
     ///
     /// Call subroutine at 0xNNN.
-    fn call_addr(&mut self) {
-        self.sp += 0x1;
+    fn call_addr(&mut self, addr: u16) -> Result<(), Chip8Error> {
+        if self.sp as usize >= self.stack.len() {
+            return Err(Chip8Error::StackOverflow { pc: self.pc });
+        }
         self.stack[self.sp as usize] = self.pc;
-        self.pc = self.instr & 0x0FFF;
-        debug!("{:#06X}: CALL {:#06X}", self.instr, self.instr & 0x0FFF);
+        self.sp += 0x1;
+        self.pc = addr;
+        Ok(())
     }
 
     /// Instruction: 0x3XNN
     ///
     /// Skip next instruction if v[X] == NN.
-    fn se_vx_byte(&mut self) {
-        let reg = ((self.instr & 0x0F00) >> 8) as usize;
-        let byte = (self.instr & 0x00FF) as u8;
+    fn se_vx_byte(&mut self, reg: usize, byte: u8) {
         if self.v[reg] == byte {
-            self.pc += 0x4;
+            self.skip_next();
         } else {
-            self.pc += 0x2;
+            self.pc = self.pc.wrapping_add(0x2);
         }
-        debug!("{:#06X}: SN V[{:X}], {:#06X}", self.instr, reg, byte);
     }
 
     /// Instruction: 0x4XNN
     ///
     /// Skip next instruction if V[X] != NN.
-    fn sne_vx_byte(&mut self) {
-        let reg = ((self.instr & 0x0F00) >> 8) as usize;
-        let byte = (self.instr & 0x00FF) as u8;
+    fn sne_vx_byte(&mut self, reg: usize, byte: u8) {
         if self.v[reg] != byte {
-            self.pc += 0x4;
+            self.skip_next();
         } else {
-            self.pc += 0x2;
+            self.pc = self.pc.wrapping_add(0x2);
         }
-        debug!("{:#06X}: SNE V[{:X}], {:#06X}", self.instr, reg, byte);
     }
 
     /// Instruction: 0x5XY0
     ///
     /// Skip next instruction if v[X] == v[Y].
-    fn se_vx_vy(&mut self) {
-        let reg_x = ((self.instr & 0x0F00) >> 8) as usize;
-        let reg_y = ((self.instr & 0x00F0) >> 4) as usize;
+    fn se_vx_vy(&mut self, reg_x: usize, reg_y: usize) {
         if self.v[reg_x] == self.v[reg_y] {
-            self.pc += 0x4;
+            self.skip_next();
         } else {
-            self.pc += 0x2;
+            self.pc = self.pc.wrapping_add(0x2);
         }
-        debug!("{:#06X}: SE V[{:X}], V[{:X}]", self.instr, reg_x, reg_y);
+    }
+
+    /// Instruction: 0x5XY2
+    ///
+    /// Store V[X] to V[Y] in memory starting at the address in the index
+    /// register. If X > Y the registers are stored in reverse order. The index
+    /// register is unchanged.
+    fn ld_index_vx_vy(&mut self, reg_x: usize,
+                      reg_y: usize) -> Result<(), Chip8Error> {
+        let regs = register_range(reg_x, reg_y);
+        self.access_memory(self.index as usize, regs.len(), Access::Write)?;
+        for (i, reg) in regs.into_iter().enumerate() {
+            self.memory[(self.index as usize) + i] = self.v[reg];
+        }
+        self.pc = self.pc.wrapping_add(0x2);
+        Ok(())
+    }
+
+    /// Instruction: 0x5XY3
+    ///
+    /// Load V[X] to V[Y] with values from memory starting at the address in
+    /// the index register. If X > Y the registers are loaded in reverse order.
+    /// The index register is unchanged.
+    fn ld_vx_vy_index(&mut self, reg_x: usize,
+                      reg_y: usize) -> Result<(), Chip8Error> {
+        let regs = register_range(reg_x, reg_y);
+        self.access_memory(self.index as usize, regs.len(), Access::Read)?;
+        for (i, reg) in regs.into_iter().enumerate() {
+            self.v[reg] = self.memory[(self.index as usize) + i];
+        }
+        self.pc = self.pc.wrapping_add(0x2);
+        Ok(())
     }
 
     /// Instruction: 0x6XNN
     ///
     /// Load NN into register V[X].
-    fn ld_vx_byte(&mut self) {
-        let reg = ((self.instr & 0x0F00) >> 8) as usize;
-        self.v[reg] = (self.instr & 0x00FF) as u8;
-        self.pc += 2;
-        debug!("{:#06X}: LD V[{:X}], {:#06X}",
-                 self.instr, reg, (self.instr & 0x00FF));
+    fn ld_vx_byte(&mut self, reg: usize, byte: u8) {
+        self.v[reg] = byte;
+        self.pc = self.pc.wrapping_add(0x2);
     }
 
     /// Instruction: 0x7XNN
     ///
-    /// Add V[X] and NN and store the result in V[X].
-    fn add_vx_byte(&mut self) {
-        let reg = ((self.instr & 0x0F00) >> 8) as usize;
-        self.v[reg] += (self.instr & 0x00FF) as u8;
-        self.pc += 0x2;
-        debug!("{:#06X}: ADD V[{:X}], {:#06X}",
-                 self.instr, reg, self.instr & 0x00FF);
+    /// Add V[X] and NN and store the lowest 8 bits of the result in V[X].
+    /// V[F] is unaffected.
+    fn add_vx_byte(&mut self, reg: usize, byte: u8) {
+        self.v[reg] = self.v[reg].wrapping_add(byte);
+        self.pc = self.pc.wrapping_add(0x2);
     }
 
     /// Instruction: 0x8XY0
     ///
     /// Load V[Y] into V[X].
-    fn ld_vx_vy(&mut self) {
-        let reg_x = ((self.instr & 0x0F00) >> 8) as usize;
-        let reg_y = ((self.instr & 0x00F0) >> 4) as usize;
+    fn ld_vx_vy(&mut self, reg_x: usize, reg_y: usize) {
         self.v[reg_x] = self.v[reg_y];
-        self.pc += 0x2;
-        debug!("{:#06X}: LD V[{:X}], V[{:X}]", self.instr, reg_x, reg_y);
+        self.pc = self.pc.wrapping_add(0x2);
     }
 
     /// Instruction: 0x8XY1
     ///
-    /// Take bitwise OR of V[X] and V[Y] and store the result in V[X].
-    fn or_vx_vy(&mut self) {
-        let reg_x = ((self.instr & 0x0F00) >> 8) as usize;
-        let reg_y = ((self.instr & 0x00F0) >> 4) as usize;
+    /// Take bitwise OR of V[X] and V[Y] and store the result in V[X]. With the
+    /// `vf_reset` quirk, V[F] is set to 0.
+    fn or_vx_vy(&mut self, reg_x: usize, reg_y: usize) {
         self.v[reg_x] |= self.v[reg_y];
-        self.pc += 0x2;
-        debug!("{:#06X}: OR V[{:X}], V[{:X}]", self.instr, reg_x, reg_y);
+        if self.quirks.vf_reset {
+            self.v[0xF] = 0x0;
+        }
+        self.pc = self.pc.wrapping_add(0x2);
     }
 
     /// Instruction: 0x8XY2
     ///
-    /// Take bitwise AND of V[X] and V[Y] and store the result in V[X].
-    fn and_vx_vy(&mut self) {
-        let reg_x = ((self.instr & 0x0F00) >> 8) as usize;
-        let reg_y = ((self.instr & 0x00F0) >> 4) as usize;
+    /// Take bitwise AND of V[X] and V[Y] and store the result in V[X]. With the
+    /// `vf_reset` quirk, V[F] is set to 0.
+    fn and_vx_vy(&mut self, reg_x: usize, reg_y: usize) {
         self.v[reg_x] &= self.v[reg_y];
-        self.pc += 0x2;
-        debug!("{:#06X}: AND V[{:X}], V[{:X}]", self.instr, reg_x, reg_y);
+        if self.quirks.vf_reset {
+            self.v[0xF] = 0x0;
+        }
+        self.pc = self.pc.wrapping_add(0x2);
     }
 
     /// Instruction: 0x8XY3
     ///
-    /// Take bitwise XOR of V[X] and V[Y] and store the result in V[X].
-    fn xor_vx_vy(&mut self) {
-        let reg_x = ((self.instr & 0x0F00) >> 8) as usize;
-        let reg_y = ((self.instr & 0x00F0) >> 4) as usize;
+    /// Take bitwise XOR of V[X] and V[Y] and store the result in V[X]. With the
+    /// `vf_reset` quirk, V[F] is set to 0.
+    fn xor_vx_vy(&mut self, reg_x: usize, reg_y: usize) {
         self.v[reg_x] ^= self.v[reg_y];
-        self.pc += 0x2;
-        debug!("{:#06X}: XOR V[{:X}], V[{:X}]", self.instr, reg_x, reg_y);
+        if self.quirks.vf_reset {
+            self.v[0xF] = 0x0;
+        }
+        self.pc = self.pc.wrapping_add(0x2);
     }
 
     /// Instruction: 0x8XY4
@@ -344,300 +1067,375 @@ impl Chip8 {
     /// Add V[X] and V[Y] and store the result in V[X]. Set V[F] to 1 if there
     /// is a carry (i.e. result > 255), otherwise 0. Only the lowest 8 bits are
     /// kept.
-    fn add_vx_vy(&mut self) {
-        let reg_x = ((self.instr & 0x0F00) >> 8) as usize;
-        let reg_y = ((self.instr & 0x00F0) >> 4) as usize;
-        if (self.v[reg_x] as u16) + (self.v[reg_y] as u16) > 0xFF {
-            self.v[reg_x] = 0xFF;
-            self.v[0xF] = 0x1;
-        } else {
-            self.v[reg_x] += self.v[reg_y];
-            self.v[0xF] = 0x0;
-        }
-        self.pc += 0x2;
-        debug!("{:#06X}: ADD V[{:X}], V[{:X}]", self.instr, reg_x, reg_y);
+    ///
+    /// Like the other 8XY_ instructions, V[F] is written last, so the flag
+    /// wins when X is F.
+    fn add_vx_vy(&mut self, reg_x: usize, reg_y: usize) {
+        let (sum, carry) = self.v[reg_x].overflowing_add(self.v[reg_y]);
+        self.v[reg_x] = sum;
+        self.v[0xF] = carry as u8;
+        self.pc = self.pc.wrapping_add(0x2);
     }
 
     /// Instruction: 0x8XY5
     ///
-    /// Subtract V[Y] from V[X] and store the result in V[X]. If V[X] < V[Y],
-    /// set V[F] to 1 and subtract V[X] from V[Y].
-    fn sub_vx_vy(&mut self) {
-        let reg_x = ((self.instr & 0x0F00) >> 8) as usize;
-        let reg_y = ((self.instr & 0x00F0) >> 4) as usize;
-        if self.v[reg_x] - self.v[reg_y] > 0x0 {
-            self.v[reg_x] -= self.v[reg_y]; 
-            self.v[0xF] = 0x0;
-        } else {
-            self.v[reg_x] = self.v[reg_y] - self.v[reg_x];
-            self.v[0xF] = 0x1;
-        }
-        self.pc += 0x2;
-        debug!("{:#06X}: SUB V[{:X}], V[{:X}]", self.instr, reg_x, reg_y);
+    /// Subtract V[Y] from V[X] and store the lowest 8 bits of the result in
+    /// V[X]. Set V[F] to 0 if there is a borrow (i.e. V[X] < V[Y]), otherwise
+    /// 1.
+    fn sub_vx_vy(&mut self, reg_x: usize, reg_y: usize) {
+        let (difference, borrow) =
+            self.v[reg_x].overflowing_sub(self.v[reg_y]);
+        self.v[reg_x] = difference;
+        self.v[0xF] = !borrow as u8;
+        self.pc = self.pc.wrapping_add(0x2);
     }
 
     /// Instruction: 0x8XY6
     ///
-    /// Shift V[X] right by one bit and store the result in V[X]. Store the
-    /// value of the least significant bit of V[X] in V[F] before shifting. The
-    /// value for register Y is unused.
-    fn shr_vx(&mut self) {
-        let reg_x = ((self.instr & 0x0F00) >> 8) as usize;
-        self.v[0xF] = self.v[reg_x] & 0x01;
-        self.v[reg_x] = self.v[reg_x] >> 1;
-        self.pc += 0x2;
-        debug!("{:#06X}: SHR V[{:X}]", self.instr, reg_x);
+    /// Shift V[Y] right by one bit and store the result in V[X]. Store the
+    /// value of the least significant bit of V[Y] in V[F] before shifting.
+    /// With the `shift` quirk, V[X] is shifted instead and V[Y] is unused.
+    fn shr_vx(&mut self, reg_x: usize, reg_y: usize) {
+        let value = if self.quirks.shift { self.v[reg_x] } else { self.v[reg_y] };
+        self.v[reg_x] = value >> 1;
+        self.v[0xF] = value & 0x01;
+        self.pc = self.pc.wrapping_add(0x2);
     }
 
     /// Instruction: 0x8XY7
     ///
-    /// Subtract V[X] from V[Y] and store the result in V[X]. If V[X] < V[Y],
-    /// set V[F] to 1 and subtract V[X] from V[Y].
-    fn subn_vx_vy(&mut self) {
-        let reg_x = ((self.instr & 0x0F00) >> 8) as usize;
-        let reg_y = ((self.instr & 0x00F0) >> 4) as usize;
-        if self.v[reg_y] - self.v[reg_x] > 0x0 {
-            self.v[reg_x] = self.v[reg_y] - self.v[reg_x]; 
-            self.v[0xF] = 0x1;
-        } else {
-            self.v[reg_x] = self.v[reg_x] - self.v[reg_y];
-            self.v[0xF] = 0x0;
-        }
-        self.pc += 0x2;
-        debug!("{:#06X}: SUBN V[{:X}], V[{:X}]", self.instr, reg_x, reg_y);
+    /// Subtract V[X] from V[Y] and store the lowest 8 bits of the result in
+    /// V[X]. Set V[F] to 0 if there is a borrow (i.e. V[Y] < V[X]), otherwise
+    /// 1.
+    fn subn_vx_vy(&mut self, reg_x: usize, reg_y: usize) {
+        let (difference, borrow) =
+            self.v[reg_y].overflowing_sub(self.v[reg_x]);
+        self.v[reg_x] = difference;
+        self.v[0xF] = !borrow as u8;
+        self.pc = self.pc.wrapping_add(0x2);
     }
 
     /// Instruction: 0x8XYE
     ///
-    /// Shift V[X] left by one bit and store the result in V[X]. Store the
-    /// value of the most significant bit of V[X] in V[F] before shifting. The
-    /// value for register Y is unused.
-    fn shl_vx(&mut self) {
-        let reg_x = ((self.instr & 0x0F00) >> 8) as usize;
-        self.v[0xF] = self.v[reg_x] & 0x80;
-        self.v[reg_x] = self.v[reg_x] << 1;
-        self.pc += 0x2;
-        debug!("{:#06X}: SHL V[{:X}]", self.instr, reg_x);
+    /// Shift V[Y] left by one bit and store the result in V[X]. Store the
+    /// value of the most significant bit of V[Y] in V[F] before shifting.
+    /// With the `shift` quirk, V[X] is shifted instead and V[Y] is unused.
+    fn shl_vx(&mut self, reg_x: usize, reg_y: usize) {
+        let value = if self.quirks.shift { self.v[reg_x] } else { self.v[reg_y] };
+        self.v[reg_x] = value << 1;
+        self.v[0xF] = value >> 7;
+        self.pc = self.pc.wrapping_add(0x2);
     }
 
     /// Instruction: 0x9XY0
     ///
     /// Skip next instruction if V[X] != V[Y].
-    fn sne_vx_vy(&mut self) {
-        let reg_x = ((self.instr & 0x0F00) >> 8) as usize;
-        let reg_y = ((self.instr & 0x00F0) >> 4) as usize;
+    fn sne_vx_vy(&mut self, reg_x: usize, reg_y: usize) {
         if self.v[reg_x] != self.v[reg_y] {
-            self.pc += 0x4;
+            self.skip_next();
         } else {
-            self.pc += 0x2;
+            self.pc = self.pc.wrapping_add(0x2);
         }
-        debug!("{:#06X}: SNE V[{:X}], V[{:X}]", self.instr, reg_x, reg_y);
     }
 
     /// Instruction: 0xANNN
     ///
     /// Set index register to 0xNNN.
-    fn ld_index_addr(&mut self) {
-        self.index = self.instr & 0x0FFF;
-        self.pc += 0x2;
-        debug!("{:#06X}: LD index, {:#06X}", self.instr, self.instr & 0x0FFF);
+    fn ld_index_addr(&mut self, addr: u16) {
+        self.index = addr;
+        self.pc = self.pc.wrapping_add(0x2);
     }
 
     /// Instruction: 0xBNNN
     ///
-    /// Jump to location 0xNNN + V[0].
-    fn jp_v0_addr(&mut self) {
-        self.pc = (self.instr & 0x0FFF) + (self.v[0] as u16);
-        debug!("{:#06X}: JP V[0], {:#06X}", self.instr, self.instr & 0x0FFF);
+    /// Jump to location 0xNNN + V[0]. With the `jump` quirk, jump to location
+    /// 0xXNN + V[X] instead.
+    fn jp_v0_addr(&mut self, addr: u16) {
+        let reg = if self.quirks.jump {
+            (addr >> 8) as usize
+        } else {
+            0x0
+        };
+        self.pc = addr + (self.v[reg] as u16);
     }
 
     /// Instruction: 0xCXNN
     ///
     /// Set V[X] to NN AND a random byte.
-    fn rnd_vx_byte(&mut self) {
-        let reg = ((self.instr & 0x0F00) >> 8) as usize;
-        let byte = (self.instr & 0x00FF) as u8;
-        let rand_byte = rand::random::<u8>();
+    fn rnd_vx_byte(&mut self, reg: usize, byte: u8) {
+        let rand_byte = self.rng.next_byte();
         self.v[reg] = rand_byte & byte;
-        self.pc += 0x2;
-        debug!("{:#06X}: RND V[{:X}], {:#06X}", self.instr, reg, byte);
+        self.pc = self.pc.wrapping_add(0x2);
     }
 
     /// Instruction: 0xDXYN
     ///
     /// Draw sprite at coordinates (V[X], V[Y]) with height N and width 8
-    /// pixels. If any pixels are overwritten, set V[F] to 1.
-    fn drw_vx_vy_nib(&mut self) {
-        let reg_x = ((self.instr & 0x0F00) >> 8) as usize;
-        let reg_y = ((self.instr & 0x00F0) >> 4) as usize;
-        let height = (self.instr & 0x000F) as u8;
-        let x_coord = self.v[reg_x as usize] as usize;
-        let y_coord = self.v[reg_y as usize] as usize;
-        let mut pixel: u8;
+    /// pixels. If N is 0, draw a 16x16 SUPER-CHIP sprite stored as two bytes
+    /// per row. If any pixels are overwritten, set V[F] to 1. The coordinates
+    /// wrap around the screen; with the `clip` quirk, the parts of the sprite
+    /// that fall off the edge are clipped, otherwise they wrap as well.
+    ///
+    /// The sprite is drawn to each selected XO-CHIP bitplane in turn, with the
+    /// data for each plane following on from the previous one in memory.
+    fn drw_vx_vy_nib(&mut self, reg_x: usize, reg_y: usize,
+                     height: u8) -> Result<(), Chip8Error> {
+        let (sprite_width, sprite_height) = if height == 0 {
+            (16, 16)
+        } else {
+            (8, height as usize)
+        };
+        let row_bytes = sprite_width / 8;
+        let sprite_bytes = sprite_height * row_bytes;
+        let width = self.width();
+        let screen_height = self.height();
+        let x_coord = (self.v[reg_x] as usize) % width;
+        let y_coord = (self.v[reg_y] as usize) % screen_height;
+        let planes: Vec<u8> = (0..NUM_PLANES as u8)
+            .map(|plane| 1 << plane)
+            .filter(|&bit| self.planes & bit != 0)
+            .collect();
+
+        self.access_memory(self.index as usize, planes.len() * sprite_bytes,
+                           Access::Read)?;
 
         // Clear V[F] before detecting collisions
         self.v[0xF] = 0;
 
         // XOR bytes into framebuffer
-        for y in 0..height {
-            pixel = self.memory[(self.index as usize) + (y as usize)];
-            for x in 0..8 {
-                let mut pixel_index = ((x_coord + (x as usize)) +
-                                   ((y_coord + (y as usize)) * 64)) as usize;
+        for (n, &plane) in planes.iter().enumerate() {
+            let sprite = (self.index as usize) + (n * sprite_bytes);
+            for y in 0..sprite_height {
+                for x in 0..sprite_width {
+                    let pixel = self.memory[sprite + (y * row_bytes) + (x / 8)];
+                    if (pixel & (0x80 >> (x % 8))) == 0 {
+                        continue;
+                    }
 
-                if pixel_index >= 0x800 {
-                    pixel_index %= 0x800;
-                }
+                    let mut fb_x = x_coord + x;
+                    let mut fb_y = y_coord + y;
+                    if fb_x >= width || fb_y >= screen_height {
+                        if self.quirks.clip {
+                            continue;
+                        }
+                        fb_x %= width;
+                        fb_y %= screen_height;
+                    }
+                    let pixel_index = fb_x + (fb_y * width);
 
-                // Check for collision and set V[F] as appropriate
-                if (pixel & (0x80 >> (x as u8))) != 0 {
-                    if self.fb[pixel_index] == 1 {
+                    // Check for collision and set V[F] as appropriate
+                    if self.fb[pixel_index] & plane != 0 {
                         self.v[0xF] = 0x1;
                     }
-                    self.fb[pixel_index] ^= 0x1;
+                    self.fb[pixel_index] ^= plane;
                 }
             }
         }
 
         self.redraw = true;
 
-        self.pc += 0x2;
-        //self.print_fb();
-        debug!("{:#06X}: DRW V[{:X}], V[{:X}], {:#06X}",
-                 self.instr, reg_x, reg_y, height);
+        self.pc = self.pc.wrapping_add(0x2);
+        Ok(())
     }
 
     /// Instruction: 0xEX9E
     ///
     /// Skip next instruction if the key with the value of V[X] is pressed.
-    fn skp_vx(&mut self) {
-        let reg = ((self.instr & 0x0F00) >> 8) as usize;
-        let key = self.v[reg];
-        if key & self.keyboard == key {
-            self.pc += 0x4;
+    fn skp_vx(&mut self, reg: usize) {
+        let key = self.v[reg] & 0xF;
+        if self.keypad.is_pressed(key) {
+            self.skip_next();
         } else {
-            self.pc += 0x2;
+            self.pc = self.pc.wrapping_add(0x2);
         }
-        debug!("{:#06X}: SKP V[{:X}]", self.instr, reg);
     }
 
     /// Instruction: 0xEXA1
     ///
     /// Skip next instruction if the key with the value of V[X] is not pressed.
-    fn sknp_vx(&mut self) {
-        let reg = ((self.instr & 0x0F00) >> 8) as usize;
-        let key = self.v[reg];
-        if key & self.keyboard != key {
-            self.pc += 0x4;
+    fn sknp_vx(&mut self, reg: usize) {
+        let key = self.v[reg] & 0xF;
+        if !self.keypad.is_pressed(key) {
+            self.skip_next();
         } else {
-            self.pc += 0x2;
+            self.pc = self.pc.wrapping_add(0x2);
         }
-        self.pc += 0x2;
-        debug!("{:#06X}: SKNP V[{:X}]", self.instr, reg);
+    }
+
+    /// Instruction: 0xF000 0xNNNN
+    ///
+    /// Set index to the 16-bit address 0xNNNN held in the following word.
+    fn ld_index_long(&mut self, addr: u16) {
+        self.index = addr;
+        self.pc = self.pc.wrapping_add(0x4);
+    }
+
+    /// Instruction: 0xFN01
+    ///
+    /// Select the XO-CHIP bitplanes used by drawing, clearing and scrolling,
+    /// as a bitmask N.
+    fn plane_nib(&mut self, planes: u8) {
+        self.planes = planes & 0x3;
+        self.pc = self.pc.wrapping_add(0x2);
+    }
+
+    /// Instruction: 0xF002
+    ///
+    /// Load the 16-byte XO-CHIP audio pattern buffer from memory starting at
+    /// the address in the index register.
+    fn ld_pattern_index(&mut self) -> Result<(), Chip8Error> {
+        let start = self.index as usize;
+        self.access_memory(start, PATTERN_SIZE, Access::Read)?;
+        let mut pattern = [0x0; PATTERN_SIZE];
+        pattern.copy_from_slice(&self.memory[start..start + PATTERN_SIZE]);
+        self.pattern = Some(pattern);
+        self.pc = self.pc.wrapping_add(0x2);
+        Ok(())
     }
 
     /// Instruction: 0xFX07
     ///
     /// Set delay timer to V[X].
-    fn ld_vx_dt(&mut self) {
-        let reg = ((self.instr & 0x0F00) >> 8) as usize;
+    fn ld_vx_dt(&mut self, reg: usize) {
         self.v[reg] = self.dt;
-        self.pc += 0x2;
-        debug!("{:#06X}: LD V[{:X}], dt", self.instr, reg);
+        self.pc = self.pc.wrapping_add(0x2);
     }
 
     /// Instruction: 0xFX0A
     ///
-    /// Wait for a key press and store the value of the key in V[X].
-    /// TODO: Implement
-    fn ld_vx_key(&mut self) {
-        let reg = ((self.instr & 0x0F00) >> 8) as usize;
-        self.pc += 0x2;
-        debug!("{:#06X}: LD V[{:X}], KEY", self.instr, reg);
+    /// Wait for a key press and store the value of the key in V[X]. Execution
+    /// halts until a key is pressed and released; the PC is advanced past this
+    /// instruction by `release_key` once the wait completes.
+    fn ld_vx_key(&mut self, reg: usize) {
+        self.key_wait = Some(KeyWait { reg: reg as u8, key: None });
     }
 
     /// Instruction: 0xFX15
     ///
     /// Set delay timer to V[X].
-    fn ld_dt_vx(&mut self) {
-        let reg = ((self.instr & 0x0F00) >> 8) as usize;
+    fn ld_dt_vx(&mut self, reg: usize) {
         self.dt = self.v[reg];
-        self.pc += 0x2;
-        debug!("{:#06X}: dt = V[{:X}]", self.instr, reg);
+        self.pc = self.pc.wrapping_add(0x2);
     }
 
     /// Instruction: 0xFX18
     ///
     /// Set sound timer to V[X].
-    fn ld_st_vx(&mut self) {
-        let reg = ((self.instr & 0x0F00) >> 8) as usize;
+    fn ld_st_vx(&mut self, reg: usize) {
         self.st = self.v[reg];
-        self.pc += 0x2;
-        debug!("{:#06X}: st = V[{:X}]", self.instr, reg);
+        self.pc = self.pc.wrapping_add(0x2);
     }
 
     /// Instruction: 0xFX1E
     ///
     /// Add index and V[X] and store the result in index.
-    fn add_index_vx(&mut self) {
-        let reg = ((self.instr & 0x0F00) >> 8) as usize;
-        self.index += self.v[reg] as u16;
-        self.pc += 0x2;
-        debug!("{:#06X}: ADD index, V[{:X}]", self.instr, reg);
+    fn add_index_vx(&mut self, reg: usize) {
+        self.index = self.index.wrapping_add(self.v[reg] as u16);
+        self.pc = self.pc.wrapping_add(0x2);
     }
 
     /// Instruction: 0xFX29
     ///
-    /// Set index to location of sprite for digit V[X].
-    fn ld_index_vx_sprite(&mut self) {
-        let reg = ((self.instr & 0x0F00) >> 8) as usize;
-        self.index = (self.v[reg] * 0x5) as u16;
-        self.pc += 0x2;
-        debug!("{:#06X}: LD index, V[{:X}]", self.instr, reg);
+    /// Set index to location of sprite for digit V[X]. Only the low nibble of
+    /// V[X] is used.
+    fn ld_index_vx_sprite(&mut self, reg: usize) {
+        let digit = (self.v[reg] & 0xF) as usize;
+        self.index = (FONT_START + digit * SMALL_GLYPH_SIZE) as u16;
+        self.pc = self.pc.wrapping_add(0x2);
+    }
+
+    /// Instruction: 0xFX30
+    ///
+    /// Set index to location of the large SUPER-CHIP sprite for digit V[X].
+    fn ld_index_vx_big_sprite(&mut self, reg: usize) {
+        let digit = (self.v[reg] & 0xF) as usize;
+        self.index = (BIG_FONT_START + digit * BIG_GLYPH_SIZE) as u16;
+        self.pc = self.pc.wrapping_add(0x2);
     }
 
     /// Instruction: 0xFX33
     ///
     /// Store the BCD (binary coded decimal) representation of V[X] in memory
     /// locations index, index + 1 and index + 2.
-    fn ld_bcd_vx(&mut self) {
-        let reg = ((self.instr & 0x0F00) >> 8) as usize;
-        let mut value = self.v[reg];
+    fn ld_bcd_vx(&mut self, reg: usize) -> Result<(), Chip8Error> {
+        self.access_memory(self.index as usize, 3, Access::Write)?;
         self.memory[self.index as usize] = self.v[reg] / 100;
         self.memory[(self.index as usize) + 1] = (self.v[reg] / 10) % 10;
         self.memory[(self.index as usize) + 2] = (self.v[reg] % 100) % 10;
-        self.pc += 0x2;
-        debug!("{:#06X}: LD BCD, V[{:X}]", self.instr, reg);
+        self.pc = self.pc.wrapping_add(0x2);
+        Ok(())
+    }
+
+    /// Instruction: 0xFX3A
+    ///
+    /// Set the XO-CHIP audio pitch to V[X].
+    fn ld_pitch_vx(&mut self, reg: usize) {
+        self.pitch = self.v[reg];
+        self.pc = self.pc.wrapping_add(0x2);
     }
 
     /// Instruction: 0xFX55
     ///
     /// Store V[0] to V[X] in memory starting at the address in the index
-    /// register. Set index to index + X + 1.
-    fn ld_index_imm_vx(&mut self) {
-        let reg = ((self.instr & 0x0F00) >> 8) as usize;
+    /// register. With the `load_store` quirk, set index to index + X + 1, or
+    /// index + X with `load_store_x`.
+    fn ld_index_imm_vx(&mut self, reg: usize) -> Result<(), Chip8Error> {
+        self.access_memory(self.index as usize, reg + 1, Access::Write)?;
         for i in 0x0..(reg + 0x1) {
             self.memory[(self.index as usize) + i] = self.v[i];
         }
-        self.index += (reg as u16) + 0x1;
-        self.pc += 0x2;
-        debug!("{:#06X}: LD [index], V[{:X}]", self.instr, reg);
+        self.advance_index(reg);
+        self.pc = self.pc.wrapping_add(0x2);
+        Ok(())
     }
 
     /// Instruction: 0xFX65
     ///
     /// Load V[0] to V[X] with values from memory starting at the address in
-    /// the index register. Set index to index + X + 1.
-    fn ld_vx_index_imm(&mut self) {
-        let reg = ((self.instr & 0x0F00) >> 8) as usize;
+    /// the index register. With the `load_store` quirk, set index to
+    /// index + X + 1, or index + X with `load_store_x`.
+    fn ld_vx_index_imm(&mut self, reg: usize) -> Result<(), Chip8Error> {
+        self.access_memory(self.index as usize, reg + 1, Access::Read)?;
         for i in 0x0..(reg + 0x1) {
             self.v[i] = self.memory[(self.index as usize) + i];
         }
-        self.index += (reg as u16) + 0x1;
-        self.pc += 0x2;
-        debug!("{:#06X}: LD V[{:X}], [index]", self.instr, reg);
+        self.advance_index(reg);
+        self.pc = self.pc.wrapping_add(0x2);
+        Ok(())
+    }
+
+    /// Instruction: 0xFX75
+    ///
+    /// Store V[0] to V[X] in the RPL user flags.
+    fn ld_rpl_vx(&mut self, reg: usize) {
+        self.rpl[..reg + 1].copy_from_slice(&self.v[..reg + 1]);
+        self.pc = self.pc.wrapping_add(0x2);
     }
 
+    /// Instruction: 0xFX85
+    ///
+    /// Load V[0] to V[X] from the RPL user flags.
+    fn ld_vx_rpl(&mut self, reg: usize) {
+        self.v[..reg + 1].copy_from_slice(&self.rpl[..reg + 1]);
+        self.pc = self.pc.wrapping_add(0x2);
+    }
+
+}
+
+/// The registers V[X] to V[Y] named by a 5XY2/5XY3 instruction, in the order
+/// they are transferred.
+fn register_range(reg_x: usize, reg_y: usize) -> Vec<usize> {
+    if reg_x <= reg_y {
+        (reg_x..reg_y + 1).collect()
+    } else {
+        (reg_y..reg_x + 1).rev().collect()
+    }
+}
+
+impl Default for Chip8 {
+    fn default() -> Chip8 {
+        Chip8::new(Quirks::default())
+    }
 }
 
 impl fmt::Debug for Chip8 {
@@ -649,14 +1447,14 @@ impl fmt::Debug for Chip8 {
     v[4]: {:#04X}, v[5]: {:#04X}, v[6]: {:#04X}, v[7]: {:#04X}
     v[8]: {:#04X}, v[9]: {:#04X}, v[A]: {:#04X}, v[B]: {:#04X}
     v[C]: {:#04X}, v[D]: {:#04X}, v[E]: {:#04X}, v[F]: {:#04X}\n
-    dt: {:#06X}\tst: {:#06X}
+    dt: {:#06X}\tst: {:#06X}\tkey_wait: {:?}
 }}", 
                self.pc, self.instr, self.sp, self.index,
                self.v[0x0], self.v[0x1], self.v[0x2], self.v[0x3],
                self.v[0x4], self.v[0x5], self.v[0x6], self.v[0x7],
                self.v[0x8], self.v[0x9], self.v[0xA], self.v[0xB],
                self.v[0xC], self.v[0xD], self.v[0xE], self.v[0xF],
-               self.dt, self.st
+               self.dt, self.st, self.key_wait
         )
     }
 }
@@ -0,0 +1,146 @@
+//! Settings a program is run with, and the options that choose them.
+//!
+//! The speed, quirks, font and palette start from the defaults. A cartridge
+//! then applies those it stores or, for a raw ROM, the ROM database those it
+//! recommends, and options given on the command line override both. `load`
+//! applies them in that order, so every frontend resolves them alike.
+
+use std::path::Path;
+
+use chip8::Chip8;
+use error::RomError;
+use font::Font;
+use palette::Palette;
+use quirks::{self, Quirks};
+use romdb::{Database, RomInfo};
+use timing::DEFAULT_INSTRUCTIONS_PER_SECOND;
+
+/// Command line options that choose a program's settings. Each is `None`
+/// unless given.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Options {
+    pub instructions_per_second: Option<u32>,
+    pub quirks: Option<Quirks>,
+
+    /// Individual quirk flags, as `FLAG=on` or `FLAG=off`, applied over the
+    /// preset or the quirks the program needs.
+    pub quirk_overrides: Vec<String>,
+
+    pub font: Option<Font>,
+    pub palette: Option<Palette>,
+
+    /// Whether to look raw ROMs up in the ROM database.
+    pub use_database: bool,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            instructions_per_second: None,
+            quirks: None,
+            quirk_overrides: Vec::new(),
+            font: None,
+            palette: None,
+            use_database: true,
+        }
+    }
+}
+
+impl Options {
+    /// Parse command line option `option`, taking its value from `args`, if
+    /// it is `--ips`, `--quirks`, `--quirk`, `--font` or `--no-database`.
+    /// Returns whether it was one of them.
+    pub fn parse<I>(&mut self, option: &str,
+                    args: &mut I) -> Result<bool, String>
+        where I: Iterator<Item = String>
+    {
+        match option {
+            "--ips" => {
+                let ips = args.next()
+                    .and_then(|value| value.parse().ok())
+                    .ok_or("--ips requires a number")?;
+                self.instructions_per_second = Some(ips);
+            },
+            "--quirks" => {
+                let preset = args.next()
+                    .and_then(|name| Quirks::preset(&name))
+                    .ok_or_else(|| format!("--quirks requires one of {}",
+                                           quirks::PRESETS.join(", ")))?;
+                self.quirks = Some(preset);
+            },
+            "--quirk" => {
+                let quirk = args.next()
+                    .ok_or("--quirk requires FLAG=on or FLAG=off")?;
+                // Checked now so a bad flag is a usage error
+                Quirks::default().apply_override(&quirk)?;
+                self.quirk_overrides.push(quirk);
+            },
+            "--font" => {
+                let name = args.next().ok_or("--font requires a FONT")?;
+                let font = match Font::preset(&name) {
+                    Some(font) => font,
+                    None => Font::load(&name).map_err(|err| {
+                        format!("--font: couldn't load {}: {}", name, err)
+                    })?,
+                };
+                self.font = Some(font);
+            },
+            "--no-database" => self.use_database = false,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+}
+
+/// Settings the frontend applies itself.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Settings {
+    pub palette: Palette,
+
+    /// What the ROM database knows about the program, if it was looked up.
+    pub info: Option<RomInfo>,
+}
+
+/// Load the ROM or cartridge at `path` into a new machine, configured as
+/// described in the module documentation.
+pub fn load<P: AsRef<Path>>(path: P,
+                            options: &Options)
+                            -> Result<(Chip8, Settings), RomError> {
+    let mut chip8 = Chip8::new(Quirks::default());
+    chip8.set_instructions_per_second(DEFAULT_INSTRUCTIONS_PER_SECOND);
+    chip8.load_font_set();
+    let cartridge = chip8.load_rom_file(path)?;
+
+    let info = if options.use_database && cartridge.is_none() {
+        Database::bundled().lookup(chip8.rom_hash())
+    } else {
+        None
+    };
+    if let Some(ref info) = info {
+        info!("Recognized {} ({})", info.title,
+              info.platform.as_deref().unwrap_or("unknown platform"));
+        for &(ref action, key) in &info.keys {
+            info!("Key {:X}: {}", key, action);
+        }
+        info.apply(&mut chip8);
+    }
+
+    let mut quirks = options.quirks.unwrap_or_else(|| chip8.quirks());
+    for quirk in &options.quirk_overrides {
+        // Already checked by `Options::parse`
+        let _ = quirks.apply_override(quirk);
+    }
+    chip8.set_quirks(quirks);
+    if let Some(ips) = options.instructions_per_second {
+        chip8.set_instructions_per_second(ips);
+    }
+    if let Some(ref font) = options.font {
+        chip8.load_font(font);
+    }
+
+    let palette = options.palette
+        .or_else(|| cartridge.and_then(|octo| octo.palette))
+        .or_else(|| info.as_ref().and_then(|info| info.palette))
+        .unwrap_or_default();
+    Ok((chip8, Settings { palette, info }))
+}
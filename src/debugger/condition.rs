@@ -0,0 +1,137 @@
+use std::convert::TryFrom;
+use std::fmt;
+
+use chip8::Chip8;
+
+/// A machine value a condition can test.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operand {
+    /// General purpose register V[N].
+    V(u8),
+    Index,
+    Pc,
+    Sp,
+    Dt,
+    St,
+}
+
+/// A comparison between an operand and a value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// A register condition such as `V3 == 0x10`, used as a breakpoint.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Condition {
+    pub operand: Operand,
+    pub comparison: Comparison,
+    pub value: u16,
+}
+
+/// Operators in the order they must be searched for, so that `<=` is not
+/// mistaken for `<`.
+const OPERATORS: &[(&str, Comparison)] = &[
+    ("==", Comparison::Eq),
+    ("!=", Comparison::Ne),
+    ("<=", Comparison::Le),
+    (">=", Comparison::Ge),
+    ("<", Comparison::Lt),
+    (">", Comparison::Gt),
+];
+
+impl Condition {
+    /// Parse a condition written as `OPERAND OP VALUE`, where OPERAND is one of
+    /// V0-VF, I, PC, SP, DT or ST, OP is one of `==`, `!=`, `<`, `<=`, `>` or
+    /// `>=`, and VALUE is a number (see `parse_number`).
+    pub fn parse(text: &str) -> Result<Condition, String> {
+        let (pos, op, comparison) = OPERATORS.iter()
+            .filter_map(|&(op, comparison)| {
+                text.find(op).map(|pos| (pos, op, comparison))
+            })
+            .next()
+            .ok_or_else(|| format!("no comparison operator in {}", text))?;
+
+        let lhs = text[..pos].trim();
+        let rhs = text[pos + op.len()..].trim();
+        let operand = parse_operand(lhs)
+            .ok_or_else(|| format!("unknown register {}", lhs))?;
+        let value = parse_number(rhs)
+            .ok_or_else(|| format!("bad value {}", rhs))?;
+
+        Ok(Condition { operand, comparison, value })
+    }
+
+    /// Evaluate the condition against the current machine state.
+    pub fn eval(&self, chip8: &Chip8) -> bool {
+        let actual = match self.operand {
+            Operand::V(reg) => chip8.registers()[reg as usize] as u16,
+            Operand::Index => chip8.index(),
+            Operand::Pc => chip8.pc(),
+            Operand::Sp => chip8.sp() as u16,
+            Operand::Dt => chip8.delay_timer() as u16,
+            Operand::St => chip8.sound_timer() as u16,
+        };
+        match self.comparison {
+            Comparison::Eq => actual == self.value,
+            Comparison::Ne => actual != self.value,
+            Comparison::Lt => actual < self.value,
+            Comparison::Le => actual <= self.value,
+            Comparison::Gt => actual > self.value,
+            Comparison::Ge => actual >= self.value,
+        }
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.operand {
+            Operand::V(reg) => write!(f, "V{:X}", reg)?,
+            Operand::Index => write!(f, "I")?,
+            Operand::Pc => write!(f, "PC")?,
+            Operand::Sp => write!(f, "SP")?,
+            Operand::Dt => write!(f, "DT")?,
+            Operand::St => write!(f, "ST")?,
+        }
+        let op = OPERATORS.iter()
+            .find(|&&(_, comparison)| comparison == self.comparison)
+            .map(|&(op, _)| op)
+            .unwrap_or("?");
+        write!(f, " {} {:#06X}", op, self.value)
+    }
+}
+
+fn parse_operand(text: &str) -> Option<Operand> {
+    let upper = text.to_uppercase();
+    match upper.as_str() {
+        "I" => Some(Operand::Index),
+        "PC" => Some(Operand::Pc),
+        "SP" => Some(Operand::Sp),
+        "DT" => Some(Operand::Dt),
+        "ST" => Some(Operand::St),
+        _ if upper.len() == 2 && upper.starts_with('V') => {
+            u8::from_str_radix(&upper[1..], 16).ok().map(Operand::V)
+        },
+        _ => None,
+    }
+}
+
+/// Parse a number written in decimal, or in hex with a `0x` prefix.
+pub fn parse_number(text: &str) -> Option<u16> {
+    parse_count(text).and_then(|number| u16::try_from(number).ok())
+}
+
+/// Parse a number as `parse_number` does, but up to `u64::MAX`, for
+/// instruction counts.
+pub fn parse_count(text: &str) -> Option<u64> {
+    if text.starts_with("0x") || text.starts_with("0X") {
+        u64::from_str_radix(&text[2..], 16).ok()
+    } else {
+        text.parse().ok()
+    }
+}
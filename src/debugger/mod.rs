@@ -0,0 +1,422 @@
+//! An interactive debugger for the emulator core.
+//!
+//! A `Debugger` drives a `Chip8` one instruction at a time, stopping at PC
+//! breakpoints, memory watchpoints and register conditions. It snapshots the
+//! machine once a frame so execution can be stepped backwards, by restoring
+//! the last snapshot and executing forwards again. The `repl` module puts a
+//! command line on top of it.
+
+use std::collections::{BTreeSet, VecDeque};
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use chip8::{Access, Chip8, MemoryAccess};
+use error::Chip8Error;
+use instruction::Instruction;
+use rewind::Rewind;
+
+pub mod condition;
+pub mod repl;
+
+pub use self::condition::Condition;
+
+/// A range of memory to watch for reads, writes or both.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub addr: usize,
+    pub len: usize,
+    pub read: bool,
+    pub write: bool,
+}
+
+impl Watchpoint {
+    /// Whether `access` touches the watched range in a watched way.
+    fn triggered_by(&self, access: &MemoryAccess) -> bool {
+        let overlaps = access.addr < self.addr + self.len &&
+                       self.addr < access.addr + access.len;
+        let watched = match access.access {
+            Access::Read => self.read,
+            Access::Write => self.write,
+        };
+        overlaps && watched
+    }
+}
+
+impl fmt::Display for Watchpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match (self.read, self.write) {
+            (true, true) => "rw",
+            (true, false) => "r",
+            _ => "w",
+        };
+        write!(f, "{:#06X}+{} ({})", self.addr, self.len, kind)
+    }
+}
+
+/// Why execution stopped.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    /// The requested step completed.
+    Stepped,
+
+    /// The subroutine being run to completion returned.
+    Returned,
+
+    /// The PC reached a breakpoint.
+    Breakpoint(u16),
+
+    /// The instruction at `pc` made a watched memory access.
+    Watchpoint { pc: u16, watchpoint: Watchpoint, access: MemoryAccess },
+
+    /// A register condition became true.
+    Condition(Condition),
+
+    /// The CPU is halted waiting for a key (FX0A).
+    KeyWait,
+
+    /// The program exited (00FD).
+    Exited,
+
+    /// The program faulted.
+    Fault(Chip8Error),
+
+    /// The cycle limit given to the run was reached.
+    CycleLimit,
+
+    /// The run was stopped through `Debugger::interrupt_flag`.
+    Interrupted,
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            StopReason::Stepped => write!(f, "Stepped"),
+            StopReason::Returned => write!(f, "Returned from subroutine"),
+            StopReason::Breakpoint(pc) => write!(f, "Breakpoint at {:#06X}", pc),
+            StopReason::Watchpoint { pc, watchpoint, access } => {
+                let verb = match access.access {
+                    Access::Read => "read",
+                    Access::Write => "wrote",
+                };
+                write!(f, "Watchpoint {}: {:#06X} {} {:#06X}+{}",
+                       watchpoint, pc, verb, access.addr, access.len)
+            },
+            StopReason::Condition(condition) => {
+                write!(f, "Condition {} became true", condition)
+            },
+            StopReason::KeyWait => write!(f, "Waiting for a key press"),
+            StopReason::Exited => write!(f, "Program exited"),
+            StopReason::Fault(err) => write!(f, "Fault: {}", err),
+            StopReason::CycleLimit => write!(f, "Cycle limit reached"),
+            StopReason::Interrupted => write!(f, "Interrupted"),
+        }
+    }
+}
+
+/// Breakpoints, watchpoints and conditions, and the logic for running a
+/// `Chip8` until one of them is hit.
+///
+/// The debugger counts the instructions it executes and ticks the timers once
+/// every `Chip8::instructions_per_frame` of them, so timing behaves as it
+/// would under `Chip8::run_frame`.
+#[derive(Default)]
+pub struct Debugger {
+    breakpoints: BTreeSet<u16>,
+    watchpoints: Vec<Watchpoint>,
+
+    // Conditions and whether each held after the last instruction. A
+    // condition only stops execution when it changes from false to true.
+    conditions: Vec<(Condition, bool)>,
+
+    cycles: u64,
+
+    // Snapshots taken at the start of each frame and whenever the machine
+    // is changed from outside, for `step_back`, and the cycle count each was
+    // taken at, oldest first
+    history: Rewind,
+    history_cycles: VecDeque<u64>,
+
+    // Set from outside, such as by a SIGINT handler, to stop a run
+    interrupt: Arc<AtomicBool>,
+}
+
+impl Debugger {
+    /// Construct a debugger with no breakpoints.
+    pub fn new() -> Debugger {
+        Debugger::default()
+    }
+
+    /// Stop when the PC reaches `addr`.
+    pub fn add_breakpoint(&mut self, addr: u16) {
+        self.breakpoints.insert(addr);
+    }
+
+    /// Remove the breakpoint at `addr`. Returns false if there was none.
+    pub fn remove_breakpoint(&mut self, addr: u16) -> bool {
+        self.breakpoints.remove(&addr)
+    }
+
+    /// Addresses with breakpoints, in ascending order.
+    pub fn breakpoints(&self) -> Vec<u16> {
+        self.breakpoints.iter().cloned().collect()
+    }
+
+    /// Stop after any instruction that makes an access matching
+    /// `watchpoint`.
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) {
+        self.watchpoints.push(watchpoint);
+    }
+
+    /// Remove the `n`th watchpoint. Returns false if there is no such
+    /// watchpoint.
+    pub fn remove_watchpoint(&mut self, n: usize) -> bool {
+        if n < self.watchpoints.len() {
+            self.watchpoints.remove(n);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// The watchpoints, in the order they were added.
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        &self.watchpoints
+    }
+
+    /// Stop when `condition` becomes true.
+    pub fn add_condition(&mut self, condition: Condition, chip8: &Chip8) {
+        self.conditions.push((condition, condition.eval(chip8)));
+    }
+
+    /// Remove the `n`th condition. Returns false if there is no such
+    /// condition.
+    pub fn remove_condition(&mut self, n: usize) -> bool {
+        if n < self.conditions.len() {
+            self.conditions.remove(n);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// The conditions, in the order they were added.
+    pub fn conditions(&self) -> Vec<Condition> {
+        self.conditions.iter().map(|&(condition, _)| condition).collect()
+    }
+
+    /// Remove every breakpoint, watchpoint and condition.
+    pub fn clear(&mut self) {
+        self.breakpoints.clear();
+        self.watchpoints.clear();
+        self.conditions.clear();
+    }
+
+    /// A flag that stops the run in progress when set, for example from a
+    /// signal handler. Runs stop with `StopReason::Interrupted` and clear
+    /// it.
+    pub fn interrupt_flag(&self) -> Arc<AtomicBool> {
+        self.interrupt.clone()
+    }
+
+    /// Number of instructions executed under the debugger.
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    /// Execute a single instruction.
+    pub fn step(&mut self, chip8: &mut Chip8) -> StopReason {
+        self.execute(chip8).unwrap_or(StopReason::Stepped)
+    }
+
+    /// Undo the most recently executed instruction. Returns false if there
+    /// is no history to go back to.
+    pub fn step_back(&mut self, chip8: &mut Chip8) -> bool {
+        if self.history_len() == 0 {
+            return false;
+        }
+        let target = self.cycles - 1;
+
+        // Restore the last snapshot taken at or before the target, keeping
+        // it in the history, then execute up to the target again
+        while self.history_cycles.back().is_some_and(|&cycles| {
+            cycles > target
+        }) {
+            self.history_cycles.pop_back();
+            self.history.pop(chip8);
+        }
+        self.history.pop(chip8);
+        self.history.push(chip8);
+        self.cycles = *self.history_cycles.back().unwrap();
+        while self.cycles < target {
+            let _ = self.advance(chip8);
+        }
+
+        for &mut (condition, ref mut held) in self.conditions.iter_mut() {
+            *held = condition.eval(chip8);
+        }
+        true
+    }
+
+    /// Number of instructions `step_back` can undo.
+    pub fn history_len(&self) -> usize {
+        self.history_cycles.front()
+            .map_or(0, |&oldest| (self.cycles - oldest) as usize)
+    }
+
+    /// Snapshot `chip8` for `step_back`. Call this after changing the machine
+    /// other than through the debugger, for example by pressing a key, so
+    /// that stepping back doesn't execute instructions across the change.
+    pub fn checkpoint(&mut self, chip8: &Chip8) {
+        self.history.push(chip8);
+        self.history_cycles.push_back(self.cycles);
+
+        // Forget the cycle counts of snapshots dropped to stay in budget
+        while self.history_cycles.len() > self.history.len() {
+            self.history_cycles.pop_front();
+        }
+    }
+
+    /// Limit the memory used by the `step_back` history to about `budget`
+    /// bytes.
+    pub fn set_history_budget(&mut self, budget: usize) {
+        self.history.set_budget(budget);
+    }
+
+    /// Execute a single instruction, running a called subroutine to
+    /// completion if the instruction is a CALL.
+    pub fn step_over(&mut self, chip8: &mut Chip8,
+                     limit: Option<u64>) -> StopReason {
+        // The instruction and the return address may wrap around the top
+        // of memory, as execution does
+        let pc = chip8.pc();
+        let bytes: Vec<u8> = (0..2)
+            .map(|i| chip8.memory()[pc.wrapping_add(i) as usize])
+            .collect();
+        let is_call = matches!(Instruction::decode_bytes(&bytes),
+                               Ok(Instruction::CallAddr(_)));
+        if !is_call {
+            return self.step(chip8);
+        }
+
+        let return_pc = pc.wrapping_add(2);
+        let depth = chip8.sp();
+        self.run_until(chip8, limit, |chip8| {
+            if chip8.pc() == return_pc && chip8.sp() == depth {
+                Some(StopReason::Stepped)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Run until the current subroutine returns. Fails if execution is not
+    /// inside a subroutine.
+    pub fn run_to_return(&mut self, chip8: &mut Chip8,
+                         limit: Option<u64>) -> Result<StopReason, String> {
+        let depth = chip8.sp();
+        if depth == 0 {
+            return Err("not in a subroutine".to_string());
+        }
+        Ok(self.run_until(chip8, limit, |chip8| {
+            if chip8.sp() < depth {
+                Some(StopReason::Returned)
+            } else {
+                None
+            }
+        }))
+    }
+
+    /// Run until a breakpoint, watchpoint or condition is hit, or `limit`
+    /// instructions have been executed.
+    pub fn run(&mut self, chip8: &mut Chip8, limit: Option<u64>) -> StopReason {
+        self.run_until(chip8, limit, |_| None)
+    }
+
+    /// Run until `done` returns a reason to stop, or the run is interrupted
+    /// for any of the reasons `run` stops for.
+    fn run_until<F>(&mut self, chip8: &mut Chip8, limit: Option<u64>,
+                    done: F) -> StopReason
+        where F: Fn(&Chip8) -> Option<StopReason> {
+        // An interrupt that arrived while stopped doesn't end this run
+        self.interrupt.store(false, Ordering::SeqCst);
+        let mut executed = 0;
+        loop {
+            if let Some(reason) = self.execute(chip8) {
+                return reason;
+            }
+            if let Some(reason) = done(chip8) {
+                return reason;
+            }
+            if self.breakpoints.contains(&chip8.pc()) {
+                return StopReason::Breakpoint(chip8.pc());
+            }
+
+            executed += 1;
+            if limit.is_some_and(|limit| executed >= limit) {
+                return StopReason::CycleLimit;
+            }
+            if self.interrupt.swap(false, Ordering::SeqCst) {
+                return StopReason::Interrupted;
+            }
+        }
+    }
+
+    /// Execute one instruction, returning the reason to stop if it faulted,
+    /// halted or hit a watchpoint or condition.
+    fn execute(&mut self, chip8: &mut Chip8) -> Option<StopReason> {
+        let pc = chip8.pc();
+        let frame_start = self.cycles
+            .is_multiple_of(chip8.instructions_per_frame() as u64);
+        if self.history_cycles.back() != Some(&self.cycles) && frame_start {
+            self.checkpoint(chip8);
+        }
+
+        if let Err(err) = self.advance(chip8) {
+            return Some(StopReason::Fault(err));
+        }
+
+        for access in chip8.last_memory_accesses() {
+            if let Some(watchpoint) = self.watchpoints.iter()
+                .find(|watchpoint| watchpoint.triggered_by(access)) {
+                return Some(StopReason::Watchpoint {
+                    pc,
+                    watchpoint: *watchpoint,
+                    access: *access,
+                });
+            }
+        }
+
+        let mut hit = None;
+        for &mut (condition, ref mut held) in self.conditions.iter_mut() {
+            let holds = condition.eval(chip8);
+            if holds && !*held && hit.is_none() {
+                hit = Some(StopReason::Condition(condition));
+            }
+            *held = holds;
+        }
+        if hit.is_some() {
+            return hit;
+        }
+
+        if chip8.has_exited() {
+            return Some(StopReason::Exited);
+        }
+        if chip8.key_wait().is_some() {
+            return Some(StopReason::KeyWait);
+        }
+        None
+    }
+
+    /// Execute one instruction and tick the timers if it ends a frame.
+    fn advance(&mut self, chip8: &mut Chip8) -> Result<(), Chip8Error> {
+        let result = chip8.execute_cycle();
+
+        // Count the instruction even if it faulted so timers stay in step
+        self.cycles += 1;
+        if self.cycles.is_multiple_of(chip8.instructions_per_frame() as u64) {
+            chip8.tick_timers();
+        }
+        result
+    }
+}
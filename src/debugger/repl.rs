@@ -0,0 +1,339 @@
+use std::io::{self, BufRead, Write};
+use std::sync::atomic::Ordering;
+
+use chip8::Chip8;
+use dump;
+use instruction::Instruction;
+use super::{Condition, Debugger, StopReason, Watchpoint};
+use super::condition::{parse_count, parse_number};
+
+const PROMPT: &str = "(chip8) ";
+
+const HELP: &str = "\
+Commands:
+    step [N]              Execute N instructions (default 1)
+    next                  Execute one instruction, stepping over CALLs
+    back [N]              Undo the last N instructions (default 1)
+    finish                Run until the current subroutine returns
+    continue [N]          Run until a breakpoint, or for at most N instructions
+    break ADDR            Stop when the PC reaches ADDR
+    delete ADDR           Remove the breakpoint at ADDR
+    watch ADDR [LEN] [r|w|rw]
+                          Stop on reads and/or writes of LEN bytes at ADDR
+                          (default 1 byte, rw)
+    unwatch N             Remove watchpoint N
+    cond EXPR             Stop when EXPR becomes true, e.g. V3 == 0x10.
+                          Registers are V0-VF, I, PC, SP, DT and ST
+    uncond N              Remove condition N
+    info                  List breakpoints, watchpoints and conditions
+    clear                 Remove all breakpoints, watchpoints and conditions
+    regs                  Show the registers
+    mem ADDR [LEN]        Show LEN bytes of memory at ADDR (default 16)
+    fb                    Show the frame buffer
+    press KEY             Press key KEY (0-F)
+    release KEY           Release key KEY (0-F)
+    help                  Show this message
+    quit                  Leave the debugger
+
+Numbers are decimal, or hex with a 0x prefix. An empty line repeats the last
+command.";
+
+/// A line-based command interpreter driving a `Debugger`, in the style of
+/// gdb.
+#[derive(Default)]
+pub struct Repl {
+    debugger: Debugger,
+    last_command: Option<String>,
+}
+
+impl Repl {
+    /// Construct a REPL with a fresh debugger.
+    pub fn new() -> Repl {
+        Repl::default()
+    }
+
+    /// The debugger the commands operate on.
+    pub fn debugger(&mut self) -> &mut Debugger {
+        &mut self.debugger
+    }
+
+    /// Read commands from `input` until it ends or `quit` is entered,
+    /// writing prompts and results to `out`.
+    pub fn run<R: BufRead, W: Write>(&mut self, chip8: &mut Chip8,
+                                     input: R, mut out: W) -> io::Result<()> {
+        show_location(chip8, &mut out)?;
+        write!(out, "{}", PROMPT)?;
+        out.flush()?;
+        for line in input.lines() {
+            if !self.execute(chip8, &line?, &mut out)? {
+                return Ok(());
+            }
+            write!(out, "{}", PROMPT)?;
+            out.flush()?;
+        }
+        writeln!(out)
+    }
+
+    /// Execute one command line. Returns false if the command was `quit`.
+    pub fn execute<W: Write>(&mut self, chip8: &mut Chip8, line: &str,
+                             out: &mut W) -> io::Result<bool> {
+        let line = match line.trim() {
+            "" => match self.last_command.clone() {
+                Some(last) => last,
+                None => return Ok(true),
+            },
+            line => line.to_string(),
+        };
+        self.last_command = Some(line.clone());
+
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or("");
+        let args: Vec<&str> = words.collect();
+
+        match self.command(chip8, command, &args, out) {
+            Ok(keep_going) => Ok(keep_going),
+            Err(Error::Io(err)) => Err(err),
+            Err(Error::Usage(message)) => {
+                writeln!(out, "{}", message)?;
+                Ok(true)
+            },
+        }
+    }
+
+    fn command<W: Write>(&mut self, chip8: &mut Chip8, command: &str,
+                         args: &[&str], out: &mut W) -> Result<bool, Error> {
+        match command {
+            "s" | "step" => {
+                let count = optional_count(args.first(), 1)?;
+                let interrupt = self.debugger.interrupt_flag();
+                interrupt.store(false, Ordering::SeqCst);
+                let mut reason = StopReason::Stepped;
+                for _ in 0..count {
+                    reason = self.debugger.step(chip8);
+                    if reason == StopReason::Stepped &&
+                       interrupt.swap(false, Ordering::SeqCst) {
+                        reason = StopReason::Interrupted;
+                    }
+                    if reason != StopReason::Stepped {
+                        break;
+                    }
+                }
+                show_stop(chip8, &reason, out)?;
+            },
+            "sb" | "back" | "step_back" => {
+                let count = optional_count(args.first(), 1)?;
+                for undone in 0..count {
+                    if !self.debugger.step_back(chip8) {
+                        writeln!(out, "No history before this point; went \
+                                       back {} instructions", undone)?;
+                        break;
+                    }
+                }
+                show_location(chip8, out)?;
+            },
+            "n" | "next" => {
+                let reason = self.debugger.step_over(chip8, None);
+                show_stop(chip8, &reason, out)?;
+            },
+            "f" | "finish" => {
+                let reason = self.debugger.run_to_return(chip8, None)
+                    .map_err(Error::Usage)?;
+                show_stop(chip8, &reason, out)?;
+            },
+            "c" | "continue" => {
+                let limit = match args.first() {
+                    Some(arg) => Some(count(arg)?),
+                    None => None,
+                };
+                let reason = self.debugger.run(chip8, limit);
+                show_stop(chip8, &reason, out)?;
+            },
+            "b" | "break" => {
+                let addr = number(required(args.first(), "break ADDR")?)?;
+                self.debugger.add_breakpoint(addr);
+                writeln!(out, "Breakpoint at {:#06X}", addr)?;
+            },
+            "d" | "delete" => {
+                let addr = number(required(args.first(), "delete ADDR")?)?;
+                if !self.debugger.remove_breakpoint(addr) {
+                    return Err(Error::Usage(
+                        format!("no breakpoint at {:#06X}", addr)));
+                }
+            },
+            "w" | "watch" => {
+                let addr = number(required(args.first(),
+                                           "watch ADDR [LEN] [r|w|rw]")?)?;
+                let len = optional_number(args.get(1), 1)?;
+                let (read, write) = match args.get(2).cloned() {
+                    None | Some("rw") => (true, true),
+                    Some("r") => (true, false),
+                    Some("w") => (false, true),
+                    Some(kind) => {
+                        return Err(Error::Usage(
+                            format!("bad watch kind {}; expected r, w or rw",
+                                    kind)));
+                    },
+                };
+                let watchpoint = Watchpoint {
+                    addr: addr as usize,
+                    len: len as usize,
+                    read,
+                    write,
+                };
+                self.debugger.add_watchpoint(watchpoint);
+                writeln!(out, "Watchpoint {}: {}",
+                         self.debugger.watchpoints().len() - 1, watchpoint)?;
+            },
+            "unwatch" => {
+                let n = number(required(args.first(), "unwatch N")?)?;
+                if !self.debugger.remove_watchpoint(n as usize) {
+                    return Err(Error::Usage(format!("no watchpoint {}", n)));
+                }
+            },
+            "cond" => {
+                let condition = Condition::parse(&args.join(" "))
+                    .map_err(Error::Usage)?;
+                self.debugger.add_condition(condition, chip8);
+                writeln!(out, "Condition {}: {}",
+                         self.debugger.conditions().len() - 1, condition)?;
+            },
+            "uncond" => {
+                let n = number(required(args.first(), "uncond N")?)?;
+                if !self.debugger.remove_condition(n as usize) {
+                    return Err(Error::Usage(format!("no condition {}", n)));
+                }
+            },
+            "i" | "info" => {
+                for addr in self.debugger.breakpoints() {
+                    writeln!(out, "Breakpoint at {:#06X}", addr)?;
+                }
+                for (n, watchpoint) in self.debugger.watchpoints()
+                    .iter().enumerate() {
+                    writeln!(out, "Watchpoint {}: {}", n, watchpoint)?;
+                }
+                for (n, condition) in self.debugger.conditions()
+                    .iter().enumerate() {
+                    writeln!(out, "Condition {}: {}", n, condition)?;
+                }
+            },
+            "clear" => self.debugger.clear(),
+            "r" | "regs" => show_registers(chip8, out)?,
+            "x" | "mem" => {
+                let addr = number(required(args.first(), "mem ADDR [LEN]")?)?;
+                let len = optional_number(args.get(1), 16)?;
+                show_memory(chip8, addr as usize, len as usize, out)?;
+            },
+            "fb" => {
+                write!(out, "{}", dump::ascii(chip8.framebuffer(),
+                                              chip8.width()))?;
+            },
+            "press" | "release" => {
+                let key = required(args.first(), "press KEY")?;
+                let key = u8::from_str_radix(key, 16)
+                    .ok()
+                    .filter(|&key| key <= 0xF)
+                    .ok_or_else(|| Error::Usage(format!("bad key {}", key)))?;
+                if command == "press" {
+                    chip8.press_key(key);
+                } else {
+                    chip8.release_key(key);
+                }
+                self.debugger.checkpoint(chip8);
+            },
+            "h" | "help" => writeln!(out, "{}", HELP)?,
+            "q" | "quit" => return Ok(false),
+            _ => {
+                return Err(Error::Usage(
+                    format!("unknown command {}; try help", command)));
+            },
+        }
+        Ok(true)
+    }
+}
+
+/// Errors from a single command.
+enum Error {
+    /// The command was malformed; report it and carry on.
+    Usage(String),
+
+    /// Output failed; give up.
+    Io(io::Error),
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+fn number(arg: &str) -> Result<u16, Error> {
+    parse_number(arg).ok_or_else(|| Error::Usage(format!("bad number {}", arg)))
+}
+
+fn optional_number(arg: Option<&&str>, default: u16) -> Result<u16, Error> {
+    arg.map_or(Ok(default), |arg| number(arg))
+}
+
+fn count(arg: &str) -> Result<u64, Error> {
+    parse_count(arg).ok_or_else(|| Error::Usage(format!("bad count {}", arg)))
+}
+
+fn optional_count(arg: Option<&&str>, default: u64) -> Result<u64, Error> {
+    arg.map_or(Ok(default), |arg| count(arg))
+}
+
+fn required<'a>(arg: Option<&&'a str>, usage: &str) -> Result<&'a str, Error> {
+    arg.cloned().ok_or_else(|| Error::Usage(format!("usage: {}", usage)))
+}
+
+/// Print the PC and the instruction about to execute.
+fn show_location<W: Write>(chip8: &Chip8, out: &mut W) -> io::Result<()> {
+    // The instruction may wrap around the top of memory, as execution does
+    let pc = chip8.pc();
+    let bytes: Vec<u8> = (0..4)
+        .map(|i| chip8.memory()[pc.wrapping_add(i) as usize])
+        .collect();
+    let word = (bytes[0] as u16) << 8 | bytes[1] as u16;
+    match Instruction::decode_bytes(&bytes) {
+        Ok(instruction) => {
+            writeln!(out, "{:#06X}: {:04X}  {}", pc, word, instruction)
+        },
+        Err(_) => writeln!(out, "{:#06X}: {:04X}  ???", pc, word),
+    }
+}
+
+fn show_stop<W: Write>(chip8: &Chip8, reason: &StopReason,
+                       out: &mut W) -> io::Result<()> {
+    if *reason != StopReason::Stepped {
+        writeln!(out, "{}", reason)?;
+    }
+    show_location(chip8, out)
+}
+
+fn show_registers<W: Write>(chip8: &Chip8, out: &mut W) -> io::Result<()> {
+    for (n, v) in chip8.registers().iter().enumerate() {
+        write!(out, "V{:X}={:02X}{}", n, v, if n % 8 == 7 { "\n" } else { " " })?;
+    }
+    writeln!(out, "I={:04X} PC={:04X} SP={:X} DT={:02X} ST={:02X}",
+             chip8.index(), chip8.pc(), chip8.sp(),
+             chip8.delay_timer(), chip8.sound_timer())?;
+    write!(out, "Stack:")?;
+    for addr in chip8.stack() {
+        write!(out, " {:04X}", addr)?;
+    }
+    writeln!(out)
+}
+
+fn show_memory<W: Write>(chip8: &Chip8, addr: usize, len: usize,
+                         out: &mut W) -> io::Result<()> {
+    let memory = chip8.memory();
+    let end = (addr + len).min(memory.len());
+    for (row, bytes) in memory[addr.min(end)..end].chunks(16).enumerate() {
+        write!(out, "{:04X}:", addr + row * 16)?;
+        for byte in bytes {
+            write!(out, " {:02X}", byte)?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
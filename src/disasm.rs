@@ -0,0 +1,174 @@
+//! A disassembler for CHIP-8 ROMs.
+//!
+//! Decoding every word of a ROM in turn misreads sprite and other data as
+//! instructions, so `disassemble` instead follows the program's control flow
+//! from its entry point. Only bytes reachable as instructions are disassembled;
+//! everything else is listed as data. Jump and call targets get labels, and
+//! the listing is valid input for the assembler.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use instruction::Instruction;
+
+/// Address programs are loaded at and start executing from.
+pub const DEFAULT_ORIGIN: u16 = 0x200;
+
+/// Number of data bytes listed per line.
+const BYTES_PER_LINE: usize = 8;
+
+/// One line of a disassembly.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Line {
+    /// An instruction reached by the flow analysis.
+    Code { addr: u16, instruction: Instruction },
+
+    /// Bytes never reached as an instruction.
+    Data { addr: u16, bytes: Vec<u8> },
+}
+
+impl Line {
+    /// Address of the first byte of the line.
+    pub fn addr(&self) -> u16 {
+        match *self {
+            Line::Code { addr, .. } | Line::Data { addr, .. } => addr,
+        }
+    }
+}
+
+/// A ROM split into code and data, with labels for branch targets.
+#[derive(Clone, Debug)]
+pub struct Disassembly {
+    lines: Vec<Line>,
+    labels: BTreeMap<u16, String>,
+}
+
+impl Disassembly {
+    /// The lines of the listing, in address order.
+    pub fn lines(&self) -> &[Line] {
+        &self.lines
+    }
+
+    /// The label for `addr`, if it is a jump or call target.
+    pub fn label(&self, addr: u16) -> Option<&str> {
+        self.labels.get(&addr).map(|label| label.as_str())
+    }
+}
+
+impl fmt::Display for Disassembly {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for line in &self.lines {
+            if let Some(label) = self.label(line.addr()) {
+                writeln!(f, "{}:", label)?;
+            }
+            match *line {
+                Line::Code { addr, instruction } => {
+                    let text = instruction.mnemonic(|target| {
+                        match self.label(target) {
+                            Some(label) => label.to_string(),
+                            None => format!("{:#06X}", target),
+                        }
+                    });
+                    writeln!(f, "    {:<52}; {:04X}", text, addr)?;
+                },
+                Line::Data { addr, ref bytes } => {
+                    let text = bytes.iter()
+                        .map(|byte| format!("{:#04X}", byte))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    writeln!(f, "    db {:<49}; {:04X}", text, addr)?;
+                },
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Disassemble `rom`, loaded at `origin`, following control flow from the
+/// origin.
+///
+/// Conditional skips continue at both following instructions, calls at both
+/// their target and the next instruction, and `JP V0, addr` at `addr`, which
+/// is usually the start of a jump table. Flow stops at RET, EXIT, an
+/// unrecognized instruction or the end of the ROM.
+pub fn disassemble(rom: &[u8], origin: u16) -> Disassembly {
+    let in_rom = |addr: u16| -> Option<usize> {
+        let offset = (addr as usize).checked_sub(origin as usize)?;
+        if offset < rom.len() { Some(offset) } else { None }
+    };
+
+    // Walk the control flow graph, recording where each instruction starts
+    let mut code = BTreeMap::new();
+    let mut targets = BTreeSet::new();
+    let mut pending = vec![origin];
+    while let Some(addr) = pending.pop() {
+        let offset = match in_rom(addr) {
+            Some(offset) if !code.contains_key(&addr) => offset,
+            _ => continue,
+        };
+        let instruction = match Instruction::decode_bytes(&rom[offset..]) {
+            Ok(instruction) => instruction,
+            Err(_) => continue,
+        };
+        code.insert(addr, instruction);
+
+        let next = addr.wrapping_add(instruction.size() as u16);
+        match instruction {
+            Instruction::Ret | Instruction::Exit => {},
+            Instruction::JpAddr(target) | Instruction::JpV0Addr(target) => {
+                targets.insert(target);
+                pending.push(target);
+            },
+            Instruction::CallAddr(target) => {
+                targets.insert(target);
+                pending.push(target);
+                pending.push(next);
+            },
+            _ if instruction.is_skip() => {
+                // The skipped instruction may itself be four bytes long
+                let after = in_rom(next)
+                    .and_then(|offset| {
+                        Instruction::decode_bytes(&rom[offset..]).ok()
+                    })
+                    .map_or(2, |skipped| skipped.size());
+                pending.push(next);
+                pending.push(next.wrapping_add(after as u16));
+            },
+            _ => pending.push(next),
+        }
+    }
+
+    // Lay the ROM out in address order. Instructions reached at an address
+    // inside one already listed are dropped, as they can't both be shown.
+    let mut lines = Vec::new();
+    let mut offset = 0;
+    while offset < rom.len() {
+        let addr = (origin as usize + offset) as u16;
+        if let Some(&instruction) = code.get(&addr) {
+            lines.push(Line::Code { addr, instruction });
+            offset += instruction.size();
+            continue;
+        }
+
+        // Data runs until the next instruction or label, or the line is full
+        let mut end = offset + 1;
+        while end < rom.len() && end - offset < BYTES_PER_LINE {
+            let end_addr = (origin as usize + end) as u16;
+            if code.contains_key(&end_addr) || targets.contains(&end_addr) {
+                break;
+            }
+            end += 1;
+        }
+        lines.push(Line::Data { addr, bytes: rom[offset..end].to_vec() });
+        offset = end;
+    }
+
+    // Only label targets a line starts at, so every label is defined
+    let labels = lines.iter()
+        .map(|line| line.addr())
+        .filter(|addr| targets.contains(addr))
+        .map(|addr| (addr, format!("L{:04X}", addr)))
+        .collect();
+
+    Disassembly { lines, labels }
+}
@@ -1,28 +1,163 @@
-use sdl2::pixels::Color;
+use sdl2::pixels::{Color, PixelFormatEnum};
 use sdl2::rect::Rect;
-use sdl2::render::Renderer;
+use sdl2::render::{Texture, TextureCreator, WindowCanvas};
+use sdl2::video::{FullscreenType, WindowContext};
 
+use chip8::chip8::{HIRES_HEIGHT, HIRES_WIDTH, LORES_HEIGHT, LORES_WIDTH};
+use chip8::filter::{Filter, FilterMode};
+use chip8::palette::Palette;
+
+/// Size of a low-resolution pixel on screen. High-resolution pixels are
+/// scaled to fit the same window.
 pub const PIXEL_SIZE: u32 = 8;
 
-pub fn render(fb: &[u8], renderer: &mut Renderer) {
-    //renderer.set_draw_color(Color::RGB(0, 0, 0));
-    //renderer.clear();
-    for (i, val) in fb.iter().enumerate() {
-        let x = ((i as i32) % 64) * (PIXEL_SIZE as i32);
-        let y = ((i as i32) / 64) * (PIXEL_SIZE as i32);
-
-        if *val == 0 {
-            renderer.set_draw_color(Color::RGB(0, 0, 0));
-        } else {
-            renderer.set_draw_color(Color::RGB(255, 255, 255));
+/// Initial window size.
+pub const WINDOW_WIDTH: u32 = LORES_WIDTH as u32 * PIXEL_SIZE;
+pub const WINDOW_HEIGHT: u32 = LORES_HEIGHT as u32 * PIXEL_SIZE;
+
+/// Bytes per texel of the streaming texture.
+const BYTES_PER_TEXEL: usize = 3;
+
+/// How the screen is fitted to the window. Either way it is centered, with
+/// borders in the background colour filling the rest of the window.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scaling {
+    /// Scale by the largest whole number that fits, so every CHIP-8 pixel is
+    /// the same size.
+    Integer,
+
+    /// Scale as large as fits while keeping the 2:1 aspect ratio.
+    Aspect,
+}
+
+/// Draws the frame buffer to a window through a streaming texture.
+///
+/// The texture is always high-resolution sized; low-resolution frame buffers
+/// are uploaded with each pixel doubled. `update` uploads a new frame buffer
+/// and `present` draws the texture scaled to the window, so the window is
+/// redrawn with a single copy however often the program draws.
+pub struct Display<'a> {
+    canvas: WindowCanvas,
+    texture: Texture<'a>,
+    scaling: Scaling,
+    palette: Palette,
+    filter: Filter,
+
+    // Size of the most recently uploaded frame buffer
+    width: usize,
+    height: usize,
+
+    // Staging buffer for texture uploads
+    texels: Vec<u8>,
+}
+
+impl<'a> Display<'a> {
+    /// Construct a display drawing to `canvas` in the colours of `palette`,
+    /// passed through `filter`, with its texture made by `creator`.
+    pub fn new(canvas: WindowCanvas,
+               creator: &'a TextureCreator<WindowContext>,
+               scaling: Scaling,
+               palette: Palette,
+               filter: Filter) -> Result<Display<'a>, String> {
+        let texture = creator.create_texture_streaming(PixelFormatEnum::RGB24,
+                                                       HIRES_WIDTH as u32,
+                                                       HIRES_HEIGHT as u32)
+            .map_err(|err| err.to_string())?;
+        Ok(Display {
+            canvas,
+            texture,
+            scaling,
+            palette,
+            filter,
+            width: LORES_WIDTH,
+            height: LORES_HEIGHT,
+            texels: vec![0x0; HIRES_WIDTH * HIRES_HEIGHT * BYTES_PER_TEXEL],
+        })
+    }
+
+    /// Upload a `width` by `height` frame buffer to the texture.
+    ///
+    /// With a filter other than `FilterMode::Off`, this should be called
+    /// every frame, even if the frame buffer hasn't changed.
+    pub fn update(&mut self, fb: &[u8], width: usize, height: usize) {
+        self.width = width;
+        self.height = height;
+
+        let colours = self.filter.apply(fb, &self.palette);
+        let row_bytes = HIRES_WIDTH * BYTES_PER_TEXEL;
+        for (y, row) in self.texels.chunks_mut(row_bytes).enumerate() {
+            let fb_row = y * height / HIRES_HEIGHT * width;
+            for (x, texel) in row.chunks_mut(BYTES_PER_TEXEL).enumerate() {
+                texel.copy_from_slice(&colours[fb_row + x * width /
+                                                HIRES_WIDTH]);
+            }
         }
 
-        let pixel = Rect::new(x, y, PIXEL_SIZE, PIXEL_SIZE);
+        if let Err(err) = self.texture.update(None, &self.texels, row_bytes) {
+            debug!("Couldn't update texture: {}", err);
+        }
+    }
+
+    /// Change the colours frame buffers are shown in. Takes effect on the
+    /// next `update`.
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+    }
+
+    /// Whether `update` must be called every frame for the filter to
+    /// animate.
+    pub fn is_filtered(&self) -> bool {
+        self.filter.mode() != FilterMode::Off
+    }
 
-        match renderer.fill_rect(pixel) {
-            Ok(_) => { },
-            Err(err) => debug!("Couldn't fill pixel: {}", err),
+    /// Draw the most recently uploaded frame buffer to the window.
+    pub fn present(&mut self) {
+        let [r, g, b] = self.palette.colour(0);
+        self.canvas.set_draw_color(Color::RGB(r, g, b));
+        self.canvas.clear();
+        let target = self.target();
+        if let Err(err) = self.canvas.copy(&self.texture, None, target) {
+            debug!("Couldn't copy texture: {}", err);
         }
+        self.canvas.present();
+    }
+
+    /// Switch between windowed and fullscreen.
+    pub fn toggle_fullscreen(&mut self) {
+        let window = self.canvas.window_mut();
+        let state = match window.fullscreen_state() {
+            FullscreenType::Off => FullscreenType::Desktop,
+            _ => FullscreenType::Off,
+        };
+        if let Err(err) = window.set_fullscreen(state) {
+            warn!("Couldn't toggle fullscreen: {}", err);
+        }
+    }
+
+    /// The part of the window the screen is drawn to.
+    fn target(&self) -> Rect {
+        let (window_width, window_height) = match self.canvas.output_size() {
+            Ok(size) => size,
+            Err(err) => {
+                debug!("Couldn't get canvas size: {}", err);
+                (WINDOW_WIDTH, WINDOW_HEIGHT)
+            },
+        };
+
+        let (width, height) = (self.width as u32, self.height as u32);
+        let scale = (window_width / width).min(window_height / height);
+        let (target_width, target_height) = match self.scaling {
+            // Fall back to aspect scaling if the window is too small to
+            // show every pixel
+            Scaling::Integer if scale >= 1 => (width * scale, height * scale),
+            _ => {
+                let target_width = window_width.min(window_height * width /
+                                                    height);
+                (target_width, target_width * height / width)
+            },
+        };
+        Rect::new(((window_width - target_width) / 2) as i32,
+                  ((window_height - target_height) / 2) as i32,
+                  target_width.max(1), target_height.max(1))
     }
-    renderer.present();
 }
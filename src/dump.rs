@@ -0,0 +1,55 @@
+use std::io::{self, Write};
+
+use png;
+
+/// Grey level used for each frame buffer value in PNG dumps.
+const GREY_LEVELS: [u8; 4] = [0x00, 0xFF, 0xAA, 0x55];
+
+/// Render a frame buffer `width` pixels wide as text, one line per row, with
+/// `█` for lit pixels and `_` for unlit ones.
+pub fn ascii(fb: &[u8], width: usize) -> String {
+    let mut text = String::with_capacity(fb.len() * 3 + fb.len() / width);
+    for row in fb.chunks(width) {
+        for &pixel in row {
+            text.push(if pixel != 0 { '█' } else { '_' });
+        }
+        text.push('\n');
+    }
+    text
+}
+
+/// Write a `width` by `height` frame buffer as a plain (ASCII) PBM image.
+/// Pixels lit in either bitplane are black.
+pub fn write_pbm<W: Write>(fb: &[u8], width: usize, height: usize,
+                           mut out: W) -> io::Result<()> {
+    writeln!(out, "P1\n{} {}", width, height)?;
+    for row in fb.chunks(width) {
+        let line: Vec<&str> = row.iter()
+            .map(|&pixel| if pixel != 0 { "1" } else { "0" })
+            .collect();
+        writeln!(out, "{}", line.join(" "))?;
+    }
+    out.flush()
+}
+
+/// Write a `width` by `height` frame buffer as an 8-bit greyscale PNG image.
+/// Lit pixels are white; the two XO-CHIP bitplanes get their own grey levels.
+pub fn write_png<W: Write>(fb: &[u8], width: usize, height: usize,
+                           out: W) -> io::Result<()> {
+    let mut encoder = png::Encoder::new(out, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let data: Vec<u8> = fb.iter()
+        .map(|&pixel| GREY_LEVELS[(pixel & 0x3) as usize])
+        .collect();
+    let mut writer = encoder.write_header().map_err(png_error)?;
+    writer.write_image_data(&data).map_err(png_error)
+}
+
+fn png_error(err: png::EncodingError) -> io::Error {
+    match err {
+        png::EncodingError::IoError(err) => err,
+        err => io::Error::other(err),
+    }
+}
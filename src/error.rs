@@ -0,0 +1,93 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use cartridge::CartridgeError;
+
+/// An error raised while executing a CHIP-8 program.
+///
+/// Each variant records the address of the faulting instruction so that a
+/// frontend can report where execution went wrong. The machine is left in the
+/// state it was in before the faulting instruction was executed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Chip8Error {
+    /// The instruction at `pc` is not a recognized opcode.
+    UnknownOpcode { pc: u16, instr: u16 },
+
+    /// A CALL at `pc` was executed with the stack already full.
+    StackOverflow { pc: u16 },
+
+    /// A RET at `pc` was executed with an empty stack.
+    StackUnderflow { pc: u16 },
+
+    /// The instruction at `pc` tried to access memory at `addr`, which lies
+    /// outside the address space.
+    MemoryOutOfBounds { pc: u16, addr: usize },
+}
+
+impl fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Chip8Error::UnknownOpcode { pc, instr } => {
+                write!(f, "{:#06X}: Unrecognized instruction {:#06X}", pc, instr)
+            },
+            Chip8Error::StackOverflow { pc } => {
+                write!(f, "{:#06X}: Stack overflow", pc)
+            },
+            Chip8Error::StackUnderflow { pc } => {
+                write!(f, "{:#06X}: Stack underflow", pc)
+            },
+            Chip8Error::MemoryOutOfBounds { pc, addr } => {
+                write!(f, "{:#06X}: Memory access out of bounds at {:#06X}",
+                       pc, addr)
+            },
+        }
+    }
+}
+
+impl Error for Chip8Error {}
+
+/// An error raised while loading a ROM.
+#[derive(Debug)]
+pub enum RomError {
+    /// The ROM couldn't be read.
+    Io(io::Error),
+
+    /// The ROM is empty.
+    Empty,
+
+    /// The ROM is `size` bytes, but only `max` fit between the load address
+    /// and the end of memory.
+    TooLarge { size: usize, max: usize },
+
+    /// The file is an Octo cartridge that couldn't be decoded.
+    Cartridge(CartridgeError),
+}
+
+impl fmt::Display for RomError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RomError::Io(ref err) => write!(f, "{}", err),
+            RomError::Empty => write!(f, "ROM is empty"),
+            RomError::TooLarge { size, max } => {
+                write!(f, "ROM is too large: {} bytes, at most {} fit",
+                       size, max)
+            },
+            RomError::Cartridge(ref err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for RomError {}
+
+impl From<io::Error> for RomError {
+    fn from(err: io::Error) -> RomError {
+        RomError::Io(err)
+    }
+}
+
+impl From<CartridgeError> for RomError {
+    fn from(err: CartridgeError) -> RomError {
+        RomError::Cartridge(err)
+    }
+}
@@ -0,0 +1,160 @@
+//! Display filters that reduce flicker.
+//!
+//! CHIP-8 programs move sprites by erasing them with an XOR draw and drawing
+//! them again, so a sprite is often missing from the frame buffer at the end
+//! of a frame and appears to flicker. These filters hide that on the host
+//! side, in the colours shown for each frame; the emulated frame buffer is
+//! unaffected.
+
+use palette::{Palette, Rgb};
+
+/// Names accepted by `FilterMode::from_name`.
+pub const MODES: &[&str] = &["off", "decay", "or", "blend"];
+
+/// How a frame is combined with the frames before it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FilterMode {
+    /// Show each frame as it is.
+    Off,
+
+    /// Simulate phosphor persistence: lit pixels appear at once, and pixels
+    /// that go out fade towards the background over several frames.
+    Decay,
+
+    /// Show a pixel lit if it was lit in this frame or the one before.
+    Or,
+
+    /// Like `Or`, but show pixels lit only in the frame before dimmed
+    /// towards the background.
+    Blend,
+}
+
+impl FilterMode {
+    /// Look up a mode by name (see `MODES`).
+    pub fn from_name(name: &str) -> Option<FilterMode> {
+        match name {
+            "off" => Some(FilterMode::Off),
+            "decay" => Some(FilterMode::Decay),
+            "or" => Some(FilterMode::Or),
+            "blend" => Some(FilterMode::Blend),
+            _ => None,
+        }
+    }
+}
+
+/// Turns frame buffers into colours, combining each with the frames shown
+/// before it according to a `FilterMode`.
+///
+/// `apply` should be called once per frame, whether or not the frame buffer
+/// changed, so that faded pixels keep fading.
+#[derive(Clone, Debug)]
+pub struct Filter {
+    mode: FilterMode,
+    strength: f32,
+
+    // Frame buffer passed to the last `apply`, for `FilterMode::Or` and
+    // `FilterMode::Blend`
+    previous: Vec<u8>,
+
+    // Colours shown for the last frame, for `FilterMode::Decay`
+    shown: Vec<[f32; 3]>,
+
+    output: Vec<Rgb>,
+}
+
+impl Filter {
+    /// Construct a filter. `strength`, from 0 to 1, is the fraction of its
+    /// brightness a pixel keeps each frame after going out under
+    /// `FilterMode::Decay`, and how bright a pixel lit only in the previous
+    /// frame is shown under `FilterMode::Blend`.
+    pub fn new(mode: FilterMode, strength: f32) -> Filter {
+        Filter {
+            mode,
+            strength: strength.clamp(0.0, 1.0),
+            previous: Vec::new(),
+            shown: Vec::new(),
+            output: Vec::new(),
+        }
+    }
+
+    /// The filter mode.
+    pub fn mode(&self) -> FilterMode {
+        self.mode
+    }
+
+    /// The colour of each pixel of frame buffer `fb` in `palette`, combined
+    /// with the frames before it.
+    pub fn apply(&mut self, fb: &[u8], palette: &Palette) -> &[Rgb] {
+        // Nothing to combine with at first or after a resolution change
+        if self.previous.len() != fb.len() {
+            self.previous = fb.to_vec();
+            self.shown = fb.iter()
+                .map(|&pixel| to_float(palette.colour(pixel)))
+                .collect();
+        }
+
+        self.output.clear();
+        match self.mode {
+            FilterMode::Off => {
+                self.output.extend(fb.iter().map(|&pixel| {
+                    palette.colour(pixel)
+                }));
+            },
+            FilterMode::Decay => {
+                for (&pixel, shown) in fb.iter().zip(self.shown.iter_mut()) {
+                    let target = to_float(palette.colour(pixel));
+                    if pixel != 0x0 {
+                        *shown = target;
+                    } else {
+                        for (component, &goal) in shown.iter_mut()
+                            .zip(target.iter()) {
+                            *component = goal +
+                                         (*component - goal) * self.strength;
+                        }
+                    }
+                    self.output.push(to_rgb(*shown));
+                }
+            },
+            FilterMode::Or => {
+                // Combine the bitplanes too, so XO-CHIP colours stay right
+                self.output.extend(fb.iter().zip(self.previous.iter())
+                    .map(|(&pixel, &previous)| {
+                        palette.colour(pixel | previous)
+                    }));
+            },
+            FilterMode::Blend => {
+                let background = to_float(palette.colour(0x0));
+                for (&pixel, &previous) in fb.iter().zip(self.previous.iter()) {
+                    if pixel != 0x0 || previous == 0x0 {
+                        self.output.push(palette.colour(pixel));
+                        continue;
+                    }
+                    let ghost = to_float(palette.colour(previous));
+                    let mut colour = [0.0; 3];
+                    for (i, component) in colour.iter_mut().enumerate() {
+                        *component = background[i] +
+                                     (ghost[i] - background[i]) * self.strength;
+                    }
+                    self.output.push(to_rgb(colour));
+                }
+            },
+        }
+
+        self.previous.copy_from_slice(fb);
+        &self.output
+    }
+}
+
+impl Default for Filter {
+    fn default() -> Filter {
+        Filter::new(FilterMode::Off, 0.0)
+    }
+}
+
+fn to_float(colour: Rgb) -> [f32; 3] {
+    [colour[0] as f32, colour[1] as f32, colour[2] as f32]
+}
+
+fn to_rgb(colour: [f32; 3]) -> Rgb {
+    [colour[0].round() as u8, colour[1].round() as u8, colour[2].round() as u8]
+}
@@ -0,0 +1,226 @@
+//! Built-in hex font sets.
+//!
+//! Interpreters kept a sprite for each hex digit in memory for FX29 (and, on
+//! SUPER-CHIP, a larger one for FX30). The digits looked different on each
+//! machine, and some programs draw them as part of their graphics, so the set
+//! can be chosen to match the interpreter a program was written for.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Bytes in a small 4x5 digit sprite.
+pub const SMALL_GLYPH_SIZE: usize = 5;
+
+/// Bytes in a large 8x10 digit sprite.
+pub const BIG_GLYPH_SIZE: usize = 10;
+
+/// Bytes in a whole small font.
+pub const SMALL_FONT_SIZE: usize = 16 * SMALL_GLYPH_SIZE;
+
+/// Bytes in a whole large font.
+pub const BIG_FONT_SIZE: usize = 16 * BIG_GLYPH_SIZE;
+
+/// Names accepted by `Font::preset`.
+pub const PRESETS: &[&str] = &["vip", "dream6800", "eti660", "fishnchips",
+                               "schip"];
+
+/// The large SUPER-CHIP digits, extended with A to F. Interpreters without a
+/// large font of their own get these.
+const SCHIP_BIG: [u8; BIG_FONT_SIZE] = [
+    0x3C, 0x7E, 0xE7, 0xC3, 0xC3, 0xC3, 0xC3, 0xE7, 0x7E, 0x3C, // 0
+    0x18, 0x38, 0x58, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C, // 1
+    0x3E, 0x7F, 0xC3, 0x06, 0x0C, 0x18, 0x30, 0x60, 0xFF, 0xFF, // 2
+    0x3C, 0x7E, 0xC3, 0x03, 0x0E, 0x0E, 0x03, 0xC3, 0x7E, 0x3C, // 3
+    0x06, 0x0E, 0x1E, 0x36, 0x66, 0xC6, 0xFF, 0xFF, 0x06, 0x06, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFE, 0x03, 0xC3, 0x7E, 0x3C, // 5
+    0x3E, 0x7C, 0xE0, 0xC0, 0xFC, 0xFE, 0xC3, 0xC3, 0x7E, 0x3C, // 6
+    0xFF, 0xFF, 0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x60, 0x60, // 7
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7E, 0x7E, 0xC3, 0xC3, 0x7E, 0x3C, // 8
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7F, 0x3F, 0x03, 0x03, 0x3E, 0x7C, // 9
+    0x3C, 0x7E, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, // A
+    0xFC, 0xFE, 0xC3, 0xC3, 0xFE, 0xFE, 0xC3, 0xC3, 0xFE, 0xFC, // B
+    0x3C, 0x7E, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0x7E, 0x3C, // C
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFC, 0xC0, 0xC0, 0xFF, 0xFF, // E
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFC, 0xC0, 0xC0, 0xC0, 0xC0  // F
+];
+
+/// Small and large sprites for the hex digits 0 to F.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Font {
+    pub small: [u8; SMALL_FONT_SIZE],
+    pub big: [u8; BIG_FONT_SIZE],
+}
+
+impl Font {
+    /// The COSMAC VIP's digits.
+    pub fn vip() -> Font {
+        Font {
+            small: [
+                0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+                0x60, 0x20, 0x20, 0x20, 0x70, // 1
+                0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+                0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+                0xA0, 0xA0, 0xF0, 0x20, 0x20, // 4
+                0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+                0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+                0xF0, 0x10, 0x10, 0x10, 0x10, // 7
+                0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+                0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+                0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+                0xF0, 0x50, 0x70, 0x50, 0xF0, // B
+                0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+                0xF0, 0x50, 0x50, 0x50, 0xF0, // D
+                0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+                0xF0, 0x80, 0xF0, 0x80, 0x80  // F
+            ],
+            big: SCHIP_BIG,
+        }
+    }
+
+    /// The DREAM 6800's three-pixel-wide digits.
+    pub fn dream6800() -> Font {
+        Font {
+            small: [
+                0xE0, 0xA0, 0xA0, 0xA0, 0xE0, // 0
+                0x40, 0x40, 0x40, 0x40, 0x40, // 1
+                0xE0, 0x20, 0xE0, 0x80, 0xE0, // 2
+                0xE0, 0x20, 0xE0, 0x20, 0xE0, // 3
+                0x80, 0xA0, 0xA0, 0xE0, 0x20, // 4
+                0xE0, 0x80, 0xE0, 0x20, 0xE0, // 5
+                0xE0, 0x80, 0xE0, 0xA0, 0xE0, // 6
+                0xE0, 0x20, 0x20, 0x20, 0x20, // 7
+                0xE0, 0xA0, 0xE0, 0xA0, 0xE0, // 8
+                0xE0, 0xA0, 0xE0, 0x20, 0xE0, // 9
+                0xE0, 0xA0, 0xE0, 0xA0, 0xA0, // A
+                0xC0, 0xA0, 0xE0, 0xA0, 0xC0, // B
+                0xE0, 0x80, 0x80, 0x80, 0xE0, // C
+                0xC0, 0xA0, 0xA0, 0xA0, 0xC0, // D
+                0xE0, 0x80, 0xE0, 0x80, 0xE0, // E
+                0xE0, 0x80, 0xC0, 0x80, 0x80  // F
+            ],
+            big: SCHIP_BIG,
+        }
+    }
+
+    /// The ETI-660's digits.
+    pub fn eti660() -> Font {
+        Font {
+            small: [
+                0xE0, 0xA0, 0xA0, 0xA0, 0xE0, // 0
+                0x20, 0x20, 0x20, 0x20, 0x20, // 1
+                0xE0, 0x20, 0xE0, 0x80, 0xE0, // 2
+                0xE0, 0x20, 0xE0, 0x20, 0xE0, // 3
+                0xA0, 0xA0, 0xE0, 0x20, 0x20, // 4
+                0xE0, 0x80, 0xE0, 0x20, 0xE0, // 5
+                0xE0, 0x80, 0xE0, 0xA0, 0xE0, // 6
+                0xE0, 0x20, 0x20, 0x20, 0x20, // 7
+                0xE0, 0xA0, 0xE0, 0xA0, 0xE0, // 8
+                0xE0, 0xA0, 0xE0, 0x20, 0xE0, // 9
+                0xE0, 0xA0, 0xE0, 0xA0, 0xA0, // A
+                0x80, 0x80, 0xE0, 0xA0, 0xE0, // B
+                0xE0, 0x80, 0x80, 0x80, 0xE0, // C
+                0x20, 0x20, 0xE0, 0xA0, 0xE0, // D
+                0xE0, 0x80, 0xE0, 0x80, 0xE0, // E
+                0xE0, 0x80, 0xE0, 0x80, 0x80  // F
+            ],
+            big: SCHIP_BIG,
+        }
+    }
+
+    /// The rounded digits of the FISH'N'CHIPS font.
+    pub fn fishnchips() -> Font {
+        Font {
+            small: [
+                0x60, 0xA0, 0xA0, 0xA0, 0xC0, // 0
+                0x40, 0xC0, 0x40, 0x40, 0xE0, // 1
+                0xC0, 0x20, 0x40, 0x80, 0xE0, // 2
+                0xC0, 0x20, 0x40, 0x20, 0xC0, // 3
+                0x20, 0xA0, 0xE0, 0x20, 0x20, // 4
+                0xE0, 0x80, 0xC0, 0x20, 0xC0, // 5
+                0x40, 0x80, 0xC0, 0xA0, 0x40, // 6
+                0xE0, 0x20, 0x60, 0x40, 0x40, // 7
+                0x40, 0xA0, 0x40, 0xA0, 0x40, // 8
+                0x40, 0xA0, 0x60, 0x20, 0x40, // 9
+                0x40, 0xA0, 0xE0, 0xA0, 0xA0, // A
+                0xC0, 0xA0, 0xC0, 0xA0, 0xC0, // B
+                0x60, 0x80, 0x80, 0x80, 0x60, // C
+                0xC0, 0xA0, 0xA0, 0xA0, 0xC0, // D
+                0xE0, 0x80, 0xC0, 0x80, 0xE0, // E
+                0xE0, 0x80, 0xC0, 0x80, 0x80  // F
+            ],
+            big: SCHIP_BIG,
+        }
+    }
+
+    /// The SUPER-CHIP's digits, small and large.
+    pub fn schip() -> Font {
+        Font {
+            small: [
+                0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+                0x20, 0x60, 0x20, 0x20, 0x70, // 1
+                0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+                0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+                0x90, 0x90, 0xF0, 0x10, 0x10, // 4
+                0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+                0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+                0xF0, 0x10, 0x20, 0x40, 0x40, // 7
+                0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+                0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+                0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+                0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
+                0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+                0xE0, 0x90, 0x90, 0x90, 0xE0, // D
+                0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+                0xF0, 0x80, 0xF0, 0x80, 0x80  // F
+            ],
+            big: SCHIP_BIG,
+        }
+    }
+
+    /// Look up a preset by name (see `PRESETS`).
+    pub fn preset(name: &str) -> Option<Font> {
+        match name {
+            "vip" => Some(Font::vip()),
+            "dream6800" => Some(Font::dream6800()),
+            "eti660" => Some(Font::eti660()),
+            "fishnchips" => Some(Font::fishnchips()),
+            "schip" => Some(Font::schip()),
+            _ => None,
+        }
+    }
+
+    /// Parse a font file: the small digits 0 to F, five bytes each, followed
+    /// by the large digits, ten bytes each. The large digits may be left out,
+    /// in which case the SUPER-CHIP ones are used.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Font> {
+        let mut font = Font { small: [0x0; SMALL_FONT_SIZE], big: SCHIP_BIG };
+        match bytes.len() {
+            SMALL_FONT_SIZE => font.small.copy_from_slice(bytes),
+            len if len == SMALL_FONT_SIZE + BIG_FONT_SIZE => {
+                let (small, big) = bytes.split_at(SMALL_FONT_SIZE);
+                font.small.copy_from_slice(small);
+                font.big.copy_from_slice(big);
+            },
+            _ => return None,
+        }
+        Some(font)
+    }
+
+    /// Read a font file (see `from_bytes`).
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Font> {
+        Font::from_bytes(&fs::read(path)?).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData,
+                           format!("font files must be {} or {} bytes",
+                                   SMALL_FONT_SIZE,
+                                   SMALL_FONT_SIZE + BIG_FONT_SIZE))
+        })
+    }
+}
+
+impl Default for Font {
+    fn default() -> Font {
+        Font::schip()
+    }
+}
@@ -1,56 +1,53 @@
-use chip8::Chip8;
+use chip8::Keypad;
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
 
-pub fn scan_keyboard(chip8: &mut Chip8, event: Event) {
+/// Map a host key to the CHIP-8 keypad key it stands for.
+///
+/// The hex keypad is laid out over the left-hand block of the keyboard:
+///
+/// ```text
+/// 1 2 3 4      0 1 2 3
+/// Q W E R  ->  4 5 6 7
+/// A S D F      8 9 A B
+/// Z X C V      C D E F
+/// ```
+fn keymap(keycode: Keycode) -> Option<u8> {
+    match keycode {
+        Keycode::Num1 => Some(0x0),
+        Keycode::Num2 => Some(0x1),
+        Keycode::Num3 => Some(0x2),
+        Keycode::Num4 => Some(0x3),
+        Keycode::Q => Some(0x4),
+        Keycode::W => Some(0x5),
+        Keycode::E => Some(0x6),
+        Keycode::R => Some(0x7),
+        Keycode::A => Some(0x8),
+        Keycode::S => Some(0x9),
+        Keycode::D => Some(0xA),
+        Keycode::F => Some(0xB),
+        Keycode::Z => Some(0xC),
+        Keycode::X => Some(0xD),
+        Keycode::C => Some(0xE),
+        Keycode::V => Some(0xF),
+        _ => None,
+    }
+}
+
+/// Update `keypad` with a host key event. The keypad is handed to the
+/// machine once per frame, so every run sees key changes at frame boundaries
+/// and can be recorded and replayed exactly.
+pub fn scan_keyboard(keypad: &mut Keypad, event: Event) {
     match event {
-        Event::KeyDown { keycode: Some(Keycode::Num1), .. } => {
-            chip8.keyboard = 0x0;
-        },
-        Event::KeyDown { keycode: Some(Keycode::Num2), .. } => {
-            chip8.keyboard = 0x1;
-        },
-        Event::KeyDown { keycode: Some(Keycode::Num3), .. } => {
-            chip8.keyboard = 0x2;
-        },
-        Event::KeyDown { keycode: Some(Keycode::Num4), .. } => {
-            chip8.keyboard = 0x3;
-        },
-        Event::KeyDown { keycode: Some(Keycode::Q), .. } => {
-            chip8.keyboard = 0x4;
-        },
-        Event::KeyDown { keycode: Some(Keycode::W), .. } => {
-            chip8.keyboard = 0x5;
-        },
-        Event::KeyDown { keycode: Some(Keycode::E), .. } => {
-            chip8.keyboard = 0x6;
-        },
-        Event::KeyDown { keycode: Some(Keycode::R), .. } => {
-            chip8.keyboard = 0x7;
-        },
-        Event::KeyDown { keycode: Some(Keycode::A), .. } => {
-            chip8.keyboard = 0x8;
-        },
-        Event::KeyDown { keycode: Some(Keycode::S), .. } => {
-            chip8.keyboard = 0x9;
-        },
-        Event::KeyDown { keycode: Some(Keycode::D), .. } => {
-            chip8.keyboard = 0xA;
-        },
-        Event::KeyDown { keycode: Some(Keycode::F), .. } => {
-            chip8.keyboard = 0xB;
-        },
-        Event::KeyDown { keycode: Some(Keycode::Z), .. } => {
-            chip8.keyboard = 0xC;
-        },
-        Event::KeyDown { keycode: Some(Keycode::X), .. } => {
-            chip8.keyboard = 0xD;
-        },
-        Event::KeyDown { keycode: Some(Keycode::C), .. } => {
-            chip8.keyboard = 0xE;
-        },
-        Event::KeyDown { keycode: Some(Keycode::V), .. } => {
-            chip8.keyboard = 0xF;
+        Event::KeyDown { keycode: Some(keycode), repeat: false, .. } => {
+            if let Some(key) = keymap(keycode) {
+                keypad.press(key);
+            }
+        },
+        Event::KeyUp { keycode: Some(keycode), .. } => {
+            if let Some(key) = keymap(keycode) {
+                keypad.release(key);
+            }
         },
         _ => { }
     };
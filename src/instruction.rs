@@ -0,0 +1,402 @@
+use std::error::Error;
+use std::fmt;
+
+/// A decoded CHIP-8, SUPER-CHIP or XO-CHIP instruction.
+///
+/// Variants are named after the interpreter's handlers. X and Y are register
+/// numbers, and addresses are the 12-bit NNN operand except for
+/// `LdIndexLong`, which carries the 16-bit word following it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Instruction {
+    /// 0x00CN
+    ScdNib(u8),
+    /// 0x00E0
+    Cls,
+    /// 0x00EE
+    Ret,
+    /// 0x00FB
+    Scr,
+    /// 0x00FC
+    Scl,
+    /// 0x00FD
+    Exit,
+    /// 0x00FE
+    Low,
+    /// 0x00FF
+    High,
+    /// 0x1NNN
+    JpAddr(u16),
+    /// 0x2NNN
+    CallAddr(u16),
+    /// 0x3XNN
+    SeVxByte(u8, u8),
+    /// 0x4XNN
+    SneVxByte(u8, u8),
+    /// 0x5XY0
+    SeVxVy(u8, u8),
+    /// 0x5XY2
+    LdIndexVxVy(u8, u8),
+    /// 0x5XY3
+    LdVxVyIndex(u8, u8),
+    /// 0x6XNN
+    LdVxByte(u8, u8),
+    /// 0x7XNN
+    AddVxByte(u8, u8),
+    /// 0x8XY0
+    LdVxVy(u8, u8),
+    /// 0x8XY1
+    OrVxVy(u8, u8),
+    /// 0x8XY2
+    AndVxVy(u8, u8),
+    /// 0x8XY3
+    XorVxVy(u8, u8),
+    /// 0x8XY4
+    AddVxVy(u8, u8),
+    /// 0x8XY5
+    SubVxVy(u8, u8),
+    /// 0x8XY6
+    ShrVx(u8, u8),
+    /// 0x8XY7
+    SubnVxVy(u8, u8),
+    /// 0x8XYE
+    ShlVx(u8, u8),
+    /// 0x9XY0
+    SneVxVy(u8, u8),
+    /// 0xANNN
+    LdIndexAddr(u16),
+    /// 0xBNNN
+    JpV0Addr(u16),
+    /// 0xCXNN
+    RndVxByte(u8, u8),
+    /// 0xDXYN
+    DrwVxVyNib(u8, u8, u8),
+    /// 0xEX9E
+    SkpVx(u8),
+    /// 0xEXA1
+    SknpVx(u8),
+    /// 0xF000 0xNNNN
+    LdIndexLong(u16),
+    /// 0xFN01
+    PlaneNib(u8),
+    /// 0xF002
+    LdPatternIndex,
+    /// 0xFX07
+    LdVxDt(u8),
+    /// 0xFX0A
+    LdVxKey(u8),
+    /// 0xFX15
+    LdDtVx(u8),
+    /// 0xFX18
+    LdStVx(u8),
+    /// 0xFX1E
+    AddIndexVx(u8),
+    /// 0xFX29
+    LdIndexVxSprite(u8),
+    /// 0xFX30
+    LdIndexVxBigSprite(u8),
+    /// 0xFX33
+    LdBcdVx(u8),
+    /// 0xFX3A
+    LdPitchVx(u8),
+    /// 0xFX55
+    LdIndexImmVx(u8),
+    /// 0xFX65
+    LdVxIndexImm(u8),
+    /// 0xFX75
+    LdRplVx(u8),
+    /// 0xFX85
+    LdVxRpl(u8),
+}
+
+/// Why a word could not be decoded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The word is not a recognized instruction.
+    UnknownOpcode(u16),
+
+    /// The instruction runs past the end of the bytes given.
+    Truncated,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodeError::UnknownOpcode(word) => {
+                write!(f, "Unrecognized instruction {:#06X}", word)
+            },
+            DecodeError::Truncated => write!(f, "Truncated instruction"),
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+impl Instruction {
+    /// Decode a single instruction word.
+    ///
+    /// F000 NNNN is four bytes long and cannot be decoded from one word; it
+    /// gives `DecodeError::Truncated`. Use `decode_bytes` where the operand is
+    /// available.
+    pub fn decode(word: u16) -> Result<Instruction, DecodeError> {
+        let x = ((word & 0x0F00) >> 8) as u8;
+        let y = ((word & 0x00F0) >> 4) as u8;
+        let nib = (word & 0x000F) as u8;
+        let byte = (word & 0x00FF) as u8;
+        let addr = word & 0x0FFF;
+        let unknown = Err(DecodeError::UnknownOpcode(word));
+
+        let instruction = match word & 0xF000 {
+            0x0000 => match word {
+                0x00E0 => Instruction::Cls,
+                0x00EE => Instruction::Ret,
+                0x00FB => Instruction::Scr,
+                0x00FC => Instruction::Scl,
+                0x00FD => Instruction::Exit,
+                0x00FE => Instruction::Low,
+                0x00FF => Instruction::High,
+                _ if word & 0xFFF0 == 0x00C0 => Instruction::ScdNib(nib),
+                _ => return unknown,
+            },
+            0x1000 => Instruction::JpAddr(addr),
+            0x2000 => Instruction::CallAddr(addr),
+            0x3000 => Instruction::SeVxByte(x, byte),
+            0x4000 => Instruction::SneVxByte(x, byte),
+            0x5000 => match nib {
+                0x0 => Instruction::SeVxVy(x, y),
+                0x2 => Instruction::LdIndexVxVy(x, y),
+                0x3 => Instruction::LdVxVyIndex(x, y),
+                _ => return unknown,
+            },
+            0x6000 => Instruction::LdVxByte(x, byte),
+            0x7000 => Instruction::AddVxByte(x, byte),
+            0x8000 => match nib {
+                0x0 => Instruction::LdVxVy(x, y),
+                0x1 => Instruction::OrVxVy(x, y),
+                0x2 => Instruction::AndVxVy(x, y),
+                0x3 => Instruction::XorVxVy(x, y),
+                0x4 => Instruction::AddVxVy(x, y),
+                0x5 => Instruction::SubVxVy(x, y),
+                0x6 => Instruction::ShrVx(x, y),
+                0x7 => Instruction::SubnVxVy(x, y),
+                0xE => Instruction::ShlVx(x, y),
+                _ => return unknown,
+            },
+            0x9000 if nib == 0x0 => Instruction::SneVxVy(x, y),
+            0xA000 => Instruction::LdIndexAddr(addr),
+            0xB000 => Instruction::JpV0Addr(addr),
+            0xC000 => Instruction::RndVxByte(x, byte),
+            0xD000 => Instruction::DrwVxVyNib(x, y, nib),
+            0xE000 => match byte {
+                0x9E => Instruction::SkpVx(x),
+                0xA1 => Instruction::SknpVx(x),
+                _ => return unknown,
+            },
+            0xF000 => match byte {
+                0x00 if word == 0xF000 => return Err(DecodeError::Truncated),
+                0x01 => Instruction::PlaneNib(x),
+                0x02 if word == 0xF002 => Instruction::LdPatternIndex,
+                0x07 => Instruction::LdVxDt(x),
+                0x0A => Instruction::LdVxKey(x),
+                0x15 => Instruction::LdDtVx(x),
+                0x18 => Instruction::LdStVx(x),
+                0x1E => Instruction::AddIndexVx(x),
+                0x29 => Instruction::LdIndexVxSprite(x),
+                0x30 => Instruction::LdIndexVxBigSprite(x),
+                0x33 => Instruction::LdBcdVx(x),
+                0x3A => Instruction::LdPitchVx(x),
+                0x55 => Instruction::LdIndexImmVx(x),
+                0x65 => Instruction::LdVxIndexImm(x),
+                0x75 => Instruction::LdRplVx(x),
+                0x85 => Instruction::LdVxRpl(x),
+                _ => return unknown,
+            },
+            _ => return unknown,
+        };
+        Ok(instruction)
+    }
+
+    /// Decode the instruction at the start of `bytes`, including the operand
+    /// of F000 NNNN.
+    pub fn decode_bytes(bytes: &[u8]) -> Result<Instruction, DecodeError> {
+        let word_at = |offset: usize| -> Result<u16, DecodeError> {
+            match bytes.get(offset..offset + 2) {
+                Some(word) => Ok((word[0] as u16) << 8 | word[1] as u16),
+                None => Err(DecodeError::Truncated),
+            }
+        };
+        match word_at(0)? {
+            0xF000 => Ok(Instruction::LdIndexLong(word_at(2)?)),
+            word => Instruction::decode(word),
+        }
+    }
+
+    /// Encode the instruction as the bytes `decode_bytes` reads. Operands
+    /// wider than their field are truncated.
+    pub fn encode(&self) -> Vec<u8> {
+        let xy = |op: u16, x: u8, y: u8, n: u16| {
+            op | (x as u16 & 0xF) << 8 | (y as u16 & 0xF) << 4 | n
+        };
+        let xnn = |op: u16, x: u8, byte: u8| {
+            op | (x as u16 & 0xF) << 8 | byte as u16
+        };
+        let nnn = |op: u16, addr: u16| op | addr & 0x0FFF;
+
+        let word = match *self {
+            Instruction::ScdNib(n) => 0x00C0 | (n as u16 & 0xF),
+            Instruction::Cls => 0x00E0,
+            Instruction::Ret => 0x00EE,
+            Instruction::Scr => 0x00FB,
+            Instruction::Scl => 0x00FC,
+            Instruction::Exit => 0x00FD,
+            Instruction::Low => 0x00FE,
+            Instruction::High => 0x00FF,
+            Instruction::JpAddr(addr) => nnn(0x1000, addr),
+            Instruction::CallAddr(addr) => nnn(0x2000, addr),
+            Instruction::SeVxByte(x, byte) => xnn(0x3000, x, byte),
+            Instruction::SneVxByte(x, byte) => xnn(0x4000, x, byte),
+            Instruction::SeVxVy(x, y) => xy(0x5000, x, y, 0x0),
+            Instruction::LdIndexVxVy(x, y) => xy(0x5000, x, y, 0x2),
+            Instruction::LdVxVyIndex(x, y) => xy(0x5000, x, y, 0x3),
+            Instruction::LdVxByte(x, byte) => xnn(0x6000, x, byte),
+            Instruction::AddVxByte(x, byte) => xnn(0x7000, x, byte),
+            Instruction::LdVxVy(x, y) => xy(0x8000, x, y, 0x0),
+            Instruction::OrVxVy(x, y) => xy(0x8000, x, y, 0x1),
+            Instruction::AndVxVy(x, y) => xy(0x8000, x, y, 0x2),
+            Instruction::XorVxVy(x, y) => xy(0x8000, x, y, 0x3),
+            Instruction::AddVxVy(x, y) => xy(0x8000, x, y, 0x4),
+            Instruction::SubVxVy(x, y) => xy(0x8000, x, y, 0x5),
+            Instruction::ShrVx(x, y) => xy(0x8000, x, y, 0x6),
+            Instruction::SubnVxVy(x, y) => xy(0x8000, x, y, 0x7),
+            Instruction::ShlVx(x, y) => xy(0x8000, x, y, 0xE),
+            Instruction::SneVxVy(x, y) => xy(0x9000, x, y, 0x0),
+            Instruction::LdIndexAddr(addr) => nnn(0xA000, addr),
+            Instruction::JpV0Addr(addr) => nnn(0xB000, addr),
+            Instruction::RndVxByte(x, byte) => xnn(0xC000, x, byte),
+            Instruction::DrwVxVyNib(x, y, n) => xy(0xD000, x, y, n as u16 & 0xF),
+            Instruction::SkpVx(x) => xnn(0xE000, x, 0x9E),
+            Instruction::SknpVx(x) => xnn(0xE000, x, 0xA1),
+            Instruction::LdIndexLong(_) => 0xF000,
+            Instruction::PlaneNib(n) => xnn(0xF000, n, 0x01),
+            Instruction::LdPatternIndex => 0xF002,
+            Instruction::LdVxDt(x) => xnn(0xF000, x, 0x07),
+            Instruction::LdVxKey(x) => xnn(0xF000, x, 0x0A),
+            Instruction::LdDtVx(x) => xnn(0xF000, x, 0x15),
+            Instruction::LdStVx(x) => xnn(0xF000, x, 0x18),
+            Instruction::AddIndexVx(x) => xnn(0xF000, x, 0x1E),
+            Instruction::LdIndexVxSprite(x) => xnn(0xF000, x, 0x29),
+            Instruction::LdIndexVxBigSprite(x) => xnn(0xF000, x, 0x30),
+            Instruction::LdBcdVx(x) => xnn(0xF000, x, 0x33),
+            Instruction::LdPitchVx(x) => xnn(0xF000, x, 0x3A),
+            Instruction::LdIndexImmVx(x) => xnn(0xF000, x, 0x55),
+            Instruction::LdVxIndexImm(x) => xnn(0xF000, x, 0x65),
+            Instruction::LdRplVx(x) => xnn(0xF000, x, 0x75),
+            Instruction::LdVxRpl(x) => xnn(0xF000, x, 0x85),
+        };
+
+        let mut bytes = word.to_be_bytes().to_vec();
+        if let Instruction::LdIndexLong(addr) = *self {
+            bytes.extend_from_slice(&addr.to_be_bytes());
+        }
+        bytes
+    }
+
+    /// Length of the instruction in bytes.
+    pub fn size(&self) -> usize {
+        match *self {
+            Instruction::LdIndexLong(_) => 4,
+            _ => 2,
+        }
+    }
+
+    /// Whether the instruction conditionally skips the one after it.
+    pub fn is_skip(&self) -> bool {
+        matches!(*self,
+                 Instruction::SeVxByte(..) | Instruction::SneVxByte(..) |
+                 Instruction::SeVxVy(..) | Instruction::SneVxVy(..) |
+                 Instruction::SkpVx(_) | Instruction::SknpVx(_))
+    }
+
+    /// The address a jump, call or index load refers to.
+    pub fn target(&self) -> Option<u16> {
+        match *self {
+            Instruction::JpAddr(addr) | Instruction::CallAddr(addr) |
+            Instruction::LdIndexAddr(addr) | Instruction::JpV0Addr(addr) |
+            Instruction::LdIndexLong(addr) => Some(addr),
+            _ => None,
+        }
+    }
+
+    /// Format the instruction's mnemonic, writing address operands with
+    /// `name` so that callers can substitute labels.
+    pub fn mnemonic<F: Fn(u16) -> String>(&self, name: F) -> String {
+        match *self {
+            Instruction::ScdNib(n) => format!("SCD {}", n),
+            Instruction::Cls => "CLS".to_string(),
+            Instruction::Ret => "RET".to_string(),
+            Instruction::Scr => "SCR".to_string(),
+            Instruction::Scl => "SCL".to_string(),
+            Instruction::Exit => "EXIT".to_string(),
+            Instruction::Low => "LOW".to_string(),
+            Instruction::High => "HIGH".to_string(),
+            Instruction::JpAddr(addr) => format!("JP {}", name(addr)),
+            Instruction::CallAddr(addr) => format!("CALL {}", name(addr)),
+            Instruction::SeVxByte(x, byte) => format!("SE V{:X}, {:#04X}", x, byte),
+            Instruction::SneVxByte(x, byte) => {
+                format!("SNE V{:X}, {:#04X}", x, byte)
+            },
+            Instruction::SeVxVy(x, y) => format!("SE V{:X}, V{:X}", x, y),
+            Instruction::LdIndexVxVy(x, y) => {
+                format!("LD [I], V{:X}-V{:X}", x, y)
+            },
+            Instruction::LdVxVyIndex(x, y) => {
+                format!("LD V{:X}-V{:X}, [I]", x, y)
+            },
+            Instruction::LdVxByte(x, byte) => format!("LD V{:X}, {:#04X}", x, byte),
+            Instruction::AddVxByte(x, byte) => {
+                format!("ADD V{:X}, {:#04X}", x, byte)
+            },
+            Instruction::LdVxVy(x, y) => format!("LD V{:X}, V{:X}", x, y),
+            Instruction::OrVxVy(x, y) => format!("OR V{:X}, V{:X}", x, y),
+            Instruction::AndVxVy(x, y) => format!("AND V{:X}, V{:X}", x, y),
+            Instruction::XorVxVy(x, y) => format!("XOR V{:X}, V{:X}", x, y),
+            Instruction::AddVxVy(x, y) => format!("ADD V{:X}, V{:X}", x, y),
+            Instruction::SubVxVy(x, y) => format!("SUB V{:X}, V{:X}", x, y),
+            Instruction::ShrVx(x, y) => format!("SHR V{:X}, V{:X}", x, y),
+            Instruction::SubnVxVy(x, y) => format!("SUBN V{:X}, V{:X}", x, y),
+            Instruction::ShlVx(x, y) => format!("SHL V{:X}, V{:X}", x, y),
+            Instruction::SneVxVy(x, y) => format!("SNE V{:X}, V{:X}", x, y),
+            Instruction::LdIndexAddr(addr) => format!("LD I, {}", name(addr)),
+            Instruction::JpV0Addr(addr) => format!("JP V0, {}", name(addr)),
+            Instruction::RndVxByte(x, byte) => {
+                format!("RND V{:X}, {:#04X}", x, byte)
+            },
+            Instruction::DrwVxVyNib(x, y, n) => {
+                format!("DRW V{:X}, V{:X}, {}", x, y, n)
+            },
+            Instruction::SkpVx(x) => format!("SKP V{:X}", x),
+            Instruction::SknpVx(x) => format!("SKNP V{:X}", x),
+            Instruction::LdIndexLong(addr) => format!("LD I, LONG {}", name(addr)),
+            Instruction::PlaneNib(n) => format!("PLANE {}", n),
+            Instruction::LdPatternIndex => "AUDIO".to_string(),
+            Instruction::LdVxDt(x) => format!("LD V{:X}, DT", x),
+            Instruction::LdVxKey(x) => format!("LD V{:X}, K", x),
+            Instruction::LdDtVx(x) => format!("LD DT, V{:X}", x),
+            Instruction::LdStVx(x) => format!("LD ST, V{:X}", x),
+            Instruction::AddIndexVx(x) => format!("ADD I, V{:X}", x),
+            Instruction::LdIndexVxSprite(x) => format!("LD F, V{:X}", x),
+            Instruction::LdIndexVxBigSprite(x) => format!("LD HF, V{:X}", x),
+            Instruction::LdBcdVx(x) => format!("LD B, V{:X}", x),
+            Instruction::LdPitchVx(x) => format!("PITCH V{:X}", x),
+            Instruction::LdIndexImmVx(x) => format!("LD [I], V{:X}", x),
+            Instruction::LdVxIndexImm(x) => format!("LD V{:X}, [I]", x),
+            Instruction::LdRplVx(x) => format!("LD R, V{:X}", x),
+            Instruction::LdVxRpl(x) => format!("LD V{:X}, R", x),
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.mnemonic(|addr| format!("{:#06X}", addr)))
+    }
+}
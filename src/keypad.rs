@@ -0,0 +1,78 @@
+/// Number of keys on the CHIP-8 hex keypad.
+pub const NUM_KEYS: usize = 16;
+
+/// The state of the 16-key hex keypad.
+///
+/// Keys are identified by their hex value, 0x0 to 0xF. Values outside that
+/// range are ignored.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Keypad {
+    pressed: [bool; NUM_KEYS],
+}
+
+impl Keypad {
+    /// Construct a keypad with no keys pressed.
+    pub fn new() -> Keypad {
+        Keypad { pressed: [false; NUM_KEYS] }
+    }
+
+    /// Mark `key` as held down.
+    pub fn press(&mut self, key: u8) {
+        if let Some(state) = self.pressed.get_mut(key as usize) {
+            *state = true;
+        }
+    }
+
+    /// Mark `key` as released.
+    pub fn release(&mut self, key: u8) {
+        if let Some(state) = self.pressed.get_mut(key as usize) {
+            *state = false;
+        }
+    }
+
+    /// Release every key.
+    pub fn release_all(&mut self) {
+        self.pressed = [false; NUM_KEYS];
+    }
+
+    /// Whether `key` is currently held down.
+    pub fn is_pressed(&self, key: u8) -> bool {
+        self.pressed.get(key as usize).cloned().unwrap_or(false)
+    }
+
+    /// The keypad as a bitmask, with bit N set if key N is held down.
+    pub fn bits(&self) -> u16 {
+        self.pressed_keys().fold(0, |bits, key| bits | 1 << key)
+    }
+
+    /// Construct a keypad from a bitmask returned by `bits`.
+    pub fn from_bits(bits: u16) -> Keypad {
+        let mut keypad = Keypad::new();
+        for key in 0..NUM_KEYS as u8 {
+            if bits & (1 << key) != 0 {
+                keypad.press(key);
+            }
+        }
+        keypad
+    }
+
+    /// Iterate over the keys that are currently held down.
+    pub fn pressed_keys<'a>(&'a self) -> impl Iterator<Item = u8> + 'a {
+        (0..NUM_KEYS as u8).filter(move |&key| self.is_pressed(key))
+    }
+}
+
+/// The state of a pending FX0A key wait.
+///
+/// While a wait is pending the CPU is halted: no instructions are executed,
+/// but the timers keep counting. The wait completes when a key is pressed and
+/// then released, at which point the key is stored in V[`reg`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyWait {
+    /// Register that will receive the key.
+    pub reg: u8,
+
+    /// Key that has been pressed since the wait began, if any. The wait
+    /// completes when this key is released.
+    pub key: Option<u8>,
+}
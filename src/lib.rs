@@ -0,0 +1,46 @@
+//! A CHIP-8 emulator core.
+//!
+//! The core has no dependency on any particular frontend. A host drives it by
+//! loading a ROM, calling `run_frame` sixty times a second (or `execute_cycle`
+//! to single-step), feeding it key state and reading back the frame buffer and
+//! timers. Sound is produced by passing the buzzer's tone to an
+//! `audio::AudioSink` once per frame. Faults in the running program are
+//! reported as a `Chip8Error` rather than aborting the process.
+
+extern crate gif;
+#[macro_use]
+extern crate log;
+extern crate png;
+extern crate rand;
+extern crate serde_json;
+extern crate sha1;
+
+pub mod asm;
+pub mod audio;
+pub mod cartridge;
+pub mod chip8;
+pub mod config;
+pub mod debugger;
+pub mod disasm;
+pub mod dump;
+pub mod error;
+pub mod filter;
+pub mod font;
+pub mod instruction;
+pub mod keypad;
+pub mod movie;
+pub mod octo;
+pub mod palette;
+pub mod quirks;
+pub mod rewind;
+pub mod rng;
+pub mod romdb;
+pub mod state;
+pub mod timing;
+
+pub use chip8::Chip8;
+pub use error::{Chip8Error, RomError};
+pub use instruction::Instruction;
+pub use keypad::{KeyWait, Keypad};
+pub use quirks::Quirks;
+pub use state::StateError;
@@ -1,54 +1,581 @@
+extern crate chip8;
 extern crate env_logger;
-#[macro_use]
+#[cfg_attr(feature = "sdl-frontend", macro_use)]
 extern crate log;
+#[cfg(feature = "sdl-frontend")]
 extern crate sdl2;
 
-use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
-use sdl2::pixels::Color;
 use std::env;
-use std::thread::sleep;
-use std::time::Duration;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
 
-pub mod chip8;
-pub mod display;
-pub mod input;
+use chip8::{asm, config, disasm, rewind};
+use chip8::filter::{self, Filter, FilterMode};
+use chip8::palette::Palette;
+#[cfg(feature = "sdl-frontend")]
+use chip8::chip8::NUM_RPL_FLAGS;
+#[cfg(feature = "sdl-frontend")]
+use chip8::palette;
 
-fn main() {
-    // Quit if a program to run was not specified on the command line
-    if env::args().len() != 2 {
-        panic!("Usage: chip8-rust PROGRAM");
+#[cfg(feature = "sdl-frontend")]
+mod display;
+#[cfg(feature = "sdl-frontend")]
+mod input;
+#[cfg(feature = "sdl-frontend")]
+mod sound;
+
+const USAGE: &str = "\
+Usage: chip8-rust [OPTIONS] PROGRAM
+       chip8-rust disasm [--origin ADDR] [-o FILE] ROM
+       chip8-rust asm [-o FILE] SOURCE
+
+Run PROGRAM in a window, disassemble ROM, or assemble SOURCE into a ROM.
+
+PROGRAM is a ROM or an Octo cartridge GIF. Known ROMs are looked up in a
+built-in database, and programs are run with the speed, quirks, font and
+colours recommended there or stored in the cartridge, unless given as options.
+
+Options:
+    --ips N              Execute N instructions per second
+    --no-database        Don't look PROGRAM up in the ROM database
+    --quirks PRESET      Emulate an interpreter: vip, chip48, schip or modern
+    --quirk FLAG=on|off  Override one quirk: shift, load_store, load_store_x,
+                         jump, clip or vf_reset
+    --load-state FILE    Resume from a state saved for PROGRAM
+    --font FONT          Digits for FX29 and FX30: vip, dream6800, eti660,
+                         fishnchips, schip (default), or a file holding the
+                         80 bytes of the small digits, optionally followed by
+                         the 160 bytes of the large ones
+    --rewind-mb N        Keep up to N megabytes of history for rewinding
+                         (default 16)
+    --seed N             Seed the random number generator with N
+    --scale MODE         Fit the screen to the window: integer (default) or
+                         aspect
+    --fullscreen         Start in fullscreen
+    --palette PALETTE    Colours to show the screen in: classic (default),
+                         amber, green, octo, or a background and foreground
+                         colour, e.g. 000000,FFFFFF. Give four colours to set
+                         those of the XO-CHIP bitplanes as well
+    --filter MODE        Reduce flicker: off (default), decay to let pixels
+                         fade out like phosphor, or to show pixels lit in
+                         either of the last two frames, or blend to show
+                         those lit only in the last one dimmed
+    --filter-strength N  How much of a pixel remains after it goes out, as a
+                         percentage, under decay and blend (default 60)
+    --config FILE        Read default options from FILE instead of
+                         ~/.config/chip8-rust/config. Each line of the file is
+                         an option without its leading dashes, optionally
+                         followed by = and its value, e.g. palette = amber
+    --record FILE        Record the run's input to the movie FILE
+    --play FILE          Play back the movie FILE, then hand over control
+
+Keys:
+    Escape               Quit
+    Left Ctrl            Pause or resume
+    Backspace            Hold to rewind
+    F10                  Cycle through the palette presets
+    F11                  Toggle fullscreen
+    Shift+F1 to F9       Save the machine to state slot 1 to 9
+    F1 to F9             Restore the machine from state slot 1 to 9
+
+Disassembler options:
+    --origin ADDR        Address ROM is loaded at, in hex (default 200)
+    -o, --output FILE    Write the listing to FILE instead of stdout
+
+Assembler options:
+    -o, --output FILE    Write the ROM to FILE instead of SOURCE with its
+                         extension changed to .ch8";
+
+/// Percentage of a pixel's brightness kept by the flicker filter.
+const DEFAULT_FILTER_STRENGTH: u32 = 60;
+
+/// Command line options.
+#[cfg_attr(not(feature = "sdl-frontend"), allow(dead_code))]
+struct Options {
+    program: String,
+    settings: config::Options,
+    load_state: Option<String>,
+    rewind_budget: usize,
+    seed: Option<u64>,
+    record: Option<String>,
+    play: Option<String>,
+    integer_scaling: bool,
+    fullscreen: bool,
+    filter: Filter,
+}
+
+/// Options for the `disasm` subcommand.
+struct DisasmOptions {
+    rom: String,
+    origin: u16,
+    output: Option<String>,
+}
+
+/// Options for the `asm` subcommand.
+struct AsmOptions {
+    source: String,
+    output: Option<String>,
+}
+
+/// Parse the emulator's command line, exiting with a usage message if it is
+/// invalid.
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Options {
+    let mut program = None;
+    let mut settings = config::Options::default();
+    let mut load_state = None;
+    let mut rewind_budget = rewind::DEFAULT_BUDGET;
+    let mut seed = None;
+    let mut record = None;
+    let mut play = None;
+    let mut integer_scaling = true;
+    let mut fullscreen = false;
+    let mut filter_mode = FilterMode::Off;
+    let mut filter_strength = DEFAULT_FILTER_STRENGTH;
+
+    while let Some(arg) = args.next() {
+        match settings.parse(&arg, &mut args) {
+            Ok(true) => continue,
+            Ok(false) => {},
+            Err(err) => usage_error(&err),
+        }
+        match arg.as_str() {
+            "--load-state" => {
+                load_state = match args.next() {
+                    Some(file) => Some(file),
+                    None => usage_error("--load-state requires a FILE"),
+                };
+            },
+            "--rewind-mb" => {
+                rewind_budget = match args.next()
+                    .and_then(|value| value.parse::<usize>().ok()) {
+                    Some(megabytes) => megabytes * 1024 * 1024,
+                    None => usage_error("--rewind-mb requires a number"),
+                };
+            },
+            "--seed" => {
+                seed = match args.next().and_then(|value| value.parse().ok()) {
+                    Some(seed) => Some(seed),
+                    None => usage_error("--seed requires a number"),
+                };
+            },
+            "--record" => {
+                record = match args.next() {
+                    Some(file) => Some(file),
+                    None => usage_error("--record requires a FILE"),
+                };
+            },
+            "--play" => {
+                play = match args.next() {
+                    Some(file) => Some(file),
+                    None => usage_error("--play requires a FILE"),
+                };
+            },
+            "--scale" => {
+                integer_scaling = match args.next().as_deref() {
+                    Some("integer") => true,
+                    Some("aspect") => false,
+                    _ => usage_error("--scale requires integer or aspect"),
+                };
+            },
+            "--fullscreen" => fullscreen = true,
+            "--palette" => {
+                settings.palette = match args.next()
+                    .map(|text| Palette::parse(&text)) {
+                    Some(Ok(palette)) => Some(palette),
+                    Some(Err(err)) => {
+                        usage_error(&format!("--palette: {}", err))
+                    },
+                    None => usage_error("--palette requires a PALETTE"),
+                };
+            },
+            "--filter" => {
+                filter_mode = match args.next()
+                    .and_then(|name| FilterMode::from_name(&name)) {
+                    Some(mode) => mode,
+                    None => usage_error(&format!("--filter requires one of {}",
+                                                 filter::MODES.join(", "))),
+                };
+            },
+            "--filter-strength" => {
+                filter_strength = match args.next()
+                    .and_then(|value| value.parse::<u32>().ok())
+                    .filter(|&percent| percent <= 100) {
+                    Some(percent) => percent,
+                    None => {
+                        usage_error("--filter-strength requires a percentage")
+                    },
+                };
+            },
+            "--config" => {
+                // Already read by `config_args`
+                args.next();
+            },
+            _ if arg.starts_with("--") => {
+                usage_error(&format!("unknown option {}", arg));
+            },
+            _ if program.is_none() => program = Some(arg),
+            _ => usage_error("only one PROGRAM may be given"),
+        }
     }
 
-    env_logger::init().unwrap();
+    // A movie replays a run from power-on with its own seed
+    if record.is_some() && play.is_some() {
+        usage_error("--record and --play can't be used together");
+    }
+    if (record.is_some() || play.is_some()) && load_state.is_some() {
+        usage_error("movies can't start from a saved state");
+    }
+    if play.is_some() && seed.is_some() {
+        usage_error("--seed has no effect when playing a movie");
+    }
+
+    match program {
+        Some(program) => Options {
+            program,
+            settings,
+            load_state,
+            rewind_budget,
+            seed,
+            record,
+            play,
+            integer_scaling,
+            fullscreen,
+            filter: Filter::new(filter_mode, filter_strength as f32 / 100.0),
+        },
+        None => usage_error("no PROGRAM given"),
+    }
+}
+
+/// Read the emulator's default options from the config file named by
+/// `--config` in `args`, or the default config file if it exists, as command
+/// line arguments to go before `args`.
+fn config_args(args: &[String]) -> Vec<String> {
+    let explicit = args.iter()
+        .position(|arg| arg == "--config")
+        .map(|i| match args.get(i + 1) {
+            Some(file) => PathBuf::from(file),
+            None => usage_error("--config requires a FILE"),
+        });
+    let path = match explicit.clone().or_else(default_config_path) {
+        Some(path) => path,
+        None => return Vec::new(),
+    };
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(_) if explicit.is_none() => return Vec::new(),
+        Err(err) => fatal(&format!("couldn't read {}: {}", path.display(),
+                                   err)),
+    };
+
+    let mut config = Vec::new();
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut parts = line.splitn(2, '=');
+        let name = parts.next().unwrap_or("").trim();
+        config.push(format!("--{}", name));
+        if let Some(value) = parts.next() {
+            config.push(value.trim().to_string());
+        }
+    }
+    config
+}
+
+/// `$XDG_CONFIG_HOME/chip8-rust/config`, falling back to
+/// `~/.config/chip8-rust/config`.
+fn default_config_path() -> Option<PathBuf> {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| {
+            env::var_os("HOME").map(|home| Path::new(&home).join(".config"))
+        })?;
+    Some(config_home.join("chip8-rust").join("config"))
+}
+
+/// Parse the arguments following `disasm`.
+fn parse_disasm_args<I: Iterator<Item = String>>(mut args: I) -> DisasmOptions {
+    let mut rom = None;
+    let mut origin = disasm::DEFAULT_ORIGIN;
+    let mut output = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--origin" => {
+                origin = match args.next()
+                    .and_then(|addr| parse_hex(&addr)) {
+                    Some(addr) => addr,
+                    None => usage_error("--origin requires a hex address"),
+                };
+            },
+            "-o" | "--output" => {
+                output = match args.next() {
+                    Some(file) => Some(file),
+                    None => usage_error("--output requires a FILE"),
+                };
+            },
+            _ if arg.starts_with('-') => {
+                usage_error(&format!("unknown option {}", arg));
+            },
+            _ if rom.is_none() => rom = Some(arg),
+            _ => usage_error("only one ROM may be given"),
+        }
+    }
+
+    match rom {
+        Some(rom) => DisasmOptions { rom, origin, output },
+        None => usage_error("no ROM given"),
+    }
+}
+
+/// Parse the arguments following `asm`.
+fn parse_asm_args<I: Iterator<Item = String>>(mut args: I) -> AsmOptions {
+    let mut source = None;
+    let mut output = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--output" => {
+                output = match args.next() {
+                    Some(file) => Some(file),
+                    None => usage_error("--output requires a FILE"),
+                };
+            },
+            _ if arg.starts_with('-') => {
+                usage_error(&format!("unknown option {}", arg));
+            },
+            _ if source.is_none() => source = Some(arg),
+            _ => usage_error("only one SOURCE may be given"),
+        }
+    }
+
+    match source {
+        Some(source) => AsmOptions { source, output },
+        None => usage_error("no SOURCE given"),
+    }
+}
+
+/// Parse a hex number, with or without a `0x` prefix.
+fn parse_hex(text: &str) -> Option<u16> {
+    let digits = text.trim_start_matches("0x").trim_start_matches("0X");
+    u16::from_str_radix(digits, 16).ok()
+}
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("chip8-rust: {}\n{}", message, USAGE);
+    process::exit(1);
+}
+
+fn fatal(message: &str) -> ! {
+    eprintln!("chip8-rust: {}", message);
+    process::exit(1);
+}
+
+/// Path of the file holding the RPL user flags persisted for `program`.
+#[cfg(feature = "sdl-frontend")]
+fn rpl_path(program: &str) -> String {
+    format!("{}.rpl", program)
+}
+
+/// Load the RPL user flags persisted for `program`, if any.
+#[cfg(feature = "sdl-frontend")]
+fn load_rpl_flags(program: &str) -> Option<[u8; NUM_RPL_FLAGS]> {
+    let bytes = fs::read(rpl_path(program)).ok()?;
+    let mut flags = [0; NUM_RPL_FLAGS];
+    let len = bytes.len().min(NUM_RPL_FLAGS);
+    flags[..len].copy_from_slice(&bytes[..len]);
+    Some(flags)
+}
+
+/// Path of the file holding save state `slot` for `program`.
+#[cfg(feature = "sdl-frontend")]
+fn state_path(program: &str, slot: u8) -> String {
+    format!("{}.state{}", program, slot)
+}
+
+/// The save state slot selected by function key `keycode`, if any.
+#[cfg(feature = "sdl-frontend")]
+fn state_slot(keycode: sdl2::keyboard::Keycode) -> Option<u8> {
+    use sdl2::keyboard::Keycode;
+
+    let slots = [Keycode::F1, Keycode::F2, Keycode::F3, Keycode::F4,
+                 Keycode::F5, Keycode::F6, Keycode::F7, Keycode::F8,
+                 Keycode::F9];
+    slots.iter()
+        .position(|&slot| slot == keycode)
+        .map(|slot| slot as u8 + 1)
+}
+
+/// Save `chip8` to, or restore it from, save state `slot` for `program`.
+#[cfg(feature = "sdl-frontend")]
+fn switch_state(chip8: &mut chip8::Chip8, program: &str, slot: u8, save: bool) {
+    use chip8::state;
+
+    let path = state_path(program, slot);
+    if save {
+        match state::save_file(chip8, &path) {
+            Ok(()) => info!("Saved state to {}", path),
+            Err(err) => warn!("Couldn't save {}: {}", path, err),
+        }
+    } else {
+        match state::load_file(chip8, &path) {
+            Ok(()) => info!("Loaded state from {}", path),
+            Err(err) => warn!("Couldn't load {}: {}", path, err),
+        }
+    }
+}
+
+/// Disassemble a ROM to a listing.
+fn run_disasm(options: DisasmOptions) {
+    let rom = match fs::read(&options.rom) {
+        Ok(rom) => rom,
+        Err(err) => fatal(&format!("couldn't read {}: {}", options.rom, err)),
+    };
+    let listing = disasm::disassemble(&rom, options.origin).to_string();
+    match options.output {
+        Some(ref file) => {
+            if let Err(err) = fs::write(file, listing) {
+                fatal(&format!("couldn't write {}: {}", file, err));
+            }
+        },
+        None => print!("{}", listing),
+    }
+}
+
+/// Assemble a source file to a ROM.
+fn run_asm(options: AsmOptions) {
+    let rom = match asm::assemble_file(&options.source) {
+        Ok(rom) => rom,
+        Err(err) => fatal(&err.to_string()),
+    };
+    let output = match options.output {
+        Some(output) => output,
+        None => Path::new(&options.source)
+            .with_extension("ch8")
+            .to_string_lossy()
+            .into_owned(),
+    };
+    if let Err(err) = fs::write(&output, rom) {
+        fatal(&format!("couldn't write {}: {}", output, err));
+    }
+}
+
+#[cfg(not(feature = "sdl-frontend"))]
+fn run_emulator(_options: Options) {
+    fatal("built without a display; rebuild with --features sdl-frontend");
+}
+
+/// Run the program in a window until it exits or the window is closed.
+#[cfg(feature = "sdl-frontend")]
+fn run_emulator(options: Options) {
+    use sdl2::event::Event;
+    use sdl2::keyboard::{Keycode, Mod};
+    use chip8::{state, Keypad};
+    use chip8::audio::AudioSink;
+    use chip8::movie::Movie;
+    use chip8::rewind::Rewind;
+    use chip8::timing::FrameClock;
 
     // Initialize Chip8
-    let mut chip8 = chip8::Chip8::new();
-    let program = env::args().nth(1).unwrap();
+    let (mut chip8, settings) = match config::load(&options.program,
+                                                   &options.settings) {
+        Ok(loaded) => loaded,
+        Err(err) => {
+            fatal(&format!("couldn't load {}: {}", options.program, err))
+        },
+    };
+    let palette = settings.palette;
+
+    if let Some(seed) = options.seed {
+        chip8.set_seed(seed);
+    }
+
+    // Persisted RPL flags would make a movie depend on earlier runs, so
+    // movies neither load nor save them
+    let movie_mode = options.record.is_some() || options.play.is_some();
+    let initial_rpl = if movie_mode {
+        [0x0; NUM_RPL_FLAGS]
+    } else {
+        load_rpl_flags(&options.program).unwrap_or_default()
+    };
+    chip8.set_rpl_flags(&initial_rpl);
 
-    chip8.load_font_set();
-    chip8.load_program(program);
+    if let Some(ref file) = options.load_state {
+        if let Err(err) = state::load_file(&mut chip8, file) {
+            fatal(&format!("couldn't load state {}: {}", file, err));
+        }
+    }
 
-    // Initialize window and renderer
+    let mut recording = options.record.as_ref().map(|_| Movie::new(&chip8));
+    let mut playback = options.play.as_ref().map(|file| {
+        let movie = match Movie::load(file) {
+            Ok(movie) => movie,
+            Err(err) => fatal(&format!("couldn't load movie {}: {}", file, err)),
+        };
+        if let Err(err) = movie.start(&mut chip8) {
+            fatal(&format!("couldn't play movie {}: {}", file, err));
+        }
+        movie
+    });
+
+    // Initialize window and display
     let sdl_context = sdl2::init().unwrap();
     let video_subsystem = sdl_context.video().unwrap();
-    let window = video_subsystem.window("chip8-rust",
-                                        64 * display::PIXEL_SIZE,
-                                        32 * display::PIXEL_SIZE)
+    let title = match settings.info {
+        Some(ref info) => format!("{} - chip8-rust", info.title),
+        None => "chip8-rust".to_string(),
+    };
+    let window = video_subsystem.window(&title,
+                                        display::WINDOW_WIDTH,
+                                        display::WINDOW_HEIGHT)
         .position_centered()
+        .resizable()
         .opengl()
         .build()
         .unwrap();
-    let mut renderer = window.renderer().build().unwrap();
+    let canvas = window.into_canvas().build().unwrap();
+    let texture_creator = canvas.texture_creator();
+    let scaling = if options.integer_scaling {
+        display::Scaling::Integer
+    } else {
+        display::Scaling::Aspect
+    };
 
-    renderer.set_draw_color(Color::RGB(0, 0, 0));
-    renderer.clear();
-    renderer.present();
+    // Cycling starts from the first preset if the palette is a custom one
+    let mut preset = palette::PRESETS.iter()
+        .position(|&name| Palette::preset(name) == Some(palette))
+        .unwrap_or(palette::PRESETS.len() - 1);
+    let mut display = match display::Display::new(canvas, &texture_creator,
+                                                  scaling, palette,
+                                                  options.filter) {
+        Ok(display) => display,
+        Err(err) => fatal(&format!("couldn't create display: {}", err)),
+    };
+    if options.fullscreen {
+        display.toggle_fullscreen();
+    }
+    display.update(chip8.framebuffer(), chip8.width(), chip8.height());
     let mut event_pump = sdl_context.event_pump().unwrap();
 
+    // Carry on without sound if no audio device is available
+    let mut audio = match sdl_context.audio()
+        .and_then(|audio_subsystem| sound::SdlAudioSink::new(&audio_subsystem)) {
+        Ok(audio) => Some(audio),
+        Err(err) => {
+            warn!("Couldn't open audio device: {}", err);
+            None
+        },
+    };
+
     let mut pause_emulation = false;
+    let mut rewinding = false;
+    let mut history = Rewind::new(options.rewind_budget);
+    let mut keys = Keypad::new();
+    let mut frame = 0;
+    let mut clock = FrameClock::new();
 
-    // Main loop
+    // Main loop, one iteration per 60 Hz frame
     'running: loop {
         for event in event_pump.poll_iter() {
             match event {
@@ -59,20 +586,128 @@ fn main() {
                 Event::KeyDown { keycode: Some(Keycode::LCtrl), .. } => {
                     pause_emulation = !pause_emulation;
                 },
-                _ => input::scan_keyboard(&mut chip8, event),
+                Event::KeyDown { keycode: Some(Keycode::F10),
+                                 repeat: false, .. } => {
+                    preset = (preset + 1) % palette::PRESETS.len();
+                    let name = palette::PRESETS[preset];
+                    info!("Palette: {}", name);
+                    display.set_palette(Palette::preset(name).unwrap());
+                    display.update(chip8.framebuffer(), chip8.width(),
+                                   chip8.height());
+                },
+                Event::KeyDown { keycode: Some(Keycode::F11),
+                                 repeat: false, .. } => {
+                    display.toggle_fullscreen();
+                },
+                Event::KeyDown { keycode: Some(Keycode::Backspace), .. }
+                    if playback.is_none() => {
+                    rewinding = true;
+                },
+                Event::KeyUp { keycode: Some(Keycode::Backspace), .. } => {
+                    rewinding = false;
+                },
+                Event::KeyDown { keycode: Some(keycode), keymod,
+                                 repeat: false, .. }
+                    if state_slot(keycode).is_some() => {
+                    let slot = state_slot(keycode).unwrap();
+                    let save = keymod.intersects(Mod::LSHIFTMOD |
+                                                 Mod::RSHIFTMOD);
+                    if save || (recording.is_none() && playback.is_none()) {
+                        switch_state(&mut chip8, &options.program, slot, save);
+                    } else {
+                        warn!("Can't load a state while a movie is running");
+                    }
+                },
+                _ => input::scan_keyboard(&mut keys, event),
             }
         }
 
-        if !pause_emulation {
-            chip8.execute_cycle();
+        // Rewind one frame per frame held, or snapshot and run one. Rewinding
+        // while recording takes back the rewound frames.
+        if rewinding {
+            if history.pop(&mut chip8) {
+                frame -= 1;
+                if let Some(ref mut movie) = recording {
+                    movie.truncate(frame);
+                }
+            }
+        } else if !pause_emulation {
+            // Hand the live keys over once the movie has played out
+            let movie_keys = playback.as_ref().and_then(|movie| {
+                movie.frame(frame)
+            });
+            if playback.is_some() && movie_keys.is_none() {
+                info!("Movie finished after {} frames", frame);
+                playback = None;
+            }
+            chip8.set_keypad(movie_keys.as_ref().unwrap_or(&keys));
+            if let Some(ref mut movie) = recording {
+                movie.record(&keys);
+            }
+
+            history.push(&chip8);
+            frame += 1;
+            if let Err(err) = chip8.run_frame() {
+                error!("{}\n{:#?}", err, chip8);
+                pause_emulation = true;
+            }
 
-            if chip8.redraw {
-                display::render(&chip8.fb, &mut renderer);
-                chip8.redraw = false;
+            if chip8.has_exited() {
+                break 'running;
             }
             debug!("{:#?}\n", chip8);
-            sleep(Duration::from_millis(2));
+        }
+
+        // Redraw every frame, so resizing the window repaints it
+        if chip8.needs_redraw() || display.is_filtered() {
+            display.update(chip8.framebuffer(), chip8.width(), chip8.height());
+            chip8.clear_redraw();
+        }
+        display.present();
+
+        if let Some(ref mut audio) = audio {
+            let silent = pause_emulation || rewinding;
+            audio.frame(if silent { None } else { chip8.tone() });
+        }
+
+        clock.wait();
+    }
+
+    if let (Some(movie), Some(file)) = (recording, options.record) {
+        if let Err(err) = movie.save(&file) {
+            error!("Couldn't save movie {}: {}", file, err);
+        }
+    }
+
+    // Persist the RPL user flags if the program changed them
+    if !movie_mode && *chip8.rpl_flags() != initial_rpl {
+        if let Err(err) = fs::write(rpl_path(&options.program),
+                                    chip8.rpl_flags()) {
+            warn!("Couldn't save RPL flags: {}", err);
         }
     }
 
 }
+
+fn main() {
+    let mut args = env::args().skip(1).peekable();
+    let subcommand = args.peek().cloned();
+
+    env_logger::init().unwrap();
+
+    match subcommand.as_deref() {
+        Some("disasm") => {
+            args.next();
+            run_disasm(parse_disasm_args(args));
+        },
+        Some("asm") => {
+            args.next();
+            run_asm(parse_asm_args(args));
+        },
+        _ => {
+            let args: Vec<String> = args.collect();
+            let config = config_args(&args);
+            run_emulator(parse_args(config.into_iter().chain(args)));
+        },
+    }
+}
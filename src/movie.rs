@@ -0,0 +1,227 @@
+//! Movies: recorded input for replaying a run exactly.
+//!
+//! A run is determined by the ROM, the interpreter settings and font, the
+//! random seed and the keys held during each frame. A movie records all of
+//! them, so playing it back on a freshly loaded machine reproduces the run
+//! frame for frame. Key changes only take effect at frame boundaries, in
+//! recording and playback alike.
+//!
+//! The file is `MAGIC`, the format `VERSION`, the ROM's SHA-1 hash, the seed,
+//! the instructions per frame, the quirk flags, the small and large digits of
+//! the font and then the keypad state of each frame as a 16-bit mask.
+//! Multi-byte values are stored big-endian.
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use chip8::Chip8;
+use font::{Font, BIG_FONT_SIZE, SMALL_FONT_SIZE};
+use keypad::Keypad;
+use quirks::Quirks;
+use state::{RomHash, ROM_HASH_SIZE};
+
+/// Bytes every movie file starts with.
+pub const MAGIC: &[u8; 8] = b"CHIP8MOV";
+
+/// Version of the movie format written by this build.
+pub const VERSION: u16 = 2;
+
+/// Size of the header preceding the frames, in bytes.
+const HEADER_SIZE: usize = 8 + 2 + ROM_HASH_SIZE + 8 + 4 + 1 +
+                           SMALL_FONT_SIZE + BIG_FONT_SIZE;
+
+/// An error raised while saving, loading or starting a movie.
+#[derive(Debug)]
+pub enum MovieError {
+    /// The movie file couldn't be read or written.
+    Io(io::Error),
+
+    /// The data isn't a movie file.
+    BadMagic,
+
+    /// The movie was written by an incompatible version of the format.
+    UnsupportedVersion(u16),
+
+    /// The movie was recorded with a different ROM.
+    RomMismatch,
+
+    /// The movie is truncated.
+    Corrupt,
+}
+
+impl fmt::Display for MovieError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MovieError::Io(ref err) => write!(f, "{}", err),
+            MovieError::BadMagic => write!(f, "not a movie"),
+            MovieError::UnsupportedVersion(version) => {
+                write!(f, "unsupported movie version {}", version)
+            },
+            MovieError::RomMismatch => {
+                write!(f, "movie was recorded with a different ROM")
+            },
+            MovieError::Corrupt => write!(f, "corrupt movie"),
+        }
+    }
+}
+
+impl Error for MovieError {}
+
+impl From<io::Error> for MovieError {
+    fn from(err: io::Error) -> MovieError {
+        MovieError::Io(err)
+    }
+}
+
+/// A recorded run.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Movie {
+    rom_hash: RomHash,
+    seed: u64,
+    instructions_per_frame: u32,
+    quirks: Quirks,
+    font: Font,
+
+    // Keypad state of each frame, as returned by `Keypad::bits`
+    frames: Vec<u16>,
+}
+
+impl Movie {
+    /// Start recording a run of `chip8`, which should have just loaded its
+    /// ROM and not yet executed anything.
+    pub fn new(chip8: &Chip8) -> Movie {
+        Movie {
+            rom_hash: *chip8.rom_hash(),
+            seed: chip8.seed(),
+            instructions_per_frame: chip8.instructions_per_frame(),
+            quirks: chip8.quirks(),
+            font: chip8.font(),
+            frames: Vec::new(),
+        }
+    }
+
+    /// Record the keys held during the next frame.
+    pub fn record(&mut self, keypad: &Keypad) {
+        self.frames.push(keypad.bits());
+    }
+
+    /// Drop every frame after the first `len`, as when the run is rewound.
+    pub fn truncate(&mut self, len: usize) {
+        self.frames.truncate(len);
+    }
+
+    /// Number of frames recorded.
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Whether no frames have been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// The keys held during frame `n`, or `None` past the end of the movie.
+    pub fn frame(&self, n: usize) -> Option<Keypad> {
+        self.frames.get(n).map(|&bits| Keypad::from_bits(bits))
+    }
+
+    /// Prepare `chip8`, which should have just loaded its ROM, to play the
+    /// movie back: apply the recorded seed, settings and font. Fails if the
+    /// movie was recorded with a different ROM.
+    pub fn start(&self, chip8: &mut Chip8) -> Result<(), MovieError> {
+        if *chip8.rom_hash() != self.rom_hash {
+            return Err(MovieError::RomMismatch);
+        }
+        chip8.set_seed(self.seed);
+        chip8.set_instructions_per_frame(self.instructions_per_frame);
+        chip8.set_quirks(self.quirks);
+        chip8.load_font(&self.font);
+        Ok(())
+    }
+
+    /// Serialize the movie.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let quirks = &self.quirks;
+        let flags = [quirks.shift, quirks.load_store, quirks.jump,
+                     quirks.clip, quirks.vf_reset, quirks.load_store_x];
+
+        let mut bytes = Vec::with_capacity(HEADER_SIZE + 2 * self.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_be_bytes());
+        bytes.extend_from_slice(&self.rom_hash);
+        bytes.extend_from_slice(&self.seed.to_be_bytes());
+        bytes.extend_from_slice(&self.instructions_per_frame.to_be_bytes());
+        bytes.push(flags.iter()
+            .enumerate()
+            .fold(0, |mask, (bit, &flag)| mask | (flag as u8) << bit));
+        bytes.extend_from_slice(&self.font.small);
+        bytes.extend_from_slice(&self.font.big);
+        for &frame in &self.frames {
+            bytes.extend_from_slice(&frame.to_be_bytes());
+        }
+        bytes
+    }
+
+    /// Deserialize a movie written by `to_bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Movie, MovieError> {
+        if !bytes.starts_with(MAGIC) {
+            return Err(MovieError::BadMagic);
+        }
+        if bytes.len() < HEADER_SIZE ||
+           !(bytes.len() - HEADER_SIZE).is_multiple_of(2) {
+            return Err(MovieError::Corrupt);
+        }
+        let u16_at = |at: usize| (bytes[at] as u16) << 8 | bytes[at + 1] as u16;
+        let u32_at = |at: usize| {
+            (u16_at(at) as u32) << 16 | u16_at(at + 2) as u32
+        };
+
+        let version = u16_at(8);
+        if version != VERSION {
+            return Err(MovieError::UnsupportedVersion(version));
+        }
+        let mut rom_hash = [0x0; ROM_HASH_SIZE];
+        rom_hash.copy_from_slice(&bytes[10..10 + ROM_HASH_SIZE]);
+        let at = 10 + ROM_HASH_SIZE;
+        let seed = (u32_at(at) as u64) << 32 | u32_at(at + 4) as u64;
+        let instructions_per_frame = u32_at(at + 8);
+        let flags = bytes[at + 12];
+        let quirks = Quirks {
+            shift: flags & 0x01 != 0,
+            load_store: flags & 0x02 != 0,
+            jump: flags & 0x04 != 0,
+            clip: flags & 0x08 != 0,
+            vf_reset: flags & 0x10 != 0,
+            load_store_x: flags & 0x20 != 0,
+        };
+        let font = Font::from_bytes(&bytes[at + 13..HEADER_SIZE])
+            .ok_or(MovieError::Corrupt)?;
+        let frames = (HEADER_SIZE..bytes.len())
+            .step_by(2)
+            .map(u16_at)
+            .collect();
+
+        Ok(Movie {
+            rom_hash,
+            seed,
+            instructions_per_frame,
+            quirks,
+            font,
+            frames,
+        })
+    }
+
+    /// Write the movie to the file at `path`.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), MovieError> {
+        fs::write(path, self.to_bytes())?;
+        Ok(())
+    }
+
+    /// Read a movie from the file at `path`.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Movie, MovieError> {
+        Movie::from_bytes(&fs::read(path)?)
+    }
+}
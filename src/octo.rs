@@ -0,0 +1,820 @@
+//! A compiler for programs written in Octo's language.
+//!
+//! Octo cartridges hold their program as Octo source, so loading one means
+//! compiling it. The common core of the language is supported:
+//!
+//! ```text
+//! # Comments run from a hash to the end of the line
+//! :const SPEED 4
+//! :alias x v1
+//!
+//! : main
+//!     x := 0
+//!     i := ship
+//!     loop
+//!         sprite x v2 4
+//!         x += SPEED
+//!         if x == 60 then x := 0
+//!         if v3 key begin
+//!             jump main
+//!         end
+//!     again
+//!
+//! : ship
+//!     0x18 0x3C 0x7E 0xFF
+//! ```
+//!
+//! That is labels, `:const`, `:alias`, `:org` and `:byte`, every
+//! instruction, `if ... then`, `if ... begin ... else ... end`, and
+//! `loop ... while ... again`. Macros, `:calc`, `:next`, `:unpack`, strings
+//! and the other directives aren't, and are reported as
+//! `OctoError::Unsupported` rather than as syntax errors. As in Octo, the
+//! program starts with a jump to `main`.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use asm::{AsmError, INPUT_NAME};
+use disasm::DEFAULT_ORIGIN;
+use instruction::Instruction;
+
+/// An error compiling an Octo program.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OctoError {
+    /// The program is invalid.
+    Syntax(AsmError),
+
+    /// The program uses `feature`, a part of Octo's language that isn't
+    /// supported, on line `line`.
+    Unsupported { line: usize, feature: String },
+}
+
+impl fmt::Display for OctoError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            OctoError::Syntax(ref err) => write!(f, "{}", err),
+            OctoError::Unsupported { line, ref feature } => {
+                write!(f, "{}:{}: unsupported Octo feature {}", INPUT_NAME,
+                       line, feature)
+            },
+        }
+    }
+}
+
+impl Error for OctoError {}
+
+impl From<AsmError> for OctoError {
+    fn from(err: AsmError) -> OctoError {
+        OctoError::Syntax(err)
+    }
+}
+
+/// Compile `source` into a ROM to be loaded at 0x200.
+pub fn compile(source: &str) -> Result<Vec<u8>, OctoError> {
+    let mut tokens = Vec::new();
+    for (n, line) in source.lines().enumerate() {
+        let code = line.split('#').next().unwrap_or("");
+        tokens.extend(code.split_whitespace().map(|token| (token, n + 1)));
+    }
+
+    let mut compiler = Compiler::new(tokens);
+    compiler.compile()?;
+    Ok(compiler.finish()?)
+}
+
+/// A comparison in an `if` or `while`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Test {
+    Eq,
+    Ne,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+    Key,
+    NotKey,
+}
+
+impl Test {
+    fn negate(self) -> Test {
+        match self {
+            Test::Eq => Test::Ne,
+            Test::Ne => Test::Eq,
+            Test::Lt => Test::Ge,
+            Test::Ge => Test::Lt,
+            Test::Gt => Test::Le,
+            Test::Le => Test::Gt,
+            Test::Key => Test::NotKey,
+            Test::NotKey => Test::Key,
+        }
+    }
+}
+
+/// The right-hand side of an operation: a register or a byte.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Operand {
+    V(u8),
+    Byte(u8),
+}
+
+/// An open `begin`, `else` or `loop`, waiting for the word that closes it.
+enum Block {
+    // Address of the jump past the `begin` block
+    Begin(usize),
+
+    // Address of the jump past the `else` block
+    Else(usize),
+
+    // Address the loop starts at, and those of the jumps out of it
+    Loop(usize, Vec<usize>),
+}
+
+/// How to patch an address into an instruction once its label is known.
+#[derive(Clone, Copy)]
+enum Fixup {
+    // The low 12 bits of the instruction
+    Nnn,
+
+    // The 16-bit word after F000
+    Long,
+}
+
+struct Compiler<'a> {
+    tokens: Vec<(&'a str, usize)>,
+    pos: usize,
+
+    // The program, starting at `DEFAULT_ORIGIN`, and where the next byte goes
+    rom: Vec<u8>,
+    here: usize,
+
+    labels: HashMap<String, usize>,
+    constants: HashMap<String, i64>,
+    aliases: HashMap<String, u8>,
+
+    // References to labels not yet defined, with the address to patch and
+    // the line they're on
+    fixups: Vec<(String, usize, Fixup, usize)>,
+    blocks: Vec<(Block, usize)>,
+}
+
+impl<'a> Compiler<'a> {
+    fn new(tokens: Vec<(&'a str, usize)>) -> Compiler<'a> {
+        Compiler {
+            tokens,
+            pos: 0,
+            rom: Vec::new(),
+            here: DEFAULT_ORIGIN as usize,
+            labels: HashMap::new(),
+            constants: HashMap::new(),
+            aliases: HashMap::new(),
+            fixups: Vec::new(),
+            blocks: Vec::new(),
+        }
+    }
+
+    /// The line of the token just read.
+    fn line(&self) -> usize {
+        self.tokens.get(self.pos.saturating_sub(1))
+            .map_or(0, |&(_, line)| line)
+    }
+
+    /// An error at the token just read.
+    fn error<S: Into<String>>(&self, message: S) -> AsmError {
+        AsmError {
+            file: INPUT_NAME.to_string(),
+            line: self.line(),
+            message: message.into(),
+        }
+    }
+
+    /// An error for the unsupported `feature` at the token just read.
+    fn unsupported(&self, feature: &str) -> OctoError {
+        OctoError::Unsupported {
+            line: self.line(),
+            feature: feature.to_string(),
+        }
+    }
+
+    fn next(&mut self) -> Option<&'a str> {
+        let token = self.tokens.get(self.pos).map(|&(token, _)| token);
+        self.pos += 1;
+        token
+    }
+
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.pos).map(|&(token, _)| token)
+    }
+
+    fn expect(&mut self, what: &str) -> Result<&'a str, AsmError> {
+        match self.next() {
+            Some(token) => Ok(token),
+            None => Err(self.error(format!("expected {} at end of input",
+                                           what))),
+        }
+    }
+
+    fn expect_word(&mut self, word: &str) -> Result<(), AsmError> {
+        let token = self.expect(word)?;
+        if token != word {
+            return Err(self.error(format!("expected {}, found {}", word,
+                                          token)));
+        }
+        Ok(())
+    }
+
+    fn compile(&mut self) -> Result<(), OctoError> {
+        // Room for the jump to main
+        self.emit(Instruction::JpAddr(0x0))?;
+        while let Some(token) = self.next() {
+            self.statement(token)?;
+        }
+        if let Some(&(_, line)) = self.blocks.last() {
+            return Err(OctoError::Syntax(AsmError {
+                file: INPUT_NAME.to_string(),
+                line,
+                message: "block isn't closed".to_string(),
+            }));
+        }
+        Ok(())
+    }
+
+    fn statement(&mut self, token: &'a str) -> Result<(), OctoError> {
+        match token {
+            ":" => {
+                let name = self.expect("a label name")?;
+                self.define(name, self.here)?;
+            },
+            ":const" => {
+                let name = self.expect("a constant name")?;
+                let value = self.expect("a value")?;
+                let value = self.value(value)?;
+                self.define_constant(name, value)?;
+            },
+            ":alias" => {
+                let name = self.expect("an alias name")?;
+                let reg = self.expect("a register")?;
+                let reg = self.register(reg)?;
+                self.check_name(name)?;
+                self.aliases.insert(name.to_string(), reg);
+            },
+            ":org" => {
+                let addr = self.expect("an address")?;
+                let addr = self.value(addr)?;
+                if addr < DEFAULT_ORIGIN as i64 || addr > 0xFFFF {
+                    return Err(self.error(format!("can't place code at {}",
+                                                  addr)).into());
+                }
+                self.here = addr as usize;
+            },
+            ":byte" => {
+                if self.peek() == Some("{") {
+                    return Err(self.unsupported(":byte { expression }"));
+                }
+                let value = self.expect("a byte")?;
+                let byte = self.byte(value)?;
+                self.emit_bytes(&[byte])?;
+            },
+            "clear" => self.emit(Instruction::Cls)?,
+            "return" | ";" => self.emit(Instruction::Ret)?,
+            "exit" => self.emit(Instruction::Exit)?,
+            "hires" => self.emit(Instruction::High)?,
+            "lores" => self.emit(Instruction::Low)?,
+            "scroll-left" => self.emit(Instruction::Scl)?,
+            "scroll-right" => self.emit(Instruction::Scr)?,
+            "scroll-down" => {
+                let n = self.nibble_arg()?;
+                self.emit(Instruction::ScdNib(n))?;
+            },
+            "scroll-up" => return Err(self.unsupported(token)),
+            "audio" => self.emit(Instruction::LdPatternIndex)?,
+            "plane" => {
+                let n = self.nibble_arg()?;
+                self.emit(Instruction::PlaneNib(n))?;
+            },
+            "jump" => {
+                let target = self.expect("an address")?;
+                self.emit_jump(Instruction::JpAddr(0x0), target)?;
+            },
+            "jump0" => {
+                let target = self.expect("an address")?;
+                self.emit_jump(Instruction::JpV0Addr(0x0), target)?;
+            },
+            "sprite" => {
+                let x = self.register_arg()?;
+                let y = self.register_arg()?;
+                let n = self.nibble_arg()?;
+                self.emit(Instruction::DrwVxVyNib(x, y, n))?;
+            },
+            "bcd" => {
+                let x = self.register_arg()?;
+                self.emit(Instruction::LdBcdVx(x))?;
+            },
+            "save" | "load" => {
+                let x = self.register_arg()?;
+                let store = token == "save";
+                let instruction = if self.peek() == Some("-") {
+                    self.next();
+                    let y = self.register_arg()?;
+                    if store {
+                        Instruction::LdIndexVxVy(x, y)
+                    } else {
+                        Instruction::LdVxVyIndex(x, y)
+                    }
+                } else if store {
+                    Instruction::LdIndexImmVx(x)
+                } else {
+                    Instruction::LdVxIndexImm(x)
+                };
+                self.emit(instruction)?;
+            },
+            "saveflags" => {
+                let x = self.register_arg()?;
+                self.emit(Instruction::LdRplVx(x))?;
+            },
+            "loadflags" => {
+                let x = self.register_arg()?;
+                self.emit(Instruction::LdVxRpl(x))?;
+            },
+            "delay" | "buzzer" | "pitch" => {
+                self.expect_word(":=")?;
+                let x = self.register_arg()?;
+                self.emit(match token {
+                    "delay" => Instruction::LdDtVx(x),
+                    "buzzer" => Instruction::LdStVx(x),
+                    _ => Instruction::LdPitchVx(x),
+                })?;
+            },
+            "i" => self.index()?,
+            "if" => {
+                let test = self.test()?;
+                match self.expect("then or begin")? {
+                    "then" => self.emit_skip(test)?,
+                    "begin" => {
+                        let (x, test, rhs) = test;
+                        self.emit_skip((x, test.negate(), rhs))?;
+                        let jump = self.here;
+                        self.emit(Instruction::JpAddr(0x0))?;
+                        self.open(Block::Begin(jump));
+                    },
+                    other => {
+                        return Err(self.error(format!(
+                            "expected then or begin, found {}", other))
+                            .into());
+                    },
+                }
+            },
+            "else" => {
+                match self.blocks.pop() {
+                    Some((Block::Begin(jump), _)) => {
+                        let skip = self.here;
+                        self.emit(Instruction::JpAddr(0x0))?;
+                        let here = self.here;
+                        self.patch(jump, here, Fixup::Nnn)?;
+                        self.open(Block::Else(skip));
+                    },
+                    _ => {
+                        return Err(self.error("else without if ... begin")
+                            .into());
+                    },
+                }
+            },
+            "end" => {
+                match self.blocks.pop() {
+                    Some((Block::Begin(jump), _)) |
+                    Some((Block::Else(jump), _)) => {
+                        let here = self.here;
+                        self.patch(jump, here, Fixup::Nnn)?;
+                    },
+                    _ => {
+                        return Err(self.error("end without if ... begin")
+                            .into());
+                    },
+                }
+            },
+            "loop" => {
+                let start = self.here;
+                self.open(Block::Loop(start, Vec::new()));
+            },
+            "while" => {
+                let (x, test, rhs) = self.test()?;
+                self.emit_skip((x, test.negate(), rhs))?;
+                let jump = self.here;
+                self.emit(Instruction::JpAddr(0x0))?;
+                let exits = self.blocks.iter_mut()
+                    .rev()
+                    .find_map(|&mut (ref mut block, _)| match *block {
+                        Block::Loop(_, ref mut exits) => Some(exits),
+                        _ => None,
+                    });
+                match exits {
+                    Some(exits) => exits.push(jump),
+                    None => {
+                        return Err(self.error("while outside a loop").into());
+                    },
+                }
+            },
+            "again" => {
+                match self.blocks.pop() {
+                    Some((Block::Loop(start, exits), _)) => {
+                        self.emit(Instruction::JpAddr(addr(start)
+                            .map_err(|message| self.error(message))?))?;
+                        let here = self.here;
+                        for exit in exits {
+                            self.patch(exit, here, Fixup::Nnn)?;
+                        }
+                    },
+                    _ => {
+                        return Err(self.error("again without loop").into());
+                    },
+                }
+            },
+            _ if token.starts_with(':') => {
+                return Err(self.unsupported(token));
+            },
+            _ if token.starts_with('"') => {
+                return Err(self.unsupported("strings"));
+            },
+            _ => {
+                if let Ok(x) = self.register(token) {
+                    return Ok(self.assignment(x)?);
+                }
+                if token.starts_with(|c: char| c.is_ascii_digit() ||
+                                             c == '-') ||
+                   self.constants.contains_key(token) {
+                    let byte = self.byte(token)?;
+                    return Ok(self.emit_bytes(&[byte])?);
+                }
+                // Anything else calls a subroutine, which may be defined
+                // further on
+                self.check_name(token)?;
+                self.emit_jump(Instruction::CallAddr(0x0), token)?;
+            },
+        }
+        Ok(())
+    }
+
+    /// `i := ...` or `i += vX`.
+    fn index(&mut self) -> Result<(), AsmError> {
+        match self.expect(":= or +=")? {
+            "+=" => {
+                let x = self.register_arg()?;
+                self.emit(Instruction::AddIndexVx(x))
+            },
+            ":=" => {
+                match self.expect("an address")? {
+                    "hex" => {
+                        let x = self.register_arg()?;
+                        self.emit(Instruction::LdIndexVxSprite(x))
+                    },
+                    "bighex" => {
+                        let x = self.register_arg()?;
+                        self.emit(Instruction::LdIndexVxBigSprite(x))
+                    },
+                    "long" => {
+                        let target = self.expect("an address")?;
+                        let at = self.here;
+                        self.emit(Instruction::LdIndexLong(0x0))?;
+                        self.reference(target, at, Fixup::Long)
+                    },
+                    target => {
+                        self.emit_jump(Instruction::LdIndexAddr(0x0), target)
+                    },
+                }
+            },
+            other => Err(self.error(format!("expected := or += after i, \
+                                             found {}", other))),
+        }
+    }
+
+    /// An operation on register `x`, such as `vX += 1`.
+    fn assignment(&mut self, x: u8) -> Result<(), AsmError> {
+        let op = self.expect("an operator")?;
+        if op == ":=" {
+            match self.peek() {
+                Some("key") => {
+                    self.next();
+                    return self.emit(Instruction::LdVxKey(x));
+                },
+                Some("delay") => {
+                    self.next();
+                    return self.emit(Instruction::LdVxDt(x));
+                },
+                Some("random") => {
+                    self.next();
+                    let mask = self.expect("a mask")?;
+                    let mask = self.byte(mask)?;
+                    return self.emit(Instruction::RndVxByte(x, mask));
+                },
+                _ => {},
+            }
+        }
+
+        let rhs = self.expect("an operand")?;
+        let instruction = match (op, self.operand(rhs)?) {
+            (":=", Operand::V(y)) => Instruction::LdVxVy(x, y),
+            (":=", Operand::Byte(nn)) => Instruction::LdVxByte(x, nn),
+            ("+=", Operand::V(y)) => Instruction::AddVxVy(x, y),
+            ("+=", Operand::Byte(nn)) => Instruction::AddVxByte(x, nn),
+            ("-=", Operand::V(y)) => Instruction::SubVxVy(x, y),
+            ("-=", Operand::Byte(nn)) => {
+                Instruction::AddVxByte(x, nn.wrapping_neg())
+            },
+            ("=-", Operand::V(y)) => Instruction::SubnVxVy(x, y),
+            ("|=", Operand::V(y)) => Instruction::OrVxVy(x, y),
+            ("&=", Operand::V(y)) => Instruction::AndVxVy(x, y),
+            ("^=", Operand::V(y)) => Instruction::XorVxVy(x, y),
+            (">>=", Operand::V(y)) => Instruction::ShrVx(x, y),
+            ("<<=", Operand::V(y)) => Instruction::ShlVx(x, y),
+            ("=-", _) | ("|=", _) | ("&=", _) | ("^=", _) | (">>=", _) |
+            ("<<=", _) => {
+                return Err(self.error(format!("{} requires a register",
+                                              op)));
+            },
+            _ => return Err(self.error(format!("unknown operator {}", op))),
+        };
+        self.emit(instruction)
+    }
+
+    /// The condition of an `if` or `while`.
+    fn test(&mut self) -> Result<(u8, Test, Operand), AsmError> {
+        let x = self.register_arg()?;
+        let test = match self.expect("a comparison")? {
+            "==" => Test::Eq,
+            "!=" => Test::Ne,
+            "<" => Test::Lt,
+            ">" => Test::Gt,
+            "<=" => Test::Le,
+            ">=" => Test::Ge,
+            "key" => return Ok((x, Test::Key, Operand::Byte(0x0))),
+            "-key" => return Ok((x, Test::NotKey, Operand::Byte(0x0))),
+            other => {
+                return Err(self.error(format!("unknown comparison {}",
+                                              other)));
+            },
+        };
+        let rhs = self.expect("an operand")?;
+        Ok((x, test, self.operand(rhs)?))
+    }
+
+    /// Emit code that skips the next instruction unless `test` holds.
+    fn emit_skip(&mut self,
+                 (x, test, rhs): (u8, Test, Operand)) -> Result<(), AsmError> {
+        match (test, rhs) {
+            (Test::Eq, Operand::Byte(nn)) => {
+                self.emit(Instruction::SneVxByte(x, nn))
+            },
+            (Test::Eq, Operand::V(y)) => self.emit(Instruction::SneVxVy(x, y)),
+            (Test::Ne, Operand::Byte(nn)) => {
+                self.emit(Instruction::SeVxByte(x, nn))
+            },
+            (Test::Ne, Operand::V(y)) => self.emit(Instruction::SeVxVy(x, y)),
+            (Test::Key, _) => self.emit(Instruction::SknpVx(x)),
+            (Test::NotKey, _) => self.emit(Instruction::SkpVx(x)),
+            (_, _) => {
+                // Subtract in VF, whose flag is then set if the first operand
+                // is at least the second
+                let (first_is_x, holds_when_set) = match test {
+                    Test::Lt => (true, false),
+                    Test::Ge => (true, true),
+                    Test::Gt => (false, false),
+                    _ => (false, true),
+                };
+                match (rhs, first_is_x) {
+                    (Operand::V(y), true) => {
+                        self.emit(Instruction::LdVxVy(0xF, x))?;
+                        self.emit(Instruction::SubVxVy(0xF, y))?;
+                    },
+                    (Operand::V(y), false) => {
+                        self.emit(Instruction::LdVxVy(0xF, y))?;
+                        self.emit(Instruction::SubVxVy(0xF, x))?;
+                    },
+                    (Operand::Byte(nn), true) => {
+                        self.emit(Instruction::LdVxByte(0xF, nn))?;
+                        self.emit(Instruction::SubnVxVy(0xF, x))?;
+                    },
+                    (Operand::Byte(nn), false) => {
+                        self.emit(Instruction::LdVxByte(0xF, nn))?;
+                        self.emit(Instruction::SubVxVy(0xF, x))?;
+                    },
+                }
+                let skip_when = if holds_when_set { 0x0 } else { 0x1 };
+                self.emit(Instruction::SeVxByte(0xF, skip_when))
+            },
+        }
+    }
+
+    fn open(&mut self, block: Block) {
+        let line = self.tokens.get(self.pos.saturating_sub(1))
+            .map_or(0, |&(_, line)| line);
+        self.blocks.push((block, line));
+    }
+
+    fn define(&mut self, name: &str, addr: usize) -> Result<(), AsmError> {
+        self.check_name(name)?;
+        if self.labels.contains_key(name) || self.constants.contains_key(name) {
+            return Err(self.error(format!("{} is already defined", name)));
+        }
+        self.labels.insert(name.to_string(), addr);
+        Ok(())
+    }
+
+    fn define_constant(&mut self, name: &str,
+                       value: i64) -> Result<(), AsmError> {
+        self.check_name(name)?;
+        if self.labels.contains_key(name) || self.constants.contains_key(name) {
+            return Err(self.error(format!("{} is already defined", name)));
+        }
+        self.constants.insert(name.to_string(), value);
+        Ok(())
+    }
+
+    /// Check that `name` can name a label, constant or alias.
+    fn check_name(&self, name: &str) -> Result<(), AsmError> {
+        let mut chars = name.chars();
+        let valid = chars.next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_') &&
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !valid || self.register(name).is_ok() {
+            return Err(self.error(format!("bad name {}", name)));
+        }
+        Ok(())
+    }
+
+    /// Emit `instruction` with its address taken from `target`.
+    fn emit_jump(&mut self, instruction: Instruction,
+                 target: &str) -> Result<(), AsmError> {
+        let at = self.here;
+        self.emit(instruction)?;
+        self.reference(target, at, Fixup::Nnn)
+    }
+
+    /// Patch the address of `target` into the instruction at `at`, now if
+    /// it's known or once it's defined.
+    fn reference(&mut self, target: &str, at: usize,
+                 fixup: Fixup) -> Result<(), AsmError> {
+        if let Some(&addr) = self.labels.get(target) {
+            return self.patch(at, addr, fixup);
+        }
+        if target.starts_with(|c: char| c.is_ascii_digit()) ||
+           self.constants.contains_key(target) {
+            let value = self.value(target)?;
+            if value < 0 {
+                return Err(self.error(format!("bad address {}", target)));
+            }
+            return self.patch(at, value as usize, fixup);
+        }
+        self.check_name(target)?;
+        let line = self.tokens[self.pos - 1].1;
+        self.fixups.push((target.to_string(), at, fixup, line));
+        Ok(())
+    }
+
+    fn patch(&mut self, at: usize, addr: usize,
+             fixup: Fixup) -> Result<(), AsmError> {
+        let i = at - DEFAULT_ORIGIN as usize;
+        match fixup {
+            Fixup::Nnn => {
+                let addr = self::addr(addr).map_err(|message| {
+                    self.error(message)
+                })?;
+                self.rom[i] = self.rom[i] & 0xF0 | (addr >> 8) as u8;
+                self.rom[i + 1] = addr as u8;
+            },
+            Fixup::Long => {
+                if addr > 0xFFFF {
+                    return Err(self.error("address doesn't fit in 16 bits"));
+                }
+                self.rom[i + 2] = (addr >> 8) as u8;
+                self.rom[i + 3] = addr as u8;
+            },
+        }
+        Ok(())
+    }
+
+    fn emit(&mut self, instruction: Instruction) -> Result<(), AsmError> {
+        self.emit_bytes(&instruction.encode())
+    }
+
+    fn emit_bytes(&mut self, bytes: &[u8]) -> Result<(), AsmError> {
+        let start = self.here - DEFAULT_ORIGIN as usize;
+        let end = start + bytes.len();
+        if self.here + bytes.len() > 0x10000 {
+            return Err(self.error("program doesn't fit in memory"));
+        }
+        if self.rom.len() < end {
+            self.rom.resize(end, 0x0);
+        }
+        self.rom[start..end].copy_from_slice(bytes);
+        self.here += bytes.len();
+        Ok(())
+    }
+
+    /// Resolve the remaining references and return the ROM.
+    fn finish(mut self) -> Result<Vec<u8>, AsmError> {
+        let main = match self.labels.get("main") {
+            Some(&main) => main,
+            None => return Err(AsmError {
+                file: INPUT_NAME.to_string(),
+                line: 0,
+                message: "no main label".to_string(),
+            }),
+        };
+        self.patch(DEFAULT_ORIGIN as usize, main, Fixup::Nnn)?;
+
+        for (name, at, fixup, line) in self.fixups.split_off(0) {
+            let error = |message: String| AsmError {
+                file: INPUT_NAME.to_string(),
+                line,
+                message,
+            };
+            let addr = *self.labels.get(&name)
+                .ok_or_else(|| error(format!("undefined label {}", name)))?;
+            self.patch(at, addr, fixup)
+                .map_err(|err| error(err.message))?;
+        }
+        Ok(self.rom)
+    }
+
+    fn register(&self, token: &str) -> Result<u8, AsmError> {
+        if let Some(&reg) = self.aliases.get(token) {
+            return Ok(reg);
+        }
+        let mut chars = token.chars();
+        match (chars.next(), chars.next(), chars.next()) {
+            (Some('v'), Some(digit), None) | (Some('V'), Some(digit), None) => {
+                digit.to_digit(16)
+                    .map(|reg| reg as u8)
+                    .ok_or_else(|| self.error(format!("bad register {}",
+                                                      token)))
+            },
+            _ => Err(self.error(format!("expected a register, found {}",
+                                        token))),
+        }
+    }
+
+    fn register_arg(&mut self) -> Result<u8, AsmError> {
+        let token = self.expect("a register")?;
+        self.register(token)
+    }
+
+    fn nibble_arg(&mut self) -> Result<u8, AsmError> {
+        let token = self.expect("a number")?;
+        let value = self.value(token)?;
+        if !(0..=0xF).contains(&value) {
+            return Err(self.error(format!("value {} doesn't fit in 4 bits",
+                                          value)));
+        }
+        Ok(value as u8)
+    }
+
+    fn operand(&self, token: &str) -> Result<Operand, AsmError> {
+        match self.register(token) {
+            Ok(reg) => Ok(Operand::V(reg)),
+            Err(_) => self.byte(token).map(Operand::Byte),
+        }
+    }
+
+    fn byte(&self, token: &str) -> Result<u8, AsmError> {
+        let value = self.value(token)?;
+        if !(-0x80..=0xFF).contains(&value) {
+            return Err(self.error(format!("value {} doesn't fit in a byte",
+                                          value)));
+        }
+        Ok(value as u8)
+    }
+
+    /// A number, constant or label defined so far.
+    fn value(&self, token: &str) -> Result<i64, AsmError> {
+        if let Some(&value) = self.constants.get(token) {
+            return Ok(value);
+        }
+        if let Some(&addr) = self.labels.get(token) {
+            return Ok(addr as i64);
+        }
+        let (negative, digits) = match token.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, token),
+        };
+        let value = if let Some(hex) = digits.strip_prefix("0x") {
+            i64::from_str_radix(hex, 16).ok()
+        } else if let Some(binary) = digits.strip_prefix("0b") {
+            i64::from_str_radix(binary, 2).ok()
+        } else {
+            digits.parse().ok()
+        };
+        match value {
+            Some(value) if negative => Ok(-value),
+            Some(value) => Ok(value),
+            None => Err(self.error(format!("bad value {}", token))),
+        }
+    }
+}
+
+fn addr(addr: usize) -> Result<u16, String> {
+    if addr > 0xFFF {
+        return Err(format!("address {:#06X} doesn't fit in 12 bits; use \
+                            i := long", addr));
+    }
+    Ok(addr as u16)
+}
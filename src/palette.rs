@@ -0,0 +1,124 @@
+/// A colour as red, green and blue components.
+pub type Rgb = [u8; 3];
+
+/// Names accepted by `Palette::preset`, in the order the frontend cycles
+/// through them.
+pub const PRESETS: &[&str] = &["classic", "amber", "green", "octo"];
+
+/// The colours frame buffer pixels are shown in.
+///
+/// Each pixel is a value from 0 to 3 (see `Chip8::framebuffer`), so a palette
+/// has four colours: the background, the foreground of the first bitplane,
+/// the colour of the second bitplane alone and the colour where both overlap.
+/// Classic programs only draw with the first two.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Palette {
+    pub colours: [Rgb; 4],
+}
+
+impl Palette {
+    /// White on black, with orange for the second XO-CHIP bitplane.
+    pub fn classic() -> Palette {
+        Palette {
+            colours: [[0x00, 0x00, 0x00], [0xFF, 0xFF, 0xFF],
+                      [0xFF, 0x66, 0x00], [0x66, 0x22, 0x00]],
+        }
+    }
+
+    /// An amber monochrome monitor.
+    pub fn amber() -> Palette {
+        Palette {
+            colours: [[0x1A, 0x0F, 0x00], [0xFF, 0xB0, 0x00],
+                      [0x80, 0x58, 0x00], [0xFF, 0xD8, 0x80]],
+        }
+    }
+
+    /// A green phosphor monochrome monitor.
+    pub fn green() -> Palette {
+        Palette {
+            colours: [[0x00, 0x14, 0x00], [0x33, 0xFF, 0x33],
+                      [0x11, 0x77, 0x11], [0xAA, 0xFF, 0xAA]],
+        }
+    }
+
+    /// The default colours of the Octo IDE.
+    pub fn octo() -> Palette {
+        Palette {
+            colours: [[0x99, 0x66, 0x00], [0xFF, 0xCC, 0x00],
+                      [0xFF, 0x66, 0x00], [0x66, 0x22, 0x00]],
+        }
+    }
+
+    /// Look up a preset by name (see `PRESETS`).
+    pub fn preset(name: &str) -> Option<Palette> {
+        match name {
+            "classic" => Some(Palette::classic()),
+            "amber" => Some(Palette::amber()),
+            "green" => Some(Palette::green()),
+            "octo" => Some(Palette::octo()),
+            _ => None,
+        }
+    }
+
+    /// Parse a preset name, or a comma-separated list of two or four hex
+    /// colours such as `000000,FFFFFF`, with or without a leading `#`.
+    ///
+    /// Two colours are the background and foreground; the second bitplane is
+    /// then shown halfway between them, and both planes in the foreground.
+    pub fn parse(text: &str) -> Result<Palette, String> {
+        if let Some(palette) = Palette::preset(text) {
+            return Ok(palette);
+        }
+        let usage = || {
+            format!("expected a palette name ({}) or two or four hex colours",
+                    PRESETS.join(", "))
+        };
+        if !text.contains(',') {
+            return Err(usage());
+        }
+
+        let colours = text.split(',')
+            .map(|colour| parse_colour(colour.trim()))
+            .collect::<Result<Vec<Rgb>, String>>()?;
+        match colours[..] {
+            [background, foreground] => {
+                let mut blend = [0x0; 3];
+                for (i, component) in blend.iter_mut().enumerate() {
+                    *component = ((background[i] as u16 +
+                                   foreground[i] as u16) / 2) as u8;
+                }
+                Ok(Palette {
+                    colours: [background, foreground, blend, foreground],
+                })
+            },
+            [c0, c1, c2, c3] => Ok(Palette { colours: [c0, c1, c2, c3] }),
+            _ => Err(usage()),
+        }
+    }
+
+    /// The colour of frame buffer value `pixel`.
+    pub fn colour(&self, pixel: u8) -> Rgb {
+        self.colours[(pixel & 0x3) as usize]
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Palette {
+        Palette::classic()
+    }
+}
+
+/// Parse a colour written as six hex digits, optionally preceded by `#`.
+fn parse_colour(text: &str) -> Result<Rgb, String> {
+    let digits = text.strip_prefix('#').unwrap_or(text);
+    let bad_colour = || format!("bad colour {}; expected RRGGBB", text);
+    if digits.len() != 6 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(bad_colour());
+    }
+    let mut colour = [0x0; 3];
+    for (i, component) in colour.iter_mut().enumerate() {
+        *component = u8::from_str_radix(&digits[i * 2..i * 2 + 2], 16)
+            .map_err(|_| bad_colour())?;
+    }
+    Ok(colour)
+}
@@ -0,0 +1,137 @@
+/// Behaviours that differ between CHIP-8 interpreters.
+///
+/// Programs written for one interpreter often rely on its particular
+/// behaviour, so the core can emulate any combination of them. Start from one
+/// of the named presets and override individual flags as needed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Quirks {
+    /// 8XY6/8XYE shift V[X] in place and ignore V[Y]. When off, V[Y] is
+    /// shifted and the result stored in V[X].
+    pub shift: bool,
+
+    /// FX55/FX65 leave the index register pointing past the last register
+    /// stored or loaded. When off, the index register is unchanged.
+    pub load_store: bool,
+
+    /// With `load_store`, FX55/FX65 leave the index register pointing at the
+    /// last register stored or loaded instead, advancing it by X.
+    pub load_store_x: bool,
+
+    /// BNNN behaves as BXNN, jumping to XNN + V[X] rather than NNN + V[0].
+    pub jump: bool,
+
+    /// Sprites drawn past the edge of the screen are clipped. When off, they
+    /// wrap around to the opposite edge.
+    pub clip: bool,
+
+    /// 8XY1/8XY2/8XY3 reset V[F] to 0.
+    pub vf_reset: bool,
+}
+
+/// Names accepted by `Quirks::preset`.
+pub const PRESETS: &[&str] = &["vip", "chip48", "schip", "modern"];
+
+/// Names accepted by `Quirks::set_flag`.
+pub const FLAGS: &[&str] = &["shift", "load_store", "load_store_x", "jump",
+                             "clip", "vf_reset"];
+
+impl Quirks {
+    /// The original COSMAC VIP interpreter.
+    pub fn vip() -> Quirks {
+        Quirks {
+            shift: false,
+            load_store: true,
+            load_store_x: false,
+            jump: false,
+            clip: true,
+            vf_reset: true,
+        }
+    }
+
+    /// CHIP-48 on the HP-48 calculators.
+    pub fn chip48() -> Quirks {
+        Quirks {
+            shift: true,
+            load_store: true,
+            load_store_x: true,
+            jump: true,
+            clip: true,
+            vf_reset: false,
+        }
+    }
+
+    /// SUPER-CHIP 1.1.
+    pub fn schip() -> Quirks {
+        Quirks {
+            shift: true,
+            load_store: false,
+            load_store_x: false,
+            jump: true,
+            clip: true,
+            vf_reset: false,
+        }
+    }
+
+    /// Modern interpreters such as Octo, and XO-CHIP.
+    pub fn modern() -> Quirks {
+        Quirks {
+            shift: false,
+            load_store: true,
+            load_store_x: false,
+            jump: false,
+            clip: false,
+            vf_reset: false,
+        }
+    }
+
+    /// Look up a preset by name (see `PRESETS`).
+    pub fn preset(name: &str) -> Option<Quirks> {
+        match name {
+            "vip" => Some(Quirks::vip()),
+            "chip48" => Some(Quirks::chip48()),
+            "schip" => Some(Quirks::schip()),
+            "modern" => Some(Quirks::modern()),
+            _ => None,
+        }
+    }
+
+    /// Set the flag called `name` (see `FLAGS`) to `value`. Returns false if
+    /// there is no such flag.
+    pub fn set_flag(&mut self, name: &str, value: bool) -> bool {
+        let flag = match name {
+            "shift" => &mut self.shift,
+            "load_store" => &mut self.load_store,
+            "load_store_x" => &mut self.load_store_x,
+            "jump" => &mut self.jump,
+            "clip" => &mut self.clip,
+            "vf_reset" => &mut self.vf_reset,
+            _ => return false,
+        };
+        *flag = value;
+        true
+    }
+
+    /// Apply an override written as `FLAG=on` or `FLAG=off`, as accepted on
+    /// the command line.
+    pub fn apply_override(&mut self, arg: &str) -> Result<(), String> {
+        let mut parts = arg.splitn(2, '=');
+        let flag = parts.next().unwrap_or("");
+        let value = match parts.next() {
+            Some("on") | Some("true") | Some("1") => true,
+            Some("off") | Some("false") | Some("0") => false,
+            _ => return Err(format!("expected {}=on or {}=off", flag, flag)),
+        };
+        if self.set_flag(flag, value) {
+            Ok(())
+        } else {
+            Err(format!("unknown quirk {}; expected one of {}",
+                        flag, FLAGS.join(", ")))
+        }
+    }
+}
+
+impl Default for Quirks {
+    fn default() -> Quirks {
+        Quirks::modern()
+    }
+}
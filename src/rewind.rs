@@ -0,0 +1,188 @@
+//! A rewind buffer of recent machine snapshots.
+//!
+//! Snapshots are save states taken with `Chip8::save_state`. Consecutive
+//! states differ in only a few bytes, so only the newest is kept whole; each
+//! older one is stored as a run-length encoded XOR against the snapshot taken
+//! after it. When the buffer grows past its memory budget the oldest
+//! snapshots are dropped.
+
+use std::collections::VecDeque;
+
+use chip8::Chip8;
+
+/// Default memory budget, in bytes.
+pub const DEFAULT_BUDGET: usize = 16 * 1024 * 1024;
+
+/// A bounded history of `Chip8` snapshots, newest last.
+pub struct Rewind {
+    budget: usize,
+
+    // The most recent snapshot, if any
+    newest: Option<Vec<u8>>,
+
+    // Deltas recovering each older snapshot from the one after it, oldest
+    // first
+    deltas: VecDeque<Vec<u8>>,
+
+    // Bytes held by `newest` and `deltas`
+    used: usize,
+}
+
+impl Rewind {
+    /// Construct an empty buffer holding at most about `budget` bytes.
+    pub fn new(budget: usize) -> Rewind {
+        Rewind {
+            budget,
+            newest: None,
+            deltas: VecDeque::new(),
+            used: 0,
+        }
+    }
+
+    /// Snapshot `chip8`, dropping the oldest snapshots if the buffer is over
+    /// budget. The newest snapshot is always kept.
+    pub fn push(&mut self, chip8: &Chip8) {
+        let state = chip8.save_state();
+        if let Some(previous) = self.newest.take() {
+            let delta = encode_delta(&previous, &state);
+            self.used = self.used + delta.len() - previous.len();
+            self.deltas.push_back(delta);
+        }
+        self.used += state.len();
+        self.newest = Some(state);
+
+        while self.used > self.budget {
+            match self.deltas.pop_front() {
+                Some(delta) => self.used -= delta.len(),
+                None => break,
+            }
+        }
+    }
+
+    /// Restore `chip8` to the most recent snapshot and remove it from the
+    /// buffer. Returns false if the buffer is empty.
+    pub fn pop(&mut self, chip8: &mut Chip8) -> bool {
+        let state = match self.newest.take() {
+            Some(state) => state,
+            None => return false,
+        };
+        self.used -= state.len();
+        if let Some(delta) = self.deltas.pop_back() {
+            let previous = apply_delta(&delta, &state);
+            self.used = self.used + previous.len() - delta.len();
+            self.newest = Some(previous);
+        }
+
+        // The snapshot was taken from the running machine, so only fails to
+        // load if it was pushed from a different ROM
+        chip8.load_state(&state).is_ok()
+    }
+
+    /// Number of snapshots held.
+    pub fn len(&self) -> usize {
+        self.deltas.len() + self.newest.iter().count()
+    }
+
+    /// Whether there are no snapshots to rewind to.
+    pub fn is_empty(&self) -> bool {
+        self.newest.is_none()
+    }
+
+    /// Approximate memory held by the snapshots, in bytes.
+    pub fn memory_used(&self) -> usize {
+        self.used
+    }
+
+    /// Change the memory budget. Takes effect on the next `push`.
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+    }
+
+    /// Drop every snapshot.
+    pub fn clear(&mut self) {
+        self.newest = None;
+        self.deltas.clear();
+        self.used = 0;
+    }
+}
+
+impl Default for Rewind {
+    fn default() -> Rewind {
+        Rewind::new(DEFAULT_BUDGET)
+    }
+}
+
+/// Encode `old` as a delta against `new`.
+///
+/// The delta is the length of `old` followed by runs, each a count of
+/// unchanged bytes, a count of changed bytes and the changed bytes XORed with
+/// those of `new`. Bytes past the end of `new` are XORed with 0. Counts are
+/// LEB128 varints.
+pub fn encode_delta(old: &[u8], new: &[u8]) -> Vec<u8> {
+    const CHUNK: usize = 64;
+    let xor = |i: usize| old[i] ^ new.get(i).cloned().unwrap_or(0x0);
+    let common = old.len().min(new.len());
+
+    let mut delta = Vec::new();
+    write_varint(&mut delta, old.len());
+    let mut i = 0;
+    while i < old.len() {
+        let same_start = i;
+        // Most of a snapshot is unchanged, so skip it a chunk at a time
+        while i + CHUNK <= common && old[i..i + CHUNK] == new[i..i + CHUNK] {
+            i += CHUNK;
+        }
+        while i < old.len() && xor(i) == 0 {
+            i += 1;
+        }
+        let changed_start = i;
+        while i < old.len() && xor(i) != 0 {
+            i += 1;
+        }
+        write_varint(&mut delta, changed_start - same_start);
+        write_varint(&mut delta, i - changed_start);
+        delta.extend((changed_start..i).map(xor));
+    }
+    delta
+}
+
+/// Recover the snapshot `delta` was encoded from, given the snapshot `new`
+/// it was encoded against.
+pub fn apply_delta(delta: &[u8], new: &[u8]) -> Vec<u8> {
+    let mut input = delta.iter().cloned();
+    let len = read_varint(&mut input);
+    let mut old = new.to_vec();
+    old.resize(len, 0x0);
+
+    let mut i = 0;
+    while i < len {
+        i += read_varint(&mut input);
+        let changed = read_varint(&mut input);
+        for byte in old[i..i + changed].iter_mut() {
+            *byte ^= input.next().unwrap_or(0x0);
+        }
+        i += changed;
+    }
+    old
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint<I: Iterator<Item = u8>>(input: &mut I) -> usize {
+    let mut value = 0;
+    let mut shift = 0;
+    for byte in input {
+        value |= ((byte & 0x7F) as usize) << shift;
+        if byte & 0x80 == 0 {
+            break;
+        }
+        shift += 7;
+    }
+    value
+}
@@ -0,0 +1,42 @@
+/// The random number generator behind CXNN.
+///
+/// A xorshift64* generator: small, fast and fully determined by its seed, so
+/// a run can be reproduced by seeding it the same way, and its whole state
+/// fits in a save state.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// Construct a generator from `seed`. Every seed, including 0, gives a
+    /// different usable sequence.
+    pub fn new(seed: u64) -> Rng {
+        // Scramble the seed with splitmix64 so similar seeds diverge and the
+        // state is never 0, which xorshift can't leave
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        Rng { state: if z == 0 { 0x1 } else { z } }
+    }
+
+    /// The next random byte.
+    pub fn next_byte(&mut self) -> u8 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        (self.state.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 56) as u8
+    }
+
+    /// The generator's internal state, for saving.
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    /// Restore a generator from a state returned by `state`. Returns `None`
+    /// for 0, which no generator can be in.
+    pub fn from_state(state: u64) -> Option<Rng> {
+        if state == 0 { None } else { Some(Rng { state }) }
+    }
+}
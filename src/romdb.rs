@@ -0,0 +1,175 @@
+//! ROM identification database.
+//!
+//! Programs are identified by the SHA-1 hash of their bytes and looked up in
+//! a database in the format of the community chip-8-database: a list of
+//! programs, each with a `roms` object keyed by hash that names the platforms
+//! the ROM runs on and any tickrate, colours, key hints and quirk overrides it
+//! needs, plus a list of platforms giving each one's default tickrate and
+//! quirks. A small database covering the bundled ROMs is built in.
+
+use std::sync::OnceLock;
+
+use serde_json::{self, Value};
+
+use chip8::Chip8;
+use palette::Palette;
+use quirks::Quirks;
+use state::RomHash;
+
+/// The built-in program list.
+const PROGRAMS: &str = include_str!("../data/programs.json");
+
+/// The built-in platform list.
+const PLATFORMS: &str = include_str!("../data/platforms.json");
+
+/// What the database knows about a ROM.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RomInfo {
+    pub title: String,
+    pub authors: Vec<String>,
+
+    /// Name of the platform the ROM was written for, if the database gives
+    /// one.
+    pub platform: Option<String>,
+
+    pub instructions_per_frame: Option<u32>,
+    pub quirks: Option<Quirks>,
+    pub palette: Option<Palette>,
+
+    /// Keys the program uses, as (action, key) pairs.
+    pub keys: Vec<(String, u8)>,
+}
+
+impl RomInfo {
+    /// Configure `chip8` with the recommended speed and quirks. The palette
+    /// and key hints are left to the frontend.
+    pub fn apply(&self, chip8: &mut Chip8) {
+        if let Some(instructions) = self.instructions_per_frame {
+            chip8.set_instructions_per_frame(instructions);
+        }
+        if let Some(quirks) = self.quirks {
+            chip8.set_quirks(quirks);
+        }
+    }
+}
+
+/// A ROM database.
+pub struct Database {
+    programs: Value,
+    platforms: Value,
+}
+
+impl Database {
+    /// The database built into the emulator, parsed on first use.
+    pub fn bundled() -> &'static Database {
+        static BUNDLED: OnceLock<Database> = OnceLock::new();
+        BUNDLED.get_or_init(|| {
+            Database::from_json(PROGRAMS, PLATFORMS)
+                .expect("built-in ROM database is invalid")
+        })
+    }
+
+    /// Parse a database from the text of its program and platform lists.
+    pub fn from_json(programs: &str,
+                     platforms: &str) -> Result<Database, serde_json::Error> {
+        Ok(Database {
+            programs: serde_json::from_str(programs)?,
+            platforms: serde_json::from_str(platforms)?,
+        })
+    }
+
+    /// Look up the ROM with SHA-1 hash `hash`.
+    pub fn lookup(&self, hash: &RomHash) -> Option<RomInfo> {
+        let key: String = hash.iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        let (program, rom) = self.programs.as_array()?
+            .iter()
+            .find_map(|program| {
+                program["roms"].get(&key).map(|rom| (program, rom))
+            })?;
+
+        let platform_id = rom["platforms"][0].as_str();
+        let platform = platform_id.and_then(|id| {
+            self.platforms.as_array()?
+                .iter()
+                .find(|platform| platform["id"].as_str() == Some(id))
+        });
+
+        // The ROM's quirk overrides for its platform apply over the
+        // platform's own quirks
+        let quirks = platform.map(|platform| {
+            let mut quirks = Quirks::modern();
+            apply_quirks(&mut quirks, &platform["quirks"]);
+            if let Some(id) = platform_id {
+                apply_quirks(&mut quirks, &rom["quirkyPlatforms"][id]);
+            }
+            quirks
+        });
+
+        let tickrate = rom["tickrate"].as_u64()
+            .or_else(|| platform?["defaultTickrate"].as_u64())
+            .filter(|&rate| rate > 0 && rate <= u32::MAX as u64);
+
+        let palette = rom["colors"]["pixels"].as_array()
+            .and_then(|colours| {
+                colours.iter().map(Value::as_str).collect::<Option<Vec<_>>>()
+            })
+            .and_then(|colours| Palette::parse(&colours.join(",")).ok());
+
+        let keys = rom["keys"].as_object()
+            .map(|keys| {
+                keys.iter()
+                    .filter_map(|(action, key)| {
+                        let key = key.as_u64().filter(|&key| key <= 0xF)?;
+                        Some((action.clone(), key as u8))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Some(RomInfo {
+            title: program["title"].as_str().unwrap_or("Unknown").to_string(),
+            authors: program["authors"].as_array()
+                .map(|authors| {
+                    authors.iter()
+                        .filter_map(Value::as_str)
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            platform: platform
+                .and_then(|platform| platform["name"].as_str())
+                .or(platform_id)
+                .map(str::to_string),
+            instructions_per_frame: tickrate.map(|rate| rate as u32),
+            quirks,
+            palette,
+            keys,
+        })
+    }
+}
+
+/// Set the flags named in a database quirks object. Flags the emulator has
+/// no equivalent for are ignored.
+fn apply_quirks(quirks: &mut Quirks, flags: &Value) {
+    let flag = |name: &str| flags[name].as_bool();
+    if let Some(shift) = flag("shift") {
+        quirks.shift = shift;
+    }
+    if let Some(unchanged) = flag("memoryLeaveIUnchanged") {
+        quirks.load_store = !unchanged;
+    }
+    if let Some(by_x) = flag("memoryIncrementByX") {
+        quirks.load_store_x = by_x;
+    }
+    if let Some(wrap) = flag("wrap") {
+        quirks.clip = !wrap;
+    }
+    if let Some(jump) = flag("jump") {
+        quirks.jump = jump;
+    }
+    if let Some(logic) = flag("logic") {
+        quirks.vf_reset = logic;
+    }
+}
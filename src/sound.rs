@@ -0,0 +1,46 @@
+use chip8::audio::{AudioSink, Tone, ToneGenerator, DEFAULT_SAMPLE_RATE,
+                   DEFAULT_VOLUME};
+use sdl2::audio::{AudioCallback, AudioDevice, AudioSpecDesired};
+use sdl2::AudioSubsystem;
+
+/// SDL audio callback that plays the buzzer's current tone.
+struct Beeper {
+    generator: ToneGenerator,
+}
+
+impl AudioCallback for Beeper {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [f32]) {
+        self.generator.fill(out);
+    }
+}
+
+/// An `AudioSink` that plays the buzzer through the default SDL audio device.
+pub struct SdlAudioSink {
+    device: AudioDevice<Beeper>,
+}
+
+impl SdlAudioSink {
+    /// Open the default playback device.
+    pub fn new(audio_subsystem: &AudioSubsystem) -> Result<SdlAudioSink, String> {
+        let desired = AudioSpecDesired {
+            freq: Some(DEFAULT_SAMPLE_RATE as i32),
+            channels: Some(1),
+            samples: None,
+        };
+        let device = audio_subsystem.open_playback(None, &desired, |spec| {
+            Beeper {
+                generator: ToneGenerator::new(spec.freq as u32, DEFAULT_VOLUME),
+            }
+        })?;
+        device.resume();
+        Ok(SdlAudioSink { device })
+    }
+}
+
+impl AudioSink for SdlAudioSink {
+    fn frame(&mut self, tone: Option<Tone>) {
+        self.device.lock().generator.set_tone(tone);
+    }
+}
@@ -0,0 +1,201 @@
+//! Save states: binary snapshots of a complete machine.
+//!
+//! A state file starts with a header holding `MAGIC`, the format `VERSION`
+//! and the SHA-1 hash of the ROM that was running, followed by the machine
+//! state itself. `Chip8::save_state` and `Chip8::load_state` produce and
+//! consume the whole file; a state is only accepted by a machine running the
+//! same ROM. Multi-byte values are stored big-endian.
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use sha1::{Digest, Sha1};
+
+use chip8::Chip8;
+
+/// Bytes every state file starts with.
+pub const MAGIC: &[u8; 8] = b"CHIP8SAV";
+
+/// Version of the state format written by this build.
+pub const VERSION: u16 = 2;
+
+/// Size of a ROM hash, in bytes.
+pub const ROM_HASH_SIZE: usize = 20;
+
+/// SHA-1 hash identifying a ROM.
+pub type RomHash = [u8; ROM_HASH_SIZE];
+
+/// Hash `rom` to identify it in state files.
+pub fn hash_rom(rom: &[u8]) -> RomHash {
+    let mut hash = [0x0; ROM_HASH_SIZE];
+    hash.copy_from_slice(&Sha1::digest(rom));
+    hash
+}
+
+/// An error raised while saving or loading a state.
+#[derive(Debug)]
+pub enum StateError {
+    /// The state file couldn't be read or written.
+    Io(io::Error),
+
+    /// The data isn't a state file.
+    BadMagic,
+
+    /// The state was written by an incompatible version of the format.
+    UnsupportedVersion(u16),
+
+    /// The state was saved while a different ROM was running.
+    RomMismatch,
+
+    /// The state is truncated or holds impossible values.
+    Corrupt(&'static str),
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            StateError::Io(ref err) => write!(f, "{}", err),
+            StateError::BadMagic => write!(f, "not a save state"),
+            StateError::UnsupportedVersion(version) => {
+                write!(f, "unsupported save state version {}", version)
+            },
+            StateError::RomMismatch => {
+                write!(f, "save state is for a different ROM")
+            },
+            StateError::Corrupt(reason) => {
+                write!(f, "corrupt save state: {}", reason)
+            },
+        }
+    }
+}
+
+impl Error for StateError {}
+
+impl From<io::Error> for StateError {
+    fn from(err: io::Error) -> StateError {
+        StateError::Io(err)
+    }
+}
+
+/// Save the state of `chip8` to the file at `path`.
+pub fn save_file<P: AsRef<Path>>(chip8: &Chip8,
+                                 path: P) -> Result<(), StateError> {
+    fs::write(path, chip8.save_state())?;
+    Ok(())
+}
+
+/// Restore `chip8` from the state file at `path`.
+pub fn load_file<P: AsRef<Path>>(chip8: &mut Chip8,
+                                 path: P) -> Result<(), StateError> {
+    let state = fs::read(path)?;
+    chip8.load_state(&state)
+}
+
+/// Serializes values in state file order.
+pub(crate) struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    /// Start a state file for a machine running the ROM hashed as `rom`.
+    pub fn new(rom: &RomHash) -> Writer {
+        let mut writer = Writer { bytes: Vec::new() };
+        writer.bytes(MAGIC);
+        writer.u16(VERSION);
+        writer.bytes(rom);
+        writer
+    }
+
+    pub fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    pub fn bool(&mut self, value: bool) {
+        self.u8(value as u8);
+    }
+
+    pub fn u16(&mut self, value: u16) {
+        self.bytes.push((value >> 8) as u8);
+        self.bytes.push(value as u8);
+    }
+
+    pub fn u64(&mut self, value: u64) {
+        self.bytes(&value.to_be_bytes());
+    }
+
+    pub fn bytes(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+/// Deserializes values written by `Writer`.
+pub(crate) struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    /// Check the header of `state` against the ROM hashed as `rom`, leaving
+    /// the reader at the start of the machine state.
+    pub fn new(state: &'a [u8],
+               rom: &RomHash) -> Result<Reader<'a>, StateError> {
+        let mut reader = Reader { bytes: state };
+        if reader.bytes(MAGIC.len()).ok() != Some(&MAGIC[..]) {
+            return Err(StateError::BadMagic);
+        }
+        let version = reader.u16()?;
+        if version != VERSION {
+            return Err(StateError::UnsupportedVersion(version));
+        }
+        if reader.bytes(ROM_HASH_SIZE)? != rom {
+            return Err(StateError::RomMismatch);
+        }
+        Ok(reader)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, StateError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub fn bool(&mut self) -> Result<bool, StateError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(StateError::Corrupt("bad flag")),
+        }
+    }
+
+    pub fn u16(&mut self) -> Result<u16, StateError> {
+        let bytes = self.bytes(2)?;
+        Ok((bytes[0] as u16) << 8 | bytes[1] as u16)
+    }
+
+    pub fn u64(&mut self) -> Result<u64, StateError> {
+        let mut bytes = [0x0; 8];
+        bytes.copy_from_slice(self.bytes(8)?);
+        Ok(u64::from_be_bytes(bytes))
+    }
+
+    pub fn bytes(&mut self, len: usize) -> Result<&'a [u8], StateError> {
+        if self.bytes.len() < len {
+            return Err(StateError::Corrupt("truncated"));
+        }
+        let (bytes, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(bytes)
+    }
+
+    /// Check that the whole state has been read.
+    pub fn finish(self) -> Result<(), StateError> {
+        if !self.bytes.is_empty() {
+            return Err(StateError::Corrupt("trailing data"));
+        }
+        Ok(())
+    }
+}
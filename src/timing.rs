@@ -0,0 +1,55 @@
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+/// Rate at which the delay and sound timers count down, in Hz.
+pub const TIMER_HZ: u32 = 60;
+
+/// Default CPU speed, in instructions per second.
+pub const DEFAULT_INSTRUCTIONS_PER_SECOND: u32 = 600;
+
+/// Convert a CPU speed in instructions per second into the number of
+/// instructions executed per 60 Hz frame. At least one instruction is always
+/// executed per frame.
+pub fn instructions_per_frame(instructions_per_second: u32) -> u32 {
+    ((instructions_per_second + TIMER_HZ / 2) / TIMER_HZ).max(1)
+}
+
+/// Paces a host loop at `TIMER_HZ` frames per second of wall-clock time.
+///
+/// Each call to `wait` sleeps until the next frame is due. If the host falls
+/// behind by more than a few frames the clock resynchronises rather than
+/// running a burst of catch-up frames.
+pub struct FrameClock {
+    frame: Duration,
+    next: Instant,
+}
+
+impl FrameClock {
+    /// Maximum number of frames the clock lets the host fall behind by.
+    const MAX_LAG_FRAMES: u32 = 4;
+
+    /// Construct a clock whose first frame is due now.
+    pub fn new() -> FrameClock {
+        FrameClock {
+            frame: Duration::from_secs(1) / TIMER_HZ,
+            next: Instant::now(),
+        }
+    }
+
+    /// Sleep until the next frame is due.
+    pub fn wait(&mut self) {
+        self.next += self.frame;
+        let now = Instant::now();
+        if self.next > now {
+            sleep(self.next - now);
+        } else if now - self.next > self.frame * FrameClock::MAX_LAG_FRAMES {
+            self.next = now;
+        }
+    }
+}
+
+impl Default for FrameClock {
+    fn default() -> FrameClock {
+        FrameClock::new()
+    }
+}
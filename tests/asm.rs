@@ -0,0 +1,77 @@
+extern crate chip8;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+use chip8::asm::{self, AsmError};
+use chip8::disasm::{self, DEFAULT_ORIGIN};
+
+const ROMS: &[&str] = &["BLINKY", "IBMLOGO", "INVADERS", "PICTURE", "PONG",
+                        "TICTAC"];
+
+#[test]
+fn disassembly_assembles_to_the_original_rom() {
+    for name in ROMS {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(name);
+        let rom = fs::read(&path).unwrap();
+        let listing = disasm::disassemble(&rom, DEFAULT_ORIGIN).to_string();
+        assert_eq!(asm::assemble(&listing).unwrap(), rom, "{}", name);
+    }
+}
+
+#[test]
+fn labels_constants_and_data() {
+    let source = "\
+SPEED = 4
+COUNT equ SPEED + 2
+start:  LD V0, SPEED        ; 0x200
+        ld i, sprite        ; 0x202
+        DRW V0, V1, COUNT - 1
+        LD [I], V0-V3
+        SHR V2
+        JP start
+sprite: db 0x18, 0b00111100, -1
+        dw 0x1234, start + 2
+";
+    assert_eq!(asm::assemble(source).unwrap(),
+               [0x60, 0x04, 0xA2, 0x0C, 0xD0, 0x15, 0x50, 0x32, 0x82, 0x26,
+                0x12, 0x00, 0x18, 0x3C, 0xFF, 0x12, 0x34, 0x02, 0x02]);
+}
+
+#[test]
+fn long_index_load_is_four_bytes() {
+    let source = "LD I, LONG end\nend: EXIT";
+    assert_eq!(asm::assemble(source).unwrap(),
+               [0xF0, 0x00, 0x02, 0x04, 0x00, 0xFD]);
+}
+
+#[test]
+fn include_is_relative_to_including_file() {
+    let dir = env::temp_dir().join(format!("chip8-asm-{}", process::id()));
+    fs::create_dir_all(dir.join("lib")).unwrap();
+    fs::write(dir.join("main.s"), "include \"lib/sub.s\"\nCALL sub\n").unwrap();
+    fs::write(dir.join("lib/sub.s"), "sub: RET\n").unwrap();
+
+    let rom = asm::assemble_file(dir.join("main.s"));
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(rom.unwrap(), [0x00, 0xEE, 0x22, 0x00]);
+}
+
+#[test]
+fn errors_report_line_numbers() {
+    let error = |source| asm::assemble(source).unwrap_err();
+
+    assert_eq!(error("CLS\n\nLD V0, 300"), AsmError {
+        file: "<input>".to_string(),
+        line: 3,
+        message: "value 300 doesn't fit in a byte".to_string(),
+    });
+    assert_eq!(error("CLS\nJP nowhere").to_string(),
+               "<input>:2: undefined label or constant nowhere");
+    assert_eq!(error("NOP").line, 1);
+    assert_eq!(error("a: CLS\na: RET").line, 2);
+    assert!(error("X = Y\nY = X\nLD V0, X").message
+            .ends_with("is defined in terms of itself"));
+}
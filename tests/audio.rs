@@ -0,0 +1,54 @@
+extern crate chip8;
+
+use chip8::{Chip8, Quirks};
+use chip8::audio::{AudioSink, WavSink};
+
+/// Run `rom` for `frames` frames, recording the buzzer into a `WavSink`.
+fn record(rom: &[u8], frames: usize) -> WavSink {
+    let mut chip8 = Chip8::new(Quirks::default());
+    chip8.load_rom(rom).unwrap();
+
+    let mut sink = WavSink::default();
+    for _ in 0..frames {
+        chip8.run_frame().unwrap();
+        sink.frame(chip8.tone());
+    }
+    sink
+}
+
+#[test]
+fn beep_lasts_while_sound_timer_is_nonzero() {
+    // LD V0, 5; LD ST, V0; JP 0x204
+    let rom = [0x60, 0x05, 0xF0, 0x18, 0x12, 0x04];
+    let sink = record(&rom, 8);
+
+    let per_frame = sink.samples_per_frame();
+    assert_eq!(sink.samples().len(), 8 * per_frame);
+
+    // ST is set to 5 during the first frame and counts down once at the end
+    // of every frame, so the tone is heard for four frames
+    let loud: Vec<bool> = sink.samples()
+        .chunks(per_frame)
+        .map(|frame| frame.iter().any(|&sample| sample != 0))
+        .collect();
+    assert_eq!(loud, [true, true, true, true, false, false, false, false]);
+}
+
+#[test]
+fn silent_program_records_silence() {
+    // JP 0x200
+    let sink = record(&[0x12, 0x00], 4);
+    assert!(sink.samples().iter().all(|&sample| sample == 0));
+}
+
+#[test]
+fn wav_header_describes_samples() {
+    let sink = record(&[0x12, 0x00], 2);
+    let mut wav = Vec::new();
+    sink.write(&mut wav).unwrap();
+
+    assert_eq!(&wav[0..4], b"RIFF");
+    assert_eq!(&wav[8..12], b"WAVE");
+    assert_eq!(&wav[36..40], b"data");
+    assert_eq!(wav.len(), 44 + sink.samples().len() * 2);
+}
@@ -0,0 +1,160 @@
+extern crate chip8;
+extern crate gif;
+
+use std::io::Cursor;
+
+use chip8::{Chip8, Quirks, RomError};
+use chip8::cartridge::{self, Cartridge, CartridgeError};
+use chip8::chip8::{DEFAULT_LOAD_ADDRESS, FONT_START};
+use chip8::font::Font;
+use chip8::palette::Palette;
+
+const WIDTH: u16 = 128;
+const HEIGHT: u16 = 64;
+
+/// Build a cartridge image holding `json`.
+fn cartridge(json: &str) -> Vec<u8> {
+    let mut payload = (json.len() as u32).to_be_bytes().to_vec();
+    payload.extend_from_slice(json.as_bytes());
+    image(&payload)
+}
+
+/// Build a GIF holding `payload`, packed two bits to a pixel.
+fn image(payload: &[u8]) -> Vec<u8> {
+    let mut pixels: Vec<u8> = payload.iter()
+        .flat_map(|&byte| (0..4).rev().map(move |i| byte >> (i * 2) & 0x3))
+        .collect();
+    let frame_size = WIDTH as usize * HEIGHT as usize;
+    let frames = pixels.len().div_ceil(frame_size);
+    pixels.resize(frames * frame_size, 0x0);
+
+    let palette: Vec<u8> = (0..16).flat_map(|i| vec![i * 16; 3]).collect();
+    let mut gif = Vec::new();
+    {
+        let mut encoder = gif::Encoder::new(&mut gif, WIDTH, HEIGHT, &palette)
+            .unwrap();
+        for frame in pixels.chunks(frame_size) {
+            let frame = gif::Frame::from_indexed_pixels(WIDTH, HEIGHT,
+                                                        frame.to_vec(), None);
+            encoder.write_frame(&frame).unwrap();
+        }
+    }
+    gif
+}
+
+#[test]
+fn loads_program_and_options() {
+    let json = r##"{
+        "program": ": main\n  v0 := 0xA\n  i := hex v0\n  loop again",
+        "options": {
+            "tickrate": 100,
+            "backgroundColor": "#000000",
+            "fillColor": "#FF0000",
+            "fillColor2": "#00FF00",
+            "blendColor": "#0000FF",
+            "shiftQuirks": true,
+            "loadStoreQuirks": true,
+            "fontStyle": "vip"
+        }
+    }"##;
+    let image = cartridge(json);
+    assert!(cartridge::is_cartridge(&image));
+
+    let mut chip8 = Chip8::new(Quirks::default());
+    let options = chip8.load_rom_from_reader(Cursor::new(image))
+        .unwrap()
+        .unwrap();
+    // Octo programs start with a jump to main
+    assert_eq!(chip8.word_at(DEFAULT_LOAD_ADDRESS), Some(0x1202));
+    assert_eq!(chip8.word_at(DEFAULT_LOAD_ADDRESS + 2), Some(0x600A));
+    assert_eq!(chip8.instructions_per_frame(), 100);
+    let quirks = chip8.quirks();
+    assert!(quirks.shift && !quirks.load_store && !quirks.jump);
+    assert_eq!(options.palette,
+               Some(Palette::parse("000000,FF0000,00FF00,0000FF").unwrap()));
+
+    // The cartridge's font is the one FX29 finds
+    for _ in 0..3 {
+        chip8.execute_cycle().unwrap();
+    }
+    let index = chip8.index() as usize;
+    assert_eq!(index, FONT_START + 0xA * 5);
+    assert_eq!(&chip8.memory()[index..index + 5], &Font::vip().small[50..55]);
+}
+
+#[test]
+fn payload_spans_frames() {
+    let program = format!(": main\n{}", "clear\n".repeat(2000));
+    let json = format!("{{\"program\": {:?}, \"options\": {{}}}}", program);
+    let cartridge = Cartridge::decode(&cartridge(&json)).unwrap();
+    assert_eq!(cartridge.program, program);
+    assert_eq!(cartridge.options.palette, None);
+    assert_eq!(cartridge.options.quirks, Quirks::modern());
+    assert_eq!(cartridge.rom().unwrap().len(), 4002);
+}
+
+#[test]
+fn loads_octo_source() {
+    let json = r#"{"program": ": main\n  v0 := 1\n", "options": {}}"#;
+    let mut chip8 = Chip8::new(Quirks::default());
+    chip8.load_rom_from_reader(Cursor::new(cartridge(json))).unwrap();
+    assert_eq!(chip8.word_at(DEFAULT_LOAD_ADDRESS + 2), Some(0x6001));
+}
+
+/// Load a cartridge holding `program`.
+fn load_program(program: &str) -> Result<Chip8, RomError> {
+    let json = format!("{{\"program\": {:?}, \"options\": {{}}}}", program);
+    let mut chip8 = Chip8::new(Quirks::default());
+    chip8.load_rom_from_reader(Cursor::new(cartridge(&json)))?;
+    Ok(chip8)
+}
+
+#[test]
+fn rejects_bad_program() {
+    match load_program(": main\n  v0 := 300") {
+        Err(RomError::Cartridge(CartridgeError::Program(err))) => {
+            assert_eq!(err.line, 2);
+        },
+        result => panic!("expected a program error, got {:?}", result.err()),
+    }
+}
+
+#[test]
+fn rejects_unsupported_octo_features() {
+    let programs = [
+        (":macro", ":macro twice { v0 += 1 }\n: main\n  twice"),
+        (":calc", ":calc SPEED { 2 * 3 }\n: main\n  v0 := SPEED"),
+        (":next", ": main\n  :next target v0 := 1"),
+        (":unpack", ": data 0x10\n: main\n  :unpack 0xA data"),
+        (":stringmode", ":stringmode hex \"0123\" { v0 := VALUE }\n: main"),
+        ("strings", ": main\n  \"hello\""),
+    ];
+    for &(feature, program) in &programs {
+        match load_program(program) {
+            Err(RomError::Cartridge(ref err @ CartridgeError::Unsupported {
+                line, feature: ref found,
+            })) => {
+                assert_eq!(found, feature);
+                assert_eq!(err.to_string(),
+                           format!("the cartridge's program uses {} on line \
+                                    {}, an unsupported Octo feature",
+                                   feature, line));
+            },
+            result => {
+                panic!("{}: expected CartridgeError::Unsupported, got {:?}",
+                       feature, result.err());
+            },
+        }
+    }
+}
+
+#[test]
+fn rejects_truncated_payload() {
+    // A 4 KB payload doesn't fit in one frame
+    let image = image(&[0x00, 0x00, 0x10, 0x00, b'{']);
+    match Cartridge::decode(&image) {
+        Err(CartridgeError::Truncated) => {},
+        result => panic!("expected CartridgeError::Truncated, got {:?}",
+                         result),
+    }
+}
@@ -0,0 +1,61 @@
+extern crate chip8;
+
+use chip8::Quirks;
+use chip8::config::{self, Options};
+use chip8::font::Font;
+use chip8::timing;
+
+/// Parse `args` as a command line of shared options.
+fn parse(args: &[&str]) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut args = args.iter().map(|arg| arg.to_string());
+    while let Some(arg) = args.next() {
+        if !options.parse(&arg, &mut args)? {
+            return Err(format!("unknown option {}", arg));
+        }
+    }
+    Ok(options)
+}
+
+#[test]
+fn parses_options() {
+    let options = parse(&["--ips", "900", "--quirks", "vip", "--quirk",
+                          "shift=on", "--font", "eti660", "--no-database"])
+        .unwrap();
+    assert_eq!(options.instructions_per_second, Some(900));
+    assert_eq!(options.quirks, Some(Quirks::vip()));
+    assert_eq!(options.quirk_overrides, vec!["shift=on".to_string()]);
+    assert_eq!(options.font, Font::preset("eti660"));
+    assert!(!options.use_database);
+
+    assert_eq!(parse(&["--ips", "fast"]).unwrap_err(),
+               "--ips requires a number");
+    assert!(parse(&["--quirks", "nes"]).is_err());
+    assert!(parse(&["--quirk", "wobble=on"]).is_err());
+    assert!(parse(&["--quirk"]).is_err());
+    assert!(parse(&["--font", "/nonexistent/font"]).is_err());
+    assert!(parse(&["--palette", "amber"]).is_err());
+}
+
+#[test]
+fn database_then_options() {
+    // BLINKY is a known SCHIP program
+    let (chip8, settings) = config::load("BLINKY", &Options::default())
+        .unwrap();
+    assert_eq!(settings.info.unwrap().title, "Blinky");
+    assert_eq!(chip8.instructions_per_frame(), 50);
+    assert_eq!(chip8.quirks(), Quirks::schip());
+
+    let options = parse(&["--ips", "600", "--quirk", "jump=off"]).unwrap();
+    let (chip8, _) = config::load("BLINKY", &options).unwrap();
+    assert_eq!(chip8.instructions_per_frame(), 10);
+    assert_eq!(chip8.quirks(), Quirks { jump: false, ..Quirks::schip() });
+
+    let options = parse(&["--no-database"]).unwrap();
+    let (chip8, settings) = config::load("BLINKY", &options).unwrap();
+    assert_eq!(settings.info, None);
+    assert_eq!(chip8.instructions_per_frame(),
+               timing::instructions_per_frame(
+                   timing::DEFAULT_INSTRUCTIONS_PER_SECOND));
+    assert_eq!(chip8.quirks(), Quirks::default());
+}
@@ -0,0 +1,665 @@
+extern crate chip8;
+
+use chip8::{Chip8, Chip8Error, KeyWait, Quirks};
+use chip8::audio::Tone;
+use chip8::chip8::{BIG_FONT_START, DEFAULT_PITCH, FONT_START};
+use chip8::font::{Font, BIG_GLYPH_SIZE, SMALL_GLYPH_SIZE};
+
+/// Address the instruction under test is loaded at.
+const START: u16 = 0x200;
+
+/// One instruction executed from a known register state.
+struct Case {
+    name: &'static str,
+    quirks: Quirks,
+
+    // Registers set before the instruction, as (register, value)
+    setup: &'static [(usize, u8)],
+
+    instr: u16,
+
+    // Registers checked afterwards, as (register, value)
+    expect: &'static [(usize, u8)],
+
+    // How far the instruction advances the PC
+    advance: u16,
+}
+
+impl Case {
+    fn new(name: &'static str, setup: &'static [(usize, u8)], instr: u16,
+           expect: &'static [(usize, u8)]) -> Case {
+        Case {
+            name,
+            quirks: Quirks::modern(),
+            setup,
+            instr,
+            expect,
+            advance: 2,
+        }
+    }
+
+    fn quirks(self, quirks: Quirks) -> Case {
+        Case { quirks, ..self }
+    }
+
+    fn advance(self, advance: u16) -> Case {
+        Case { advance, ..self }
+    }
+
+    /// Load the registers and press `keys`, then execute the instruction and
+    /// check the registers and PC.
+    fn run(&self, keys: &[u8]) {
+        let mut chip8 = Chip8::new(self.quirks);
+        chip8.load_rom(&self.rom()).unwrap();
+        for _ in 0..self.setup.len() {
+            chip8.execute_cycle().unwrap();
+        }
+        for &key in keys {
+            chip8.press_key(key);
+        }
+
+        let pc = chip8.pc();
+        chip8.execute_cycle().unwrap();
+        for &(reg, value) in self.expect {
+            assert_eq!(chip8.registers()[reg], value, "{}: V{:X}",
+                       self.name, reg);
+        }
+        assert_eq!(chip8.pc(), pc + self.advance, "{}: PC", self.name);
+    }
+
+    /// 6XNN loads for the setup, the instruction and a word to skip.
+    fn rom(&self) -> Vec<u8> {
+        let mut rom = Vec::new();
+        for &(reg, value) in self.setup {
+            rom.extend_from_slice(&[0x60 | reg as u8, value]);
+        }
+        rom.extend_from_slice(&self.instr.to_be_bytes());
+        rom.extend_from_slice(&[0x12, 0x00]);
+        rom
+    }
+}
+
+/// Load `rom` into a machine with `quirks` and execute `instructions`
+/// instructions.
+fn run(quirks: Quirks, rom: &[u8], instructions: usize) -> Chip8 {
+    let mut chip8 = Chip8::new(quirks);
+    chip8.load_rom(rom).unwrap();
+    execute(&mut chip8, instructions);
+    chip8
+}
+
+fn execute(chip8: &mut Chip8, instructions: usize) {
+    for _ in 0..instructions {
+        chip8.execute_cycle().unwrap();
+    }
+}
+
+/// The lit pixels in the frame buffer, as (x, y, colour).
+fn lit_pixels(chip8: &Chip8) -> Vec<(usize, usize, u8)> {
+    let width = chip8.width();
+    chip8.framebuffer().iter().enumerate()
+        .filter(|&(_, &pixel)| pixel != 0)
+        .map(|(i, &pixel)| (i % width, i / width, pixel))
+        .collect()
+}
+
+#[test]
+fn alu() {
+    let cases = [
+        Case::new("7XNN adds", &[(0x1, 0x10)], 0x7122, &[(0x1, 0x32)]),
+        Case::new("7XNN wraps without touching VF",
+                  &[(0x1, 0xFF), (0xF, 0x55)], 0x7102,
+                  &[(0x1, 0x01), (0xF, 0x55)]),
+        Case::new("8XY0 copies", &[(0x1, 0x12), (0x2, 0x34)], 0x8120,
+                  &[(0x1, 0x34), (0x2, 0x34)]),
+        Case::new("8XY1 ors", &[(0x1, 0xF0), (0x2, 0x0F), (0xF, 0x55)],
+                  0x8121, &[(0x1, 0xFF), (0xF, 0x55)]),
+        Case::new("8XY1 resets VF", &[(0x1, 0xF0), (0x2, 0x0F), (0xF, 0x55)],
+                  0x8121, &[(0x1, 0xFF), (0xF, 0x00)])
+            .quirks(Quirks::vip()),
+        Case::new("8XY2 ands", &[(0x1, 0xFC), (0x2, 0x3F)], 0x8122,
+                  &[(0x1, 0x3C)]),
+        Case::new("8XY3 xors", &[(0x1, 0xFC), (0x2, 0x3F)], 0x8123,
+                  &[(0x1, 0xC3)]),
+        Case::new("8XY4 adds", &[(0x1, 0x12), (0x2, 0x34), (0xF, 0x55)],
+                  0x8124, &[(0x1, 0x46), (0xF, 0x00)]),
+        Case::new("8XY4 adds to 0xFF without carry",
+                  &[(0x1, 0xF0), (0x2, 0x0F)], 0x8124,
+                  &[(0x1, 0xFF), (0xF, 0x00)]),
+        Case::new("8XY4 wraps and carries", &[(0x1, 0xFF), (0x2, 0x02)],
+                  0x8124, &[(0x1, 0x01), (0xF, 0x01)]),
+        Case::new("8XY4 sets VF last", &[(0xF, 0xFF), (0x2, 0x02)], 0x8F24,
+                  &[(0xF, 0x01)]),
+        Case::new("8XY5 subtracts", &[(0x1, 0x34), (0x2, 0x12)], 0x8125,
+                  &[(0x1, 0x22), (0xF, 0x01)]),
+        Case::new("8XY5 doesn't borrow on equal values",
+                  &[(0x1, 0x34), (0x2, 0x34)], 0x8125,
+                  &[(0x1, 0x00), (0xF, 0x01)]),
+        Case::new("8XY5 wraps and borrows", &[(0x1, 0x12), (0x2, 0x34)],
+                  0x8125, &[(0x1, 0xDE), (0xF, 0x00)]),
+        Case::new("8XY5 sets VF last", &[(0xF, 0x34), (0x2, 0x12)], 0x8F25,
+                  &[(0xF, 0x01)]),
+        Case::new("8XY6 shifts VY", &[(0x1, 0x00), (0x2, 0x05)], 0x8126,
+                  &[(0x1, 0x02), (0x2, 0x05), (0xF, 0x01)]),
+        Case::new("8XY6 shifts VX", &[(0x1, 0x04), (0x2, 0x05)], 0x8126,
+                  &[(0x1, 0x02), (0xF, 0x00)])
+            .quirks(Quirks::schip()),
+        Case::new("8XY6 sets VF last", &[(0x2, 0x04)], 0x8F26,
+                  &[(0xF, 0x00)]),
+        Case::new("8XY7 subtracts", &[(0x1, 0x12), (0x2, 0x34)], 0x8127,
+                  &[(0x1, 0x22), (0xF, 0x01)]),
+        Case::new("8XY7 doesn't borrow on equal values",
+                  &[(0x1, 0x34), (0x2, 0x34)], 0x8127,
+                  &[(0x1, 0x00), (0xF, 0x01)]),
+        Case::new("8XY7 wraps and borrows", &[(0x1, 0x34), (0x2, 0x12)],
+                  0x8127, &[(0x1, 0xDE), (0xF, 0x00)]),
+        Case::new("8XY7 sets VF last", &[(0xF, 0x34), (0x2, 0x12)], 0x8F27,
+                  &[(0xF, 0x00)]),
+        Case::new("8XYE shifts VY", &[(0x1, 0x00), (0x2, 0x81)], 0x812E,
+                  &[(0x1, 0x02), (0x2, 0x81), (0xF, 0x01)]),
+        Case::new("8XYE shifts VX", &[(0x1, 0x41), (0x2, 0x81)], 0x812E,
+                  &[(0x1, 0x82), (0xF, 0x00)])
+            .quirks(Quirks::schip()),
+        Case::new("8XYE sets VF last", &[(0x2, 0x81)], 0x8F2E,
+                  &[(0xF, 0x01)]),
+    ];
+    for case in &cases {
+        case.run(&[]);
+    }
+}
+
+#[test]
+fn skips() {
+    let cases = [
+        Case::new("3XNN skips if equal", &[(0x1, 0x12)], 0x3112, &[])
+            .advance(4),
+        Case::new("3XNN doesn't skip if not equal", &[(0x1, 0x12)], 0x3113,
+                  &[]),
+        Case::new("4XNN skips if not equal", &[(0x1, 0x12)], 0x4113, &[])
+            .advance(4),
+        Case::new("4XNN doesn't skip if equal", &[(0x1, 0x12)], 0x4112, &[]),
+        Case::new("5XY0 skips if equal", &[(0x1, 0x12), (0x2, 0x12)], 0x5120,
+                  &[])
+            .advance(4),
+        Case::new("5XY0 doesn't skip if not equal",
+                  &[(0x1, 0x12), (0x2, 0x13)], 0x5120, &[]),
+        Case::new("9XY0 skips if not equal", &[(0x1, 0x12), (0x2, 0x13)],
+                  0x9120, &[])
+            .advance(4),
+        Case::new("9XY0 doesn't skip if equal", &[(0x1, 0x12), (0x2, 0x12)],
+                  0x9120, &[]),
+    ];
+    for case in &cases {
+        case.run(&[]);
+    }
+}
+
+#[test]
+fn key_skips() {
+    let cases = [
+        (Case::new("EX9E skips if pressed", &[(0x1, 0x5)], 0xE19E, &[])
+            .advance(4), true),
+        (Case::new("EX9E doesn't skip if not pressed", &[(0x1, 0x5)], 0xE19E,
+                   &[]), false),
+        (Case::new("EXA1 skips if not pressed", &[(0x1, 0x5)], 0xE1A1, &[])
+            .advance(4), false),
+        (Case::new("EXA1 doesn't skip if pressed", &[(0x1, 0x5)], 0xE1A1,
+                   &[]), true),
+    ];
+    for &(ref case, pressed) in &cases {
+        case.run(if pressed { &[0x5] } else { &[] });
+    }
+}
+
+#[test]
+fn key_wait_across_frames() {
+    let rom = [
+        0x60, 0x05,     // LD V0, 0x5
+        0xF0, 0x15,     // LD DT, V0
+        0xF1, 0x0A,     // LD V1, K
+        0x72, 0x01,     // ADD V2, 0x1
+        0x12, 0x08,     // JP 0x208
+    ];
+    let mut chip8 = Chip8::new(Quirks::modern());
+    chip8.set_instructions_per_frame(4);
+    chip8.load_rom(&rom).unwrap();
+
+    // Halted with the PC on FX0A, while the timers keep counting
+    for _ in 0..3 {
+        chip8.run_frame().unwrap();
+    }
+    assert_eq!(chip8.key_wait(), Some(KeyWait { reg: 0x1, key: None }));
+    assert_eq!(chip8.pc(), START + 0x4);
+    assert_eq!(chip8.delay_timer(), 2);
+
+    // A press is remembered but doesn't complete the wait, and nor does
+    // releasing a key pressed after it
+    chip8.press_key(0x7);
+    chip8.press_key(0x3);
+    chip8.run_frame().unwrap();
+    chip8.release_key(0x3);
+    chip8.run_frame().unwrap();
+    assert_eq!(chip8.key_wait(), Some(KeyWait { reg: 0x1, key: Some(0x7) }));
+    assert_eq!(chip8.pc(), START + 0x4);
+    assert_eq!(chip8.registers()[0x1], 0x0);
+    assert_eq!(chip8.registers()[0x2], 0x0);
+
+    // Releasing the first key stores it and moves past FX0A
+    chip8.release_key(0x7);
+    assert_eq!(chip8.key_wait(), None);
+    assert_eq!(chip8.registers()[0x1], 0x7);
+    assert_eq!(chip8.pc(), START + 0x6);
+    chip8.run_frame().unwrap();
+    assert_eq!(chip8.registers()[0x2], 0x1);
+    assert_eq!(chip8.pc(), START + 0x8);
+}
+
+#[test]
+fn skips_over_long_index_load() {
+    // LD V1, 0x12; SE V1, 0x12; LD I, LONG 0x0000
+    let mut chip8 = Chip8::new(Quirks::modern());
+    chip8.load_rom(&[0x61, 0x12, 0x31, 0x12, 0xF0, 0x00, 0x00, 0x00])
+        .unwrap();
+    chip8.execute_cycle().unwrap();
+    chip8.execute_cycle().unwrap();
+    assert_eq!(chip8.pc(), START + 0x8);
+}
+
+#[test]
+fn digit_sprites() {
+    // LD V1, 0x3A; LD F, V1; LD HF, V1
+    let mut chip8 = Chip8::new(Quirks::modern());
+    chip8.load_font(&Font::vip());
+    chip8.load_rom(&[0x61, 0x3A, 0xF1, 0x29, 0xF1, 0x30]).unwrap();
+    chip8.execute_cycle().unwrap();
+
+    chip8.execute_cycle().unwrap();
+    let index = chip8.index() as usize;
+    assert_eq!(index, FONT_START + 0xA * SMALL_GLYPH_SIZE);
+    let glyph = 0xA * SMALL_GLYPH_SIZE..0xB * SMALL_GLYPH_SIZE;
+    assert_eq!(&chip8.memory()[index..index + SMALL_GLYPH_SIZE],
+               &Font::vip().small[glyph]);
+
+    chip8.execute_cycle().unwrap();
+    assert_eq!(chip8.index() as usize, BIG_FONT_START + 0xA * BIG_GLYPH_SIZE);
+}
+
+#[test]
+fn load_store_advances_index() {
+    // LD I, 0x300; LD [I], V3 or LD V3, [I]
+    for &instr in &[0xF355, 0xF365] {
+        for &(quirks, index) in &[(Quirks::vip(), 0x304),
+                                  (Quirks::chip48(), 0x303),
+                                  (Quirks::schip(), 0x300)] {
+            let mut chip8 = Chip8::new(quirks);
+            let mut rom = vec![0xA3, 0x00];
+            rom.extend_from_slice(&u16::to_be_bytes(instr));
+            chip8.load_rom(&rom).unwrap();
+            chip8.execute_cycle().unwrap();
+            chip8.execute_cycle().unwrap();
+            assert_eq!(chip8.index(), index, "{:#06X} {:?}", instr, quirks);
+        }
+    }
+}
+
+#[test]
+fn unknown_opcode_leaves_state() {
+    // LD V1, 0x12; then an opcode no interpreter defines
+    let mut chip8 = Chip8::new(Quirks::modern());
+    chip8.load_rom(&[0x61, 0x12, 0x51, 0x21]).unwrap();
+    chip8.execute_cycle().unwrap();
+    let err = chip8.execute_cycle().unwrap_err();
+    assert_eq!(err, Chip8Error::UnknownOpcode { pc: START + 0x2,
+                                                instr: 0x5121 });
+    assert_eq!(chip8.pc(), START + 0x2);
+    assert_eq!(chip8.instr(), 0x6112);
+}
+
+#[test]
+fn stack_faults() {
+    // CALL 0x200, until the stack is full
+    let mut chip8 = Chip8::new(Quirks::modern());
+    chip8.load_rom(&[0x22, 0x00]).unwrap();
+    execute(&mut chip8, 16);
+    let err = chip8.execute_cycle().unwrap_err();
+    assert_eq!(err, Chip8Error::StackOverflow { pc: START });
+    assert_eq!(err.to_string(), "0x0200: Stack overflow");
+    assert_eq!((chip8.pc(), chip8.sp()), (START, 16));
+
+    // RET
+    let mut chip8 = Chip8::new(Quirks::modern());
+    chip8.load_rom(&[0x00, 0xEE]).unwrap();
+    let err = chip8.execute_cycle().unwrap_err();
+    assert_eq!(err, Chip8Error::StackUnderflow { pc: START });
+    assert_eq!(err.to_string(), "0x0200: Stack underflow");
+    assert_eq!((chip8.pc(), chip8.sp()), (START, 0));
+}
+
+#[test]
+fn memory_faults() {
+    // LD I, LONG 0xFFFE; then an access running past the top of memory
+    for &(instr, addr) in &[(0xFF55, 0x10000), (0xFF65, 0x10000),
+                            (0xF033, 0x10000), (0xD00F, 0x10000)] {
+        let mut chip8 = Chip8::new(Quirks::modern());
+        let mut rom = vec![0xF0, 0x00, 0xFF, 0xFE];
+        rom.extend_from_slice(&u16::to_be_bytes(instr));
+        chip8.load_rom(&rom).unwrap();
+        chip8.execute_cycle().unwrap();
+        let (registers, memory) = (*chip8.registers(), chip8.memory().to_vec());
+
+        let err = chip8.execute_cycle().unwrap_err();
+        assert_eq!(err, Chip8Error::MemoryOutOfBounds { pc: START + 0x4,
+                                                        addr },
+                   "{:#06X}", instr);
+        assert_eq!(chip8.pc(), START + 0x4);
+        assert_eq!(chip8.index(), 0xFFFE);
+        assert_eq!(chip8.registers(), &registers);
+        assert!(chip8.memory() == &memory[..]);
+        assert!(lit_pixels(&chip8).is_empty());
+    }
+
+    let err = Chip8Error::MemoryOutOfBounds { pc: 0x204, addr: 0x10000 };
+    assert_eq!(err.to_string(),
+               "0x0204: Memory access out of bounds at 0x10000");
+}
+
+#[test]
+fn wraps_at_top_of_memory() {
+    // SE V1, 0x00 at 0xFFFC and 0xFFFE
+    for &(start, pc) in &[(0xFFFC, 0x0000), (0xFFFE, 0x0002)] {
+        let mut chip8 = Chip8::new(Quirks::modern());
+        chip8.set_extended_memory(true);
+        chip8.set_load_address(start);
+        chip8.load_rom(&[0x31, 0x00]).unwrap();
+        chip8.execute_cycle().unwrap();
+        assert_eq!(chip8.pc(), pc, "skip from {:#06X}", start);
+    }
+
+    // SE V1, 0x00 at 0xFFFB onto 0x12 at 0xFFFF, which reads 0x1200 with
+    // the byte at 0x0000
+    let mut chip8 = Chip8::new(Quirks::modern());
+    chip8.set_extended_memory(true);
+    chip8.set_load_address(0xFFFB);
+    chip8.load_rom(&[0x31, 0x00, 0xFF, 0xFF, 0x12]).unwrap();
+    chip8.execute_cycle().unwrap();
+    assert_eq!(chip8.pc(), 0xFFFF);
+    chip8.execute_cycle().unwrap();
+    assert_eq!(chip8.pc(), 0x0200);
+
+    // LD I, LONG 0xFFFF; LD V0, 0x10; ADD I, V0
+    let mut chip8 = Chip8::new(Quirks::modern());
+    chip8.load_rom(&[0xF0, 0x00, 0xFF, 0xFF, 0x60, 0x10, 0xF0, 0x1E])
+        .unwrap();
+    for _ in 0..3 {
+        chip8.execute_cycle().unwrap();
+    }
+    assert_eq!(chip8.index(), 0x000F);
+
+    // LD I, LONG 0xFFF0; LD [I], VF; LD VF, [I]
+    for &instr in &[0xFF55, 0xFF65] {
+        let mut chip8 = Chip8::new(Quirks::vip());
+        let mut rom = vec![0xF0, 0x00, 0xFF, 0xF0];
+        rom.extend_from_slice(&u16::to_be_bytes(instr));
+        chip8.load_rom(&rom).unwrap();
+        chip8.execute_cycle().unwrap();
+        chip8.execute_cycle().unwrap();
+        assert_eq!(chip8.index(), 0x0000, "{:#06X}", instr);
+    }
+
+    // LD V1, K at 0xFFFE
+    let mut chip8 = Chip8::new(Quirks::modern());
+    chip8.set_extended_memory(true);
+    chip8.set_load_address(0xFFFE);
+    chip8.load_rom(&[0xF1, 0x0A]).unwrap();
+    chip8.execute_cycle().unwrap();
+    chip8.press_key(0x5);
+    chip8.release_key(0x5);
+    assert_eq!(chip8.pc(), 0x0000);
+    assert_eq!(chip8.registers()[0x1], 0x5);
+}
+
+#[test]
+fn xo_chip_planes() {
+    let mut rom = vec![
+        0xF2, 0x01,     // PLANE 2
+        0xA2, 0x20,     // LD I, 0x220
+        0xD0, 0x01,     // DRW V0, V0, 1
+        0xF1, 0x01,     // PLANE 1
+        0x00, 0xE0,     // CLS
+        0xF3, 0x01,     // PLANE 3
+        0xD0, 0x01,     // DRW V0, V0, 1
+        0xF0, 0x01,     // PLANE 0
+        0xD0, 0x01,     // DRW V0, V0, 1
+    ];
+    rom.resize(0x20, 0x0);
+    rom.extend_from_slice(&[0xC0, 0x40]);
+
+    // Only the second plane is drawn to, and clearing the first keeps it
+    let mut chip8 = run(Quirks::modern(), &rom, 5);
+    assert_eq!(lit_pixels(&chip8), [(0, 0, 2), (1, 0, 2)]);
+
+    // Each selected plane takes the next sprite in memory
+    execute(&mut chip8, 2);
+    assert_eq!(lit_pixels(&chip8), [(0, 0, 3), (1, 0, 1)]);
+    assert_eq!(chip8.registers()[0xF], 0x1);
+
+    // No planes, nothing drawn
+    execute(&mut chip8, 2);
+    assert_eq!(lit_pixels(&chip8), [(0, 0, 3), (1, 0, 1)]);
+    assert_eq!(chip8.registers()[0xF], 0x0);
+}
+
+#[test]
+fn xo_chip_register_ranges() {
+    let rom = [
+        0x61, 0x01, 0x62, 0x02, 0x63, 0x03,
+        0xA3, 0x00,     // LD I, 0x300
+        0x51, 0x32,     // SAVE V1 - V3
+        0xA3, 0x04,     // LD I, 0x304
+        0x53, 0x12,     // SAVE V3 - V1
+        0xA3, 0x00,     // LD I, 0x300
+        0x54, 0x63,     // LOAD V4 - V6
+        0x5A, 0x83,     // LOAD VA - V8
+    ];
+    let chip8 = run(Quirks::modern(), &rom, 10);
+    assert_eq!(&chip8.memory()[0x300..0x307], &[1, 2, 3, 0, 3, 2, 1]);
+    assert_eq!(&chip8.registers()[0x4..0x7], &[1, 2, 3]);
+    assert_eq!(&chip8.registers()[0x8..0xB], &[3, 2, 1]);
+    assert_eq!(chip8.index(), 0x300);
+}
+
+#[test]
+fn xo_chip_audio() {
+    let mut rom = vec![
+        0xF0, 0x18,     // LD ST, V0
+        0xA2, 0x20,     // LD I, 0x220
+        0xF0, 0x02,     // AUDIO
+        0x60, 0x70,     // LD V0, 0x70
+        0xF0, 0x3A,     // PITCH V0
+        0x60, 0x02,     // LD V0, 0x2
+        0xF0, 0x18,     // LD ST, V0
+    ];
+    rom.resize(0x20, 0x0);
+    let pattern = [0xF0, 0x0F, 0xAA, 0x55, 0x01, 0x02, 0x03, 0x04,
+                   0x05, 0x06, 0x07, 0x08, 0x80, 0x40, 0x20, 0x10];
+    rom.extend_from_slice(&pattern);
+
+    // The classic beep until a pattern is loaded
+    let mut chip8 = run(Quirks::modern(), &[0x60, 0x02, 0xF0, 0x18], 2);
+    assert_eq!(chip8.tone(), Some(Tone::Beep));
+
+    // A pattern and pitch only sound while the sound timer runs
+    chip8 = run(Quirks::modern(), &rom, 3);
+    assert_eq!(chip8.tone(), None);
+    execute(&mut chip8, 2);
+    assert_eq!(chip8.tone(), None);
+    execute(&mut chip8, 2);
+    assert_eq!(chip8.tone(),
+               Some(Tone::Pattern { pattern, pitch: 0x70 }));
+    chip8.tick_timers();
+    chip8.tick_timers();
+    assert_eq!(chip8.tone(), None);
+
+    // The pattern plays at the default pitch until FX3A
+    let chip8 = run(Quirks::modern(), &[0xA2, 0x00, 0xF0, 0x02, 0x60, 0x01,
+                                          0xF0, 0x18], 4);
+    assert_eq!(chip8.tone(), Some(Tone::Pattern {
+        pattern: [0xA2, 0x00, 0xF0, 0x02, 0x60, 0x01, 0xF0, 0x18,
+                  0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0],
+        pitch: DEFAULT_PITCH,
+    }));
+}
+
+#[test]
+fn schip_resolution() {
+    let rom = [
+        0xA2, 0x00,     // LD I, 0x200
+        0xD0, 0x01,     // DRW V0, V0, 1
+        0x00, 0xFF,     // HIGH
+        0xD0, 0x01,     // DRW V0, V0, 1
+        0x00, 0xFE,     // LOW
+    ];
+    let mut chip8 = run(Quirks::schip(), &rom, 2);
+    assert!(!chip8.is_hires());
+    assert!(!lit_pixels(&chip8).is_empty());
+
+    // Each switch clears the screen
+    execute(&mut chip8, 1);
+    assert!(chip8.is_hires());
+    assert_eq!((chip8.width(), chip8.height()), (128, 64));
+    assert_eq!(chip8.framebuffer().len(), 128 * 64);
+    assert!(lit_pixels(&chip8).is_empty());
+
+    execute(&mut chip8, 1);
+    assert!(!lit_pixels(&chip8).is_empty());
+    execute(&mut chip8, 1);
+    assert!(!chip8.is_hires());
+    assert_eq!((chip8.width(), chip8.height()), (64, 32));
+    assert_eq!(chip8.framebuffer().len(), 64 * 32);
+    assert!(lit_pixels(&chip8).is_empty());
+}
+
+#[test]
+fn schip_scrolling() {
+    let mut rom = vec![
+        0x00, 0xFF,     // HIGH
+        0x60, 0x08,     // LD V0, 0x8
+        0xA2, 0x20,     // LD I, 0x220
+        0xD0, 0x01,     // DRW V0, V0, 1
+        0x00, 0xC3,     // SCD 3
+        0x00, 0xFB,     // SCR
+        0x00, 0xFC,     // SCL
+        0x00, 0xFC,     // SCL
+        0x00, 0xFC,     // SCL
+        0x00, 0xFC,     // SCL
+        0x00, 0xC0,     // SCD 0
+    ];
+    rom.resize(0x20, 0x0);
+    rom.push(0x80);
+
+    let mut chip8 = run(Quirks::schip(), &rom, 4);
+    assert_eq!(lit_pixels(&chip8), [(8, 8, 1)]);
+    execute(&mut chip8, 1);
+    assert_eq!(lit_pixels(&chip8), [(8, 11, 1)]);
+    execute(&mut chip8, 1);
+    assert_eq!(lit_pixels(&chip8), [(12, 11, 1)]);
+    execute(&mut chip8, 1);
+    assert_eq!(lit_pixels(&chip8), [(8, 11, 1)]);
+    execute(&mut chip8, 2);
+    assert_eq!(lit_pixels(&chip8), [(0, 11, 1)]);
+
+    // Pixels scrolled off the edge are lost
+    execute(&mut chip8, 1);
+    assert!(lit_pixels(&chip8).is_empty());
+    execute(&mut chip8, 1);
+    assert_eq!(chip8.pc(), START + 0x16);
+}
+
+#[test]
+fn schip_big_sprites() {
+    let mut rom = vec![
+        0x00, 0xFF,     // HIGH
+        0x60, 0x70,     // LD V0, 0x70
+        0x61, 0x30,     // LD V1, 0x30
+        0xA2, 0x20,     // LD I, 0x220
+        0xD0, 0x10,     // DRW V0, V1, 0
+        0xD0, 0x10,     // DRW V0, V1, 0
+    ];
+    rom.resize(0x20, 0x0);
+    // A 16x16 square outline, two bytes per row
+    rom.extend_from_slice(&[0xFF, 0xFF]);
+    for _ in 0..14 {
+        rom.extend_from_slice(&[0x80, 0x01]);
+    }
+    rom.extend_from_slice(&[0xFF, 0xFF]);
+
+    let mut chip8 = run(Quirks::schip(), &rom, 5);
+    let pixels = lit_pixels(&chip8);
+    assert_eq!(pixels.len(), 60);
+    assert!(pixels.contains(&(0x70, 0x30, 1)));
+    assert!(pixels.contains(&(0x70 + 15, 0x30 + 15, 1)));
+    assert!(pixels.iter().all(|&(x, y, _)| {
+        (0x70..0x70 + 16).contains(&x) && (0x30..0x30 + 16).contains(&y)
+    }));
+    assert_eq!(chip8.registers()[0xF], 0x0);
+
+    // Drawing it again erases it
+    execute(&mut chip8, 1);
+    assert!(lit_pixels(&chip8).is_empty());
+    assert_eq!(chip8.registers()[0xF], 0x1);
+}
+
+#[test]
+fn schip_big_font() {
+    // LD V1, 0x7; LD HF, V1; DRW V0, V0, 10
+    let rom = [0x00, 0xFF, 0x61, 0x07, 0xF1, 0x30, 0xD0, 0x0A];
+    let mut chip8 = Chip8::new(Quirks::schip());
+    chip8.load_font(&Font::schip());
+    chip8.load_rom(&rom).unwrap();
+    execute(&mut chip8, 4);
+
+    let glyph = &Font::schip().big[0x7 * BIG_GLYPH_SIZE..];
+    for (y, &row) in glyph[..BIG_GLYPH_SIZE].iter().enumerate() {
+        let drawn = (0..8).fold(0x0, |byte, x| {
+            byte << 1 | chip8.framebuffer()[y * chip8.width() + x]
+        });
+        assert_eq!(drawn, row, "row {}", y);
+    }
+}
+
+#[test]
+fn schip_rpl_flags() {
+    let rom = [
+        0x60, 0x11, 0x61, 0x22, 0x62, 0x33,
+        0xF2, 0x75,     // LD R, V2
+        0x60, 0x00, 0x61, 0x00, 0x62, 0x00,
+        0xF1, 0x85,     // LD V1, R
+    ];
+    let chip8 = run(Quirks::schip(), &rom, 8);
+    assert_eq!(&chip8.rpl_flags()[..4], &[0x11, 0x22, 0x33, 0x00]);
+    assert_eq!(&chip8.registers()[..3], &[0x11, 0x22, 0x00]);
+
+    // Flags set by the host are loaded as well
+    let mut chip8 = Chip8::new(Quirks::schip());
+    let mut flags = *chip8.rpl_flags();
+    flags[0x0] = 0x99;
+    chip8.set_rpl_flags(&flags);
+    chip8.load_rom(&[0xF0, 0x85]).unwrap();
+    execute(&mut chip8, 1);
+    assert_eq!(chip8.registers()[0x0], 0x99);
+}
+
+#[test]
+fn schip_exit() {
+    // LD V0, 0x1; EXIT; LD V0, 0x2
+    let mut chip8 = run(Quirks::schip(), &[0x60, 0x01, 0x00, 0xFD, 0x60, 0x02],
+                        2);
+    assert!(chip8.has_exited());
+    assert_eq!(chip8.pc(), START + 0x2);
+
+    // Nothing executes afterwards
+    chip8.run_frame().unwrap();
+    execute(&mut chip8, 1);
+    assert_eq!(chip8.pc(), START + 0x2);
+    assert_eq!(chip8.registers()[0x0], 0x1);
+}
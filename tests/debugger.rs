@@ -0,0 +1,176 @@
+extern crate chip8;
+
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+use chip8::{Chip8, Quirks};
+use chip8::debugger::{Condition, Debugger, StopReason};
+use chip8::debugger::repl::Repl;
+use chip8::debugger::condition::{Comparison, Operand};
+
+#[test]
+fn parses_conditions() {
+    let condition = Condition::parse("V3 == 0x10").unwrap();
+    assert_eq!(condition, Condition {
+        operand: Operand::V(0x3),
+        comparison: Comparison::Eq,
+        value: 0x10,
+    });
+    assert_eq!(condition.to_string(), "V3 == 0x0010");
+
+    let condition = Condition::parse("i<=768").unwrap();
+    assert_eq!((condition.operand, condition.comparison, condition.value),
+               (Operand::Index, Comparison::Le, 0x300));
+    let condition = Condition::parse("PC > 0x200").unwrap();
+    assert_eq!((condition.operand, condition.comparison),
+               (Operand::Pc, Comparison::Gt));
+
+    for &text in &["V3 0x10", "VG == 1", "V10 == 1", "V3 == zz",
+                   "DT != 0x10000"] {
+        assert!(Condition::parse(text).is_err(), "{}", text);
+    }
+}
+
+#[test]
+fn evaluates_conditions() {
+    // LD V3, 0x10
+    let mut chip8 = Chip8::new(Quirks::default());
+    chip8.load_rom(&[0x63, 0x10]).unwrap();
+    chip8.execute_cycle().unwrap();
+
+    let holds = |text: &str| Condition::parse(text).unwrap().eval(&chip8);
+    assert!(holds("V3 == 0x10"));
+    assert!(!holds("V3 != 16"));
+    assert!(holds("V3 < 0x11") && !holds("V3 < 0x10"));
+    assert!(holds("V3 >= 0x10") && !holds("V3 > 0x10"));
+    assert!(holds("PC == 0x202") && holds("SP == 0"));
+}
+
+#[test]
+fn stops_when_condition_becomes_true() {
+    // ADD V1, 1; JP 0x200
+    let mut chip8 = Chip8::new(Quirks::default());
+    chip8.load_rom(&[0x71, 0x01, 0x12, 0x00]).unwrap();
+    let mut debugger = Debugger::new();
+    let condition = Condition::parse("V1 >= 5").unwrap();
+    debugger.add_condition(condition, &chip8);
+    assert_eq!(debugger.run(&mut chip8, Some(100)),
+               StopReason::Condition(condition));
+    assert_eq!(chip8.registers()[0x1], 5);
+}
+
+#[test]
+fn steps_back_across_frames() {
+    // LD V2, 0x20; LD DT, V2; RND V0, 0xFF; ADD V1, 1; LD I, 0x300;
+    // LD [I], V1; JP 0x204
+    let rom = [0x62, 0x20, 0xF2, 0x15, 0xC0, 0xFF, 0x71, 0x01, 0xA3, 0x00,
+               0xF1, 0x55, 0x12, 0x04];
+    let mut chip8 = Chip8::new(Quirks::default());
+    chip8.set_seed(1);
+    chip8.set_instructions_per_frame(7);
+    chip8.load_rom(&rom).unwrap();
+
+    let mut debugger = Debugger::new();
+    let mut states = Vec::new();
+    for _ in 0..50 {
+        states.push(chip8.save_state());
+        assert_eq!(debugger.step(&mut chip8), StopReason::Stepped);
+    }
+    assert_eq!(debugger.history_len(), 50);
+
+    while let Some(state) = states.pop() {
+        assert!(debugger.step_back(&mut chip8));
+        assert_eq!(chip8.save_state(), state, "cycle {}", states.len());
+        assert_eq!(debugger.cycles(), states.len() as u64);
+    }
+    assert!(!debugger.step_back(&mut chip8));
+}
+
+#[test]
+fn steps_back_to_oldest_snapshot() {
+    // ADD V1, 1; JP 0x200
+    let mut chip8 = Chip8::new(Quirks::default());
+    chip8.set_instructions_per_frame(10);
+    chip8.load_rom(&[0x71, 0x01, 0x12, 0x00]).unwrap();
+    let mut debugger = Debugger::new();
+    debugger.set_history_budget(chip8.save_state().len() + 200);
+    for _ in 0..500 {
+        debugger.step(&mut chip8);
+    }
+
+    let len = debugger.history_len();
+    assert!(len > 0 && len < 500);
+    for _ in 0..len {
+        assert!(debugger.step_back(&mut chip8));
+    }
+    assert!(!debugger.step_back(&mut chip8));
+    assert_eq!(debugger.cycles(), 500 - len as u64);
+}
+
+#[test]
+fn steps_over_call_at_top_of_memory() {
+    let rom = [
+        0xA0, 0x10,     // LD I, 0x010
+        0x60, 0x00,     // LD V0, 0x00
+        0x61, 0xEE,     // LD V1, 0xEE
+        0xF1, 0x55,     // LD [I], V1
+        0x60, 0x00, 0x60, 0x00, 0x60, 0x00,
+        0x20, 0x10,     // CALL 0x010, which returns to 0x0000
+    ];
+    let mut chip8 = Chip8::new(Quirks::default());
+    chip8.set_extended_memory(true);
+    chip8.set_load_address(0xFFF0);
+    chip8.load_rom(&rom).unwrap();
+
+    let mut debugger = Debugger::new();
+    for _ in 0..7 {
+        assert_eq!(debugger.step(&mut chip8), StopReason::Stepped);
+    }
+    assert_eq!(chip8.pc(), 0xFFFE);
+    assert_eq!(debugger.step_over(&mut chip8, Some(10)), StopReason::Stepped);
+    assert_eq!((chip8.pc(), chip8.sp()), (0x0000, 0));
+}
+
+#[test]
+fn interrupts_endless_run() {
+    // JP 0x200
+    let mut chip8 = Chip8::new(Quirks::default());
+    chip8.load_rom(&[0x12, 0x00]).unwrap();
+    let mut debugger = Debugger::new();
+
+    // An interrupt while stopped is ignored by the next run
+    let interrupt = debugger.interrupt_flag();
+    interrupt.store(true, Ordering::SeqCst);
+    assert_eq!(debugger.run(&mut chip8, Some(5)), StopReason::CycleLimit);
+
+    let interrupter = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        interrupt.store(true, Ordering::SeqCst);
+    });
+    assert_eq!(debugger.run(&mut chip8, None), StopReason::Interrupted);
+    interrupter.join().unwrap();
+    assert!(!debugger.interrupt_flag().load(Ordering::SeqCst));
+}
+
+#[test]
+fn repl_counts_past_16_bits() {
+    // JP 0x200
+    let mut chip8 = Chip8::new(Quirks::default());
+    chip8.load_rom(&[0x12, 0x00]).unwrap();
+    let mut repl = Repl::new();
+    let mut out = Vec::new();
+
+    repl.execute(&mut chip8, "step 70000", &mut out).unwrap();
+    assert_eq!(repl.debugger().cycles(), 70000);
+    repl.execute(&mut chip8, "continue 0x20000", &mut out).unwrap();
+    assert_eq!(repl.debugger().cycles(), 70000 + 0x20000);
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.contains("Cycle limit reached"), "{}", out);
+
+    let mut out = Vec::new();
+    repl.execute(&mut chip8, "continue 99999999999999999999", &mut out)
+        .unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(),
+               "bad count 99999999999999999999\n");
+}
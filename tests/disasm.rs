@@ -0,0 +1,90 @@
+extern crate chip8;
+
+use chip8::disasm::{self, Line, DEFAULT_ORIGIN};
+use chip8::instruction::Instruction;
+
+fn code(addr: u16, instruction: Instruction) -> Line {
+    Line::Code { addr, instruction }
+}
+
+fn data(addr: u16, bytes: &[u8]) -> Line {
+    Line::Data { addr, bytes: bytes.to_vec() }
+}
+
+#[test]
+fn splits_code_from_data() {
+    let rom = [
+        0xA2, 0x08,     // LD I, 0x208
+        0xD0, 0x15,     // DRW V0, V1, 5
+        0x12, 0x04,     // JP 0x204
+        0x00, 0xEE,     // unreachable, though it decodes as RET
+        0xF0, 0x90, 0x90, 0x90, 0xF0,
+        0x01, 0x02, 0x03, 0x04, 0x05,
+    ];
+    let disassembly = disasm::disassemble(&rom, DEFAULT_ORIGIN);
+    assert_eq!(disassembly.lines(), &[
+        code(0x200, Instruction::LdIndexAddr(0x208)),
+        code(0x202, Instruction::DrwVxVyNib(0x0, 0x1, 0x5)),
+        code(0x204, Instruction::JpAddr(0x204)),
+        data(0x206, &[0x00, 0xEE, 0xF0, 0x90, 0x90, 0x90, 0xF0, 0x01]),
+        data(0x20E, &[0x02, 0x03, 0x04, 0x05]),
+    ]);
+
+    // Addresses only loaded into I aren't labelled
+    assert_eq!(disassembly.label(0x208), None);
+}
+
+#[test]
+fn skips_over_long_index_load() {
+    let rom = [
+        0x31, 0x00,                 // SE V1, 0x0
+        0xF0, 0x00, 0x02, 0x0A,     // LD I, LONG 0x20A
+        0x12, 0x06,                 // JP 0x206
+        0xAB, 0xCD,
+    ];
+    let disassembly = disasm::disassemble(&rom, DEFAULT_ORIGIN);
+    assert_eq!(disassembly.lines(), &[
+        code(0x200, Instruction::SeVxByte(0x1, 0x0)),
+        code(0x202, Instruction::LdIndexLong(0x20A)),
+        code(0x206, Instruction::JpAddr(0x206)),
+        data(0x208, &[0xAB, 0xCD]),
+    ]);
+}
+
+#[test]
+fn labels_branch_targets() {
+    let rom = [
+        0x22, 0x06,     // CALL 0x206
+        0x12, 0x0A,     // JP 0x20A
+        0x12, 0x03,     // unreachable
+        0x60, 0x01,     // LD V0, 0x1
+        0x00, 0xEE,     // RET
+        0xB2, 0x0E,     // JP V0, 0x20E
+        0x00, 0xFD,     // unreachable
+        0x12, 0x10,     // JP 0x210, past the end of the ROM
+    ];
+    let disassembly = disasm::disassemble(&rom, DEFAULT_ORIGIN);
+    for &addr in &[0x206, 0x20A, 0x20E] {
+        assert_eq!(disassembly.label(addr),
+                   Some(format!("L{:04X}", addr)).as_deref());
+    }
+
+    // Neither unreachable code nor targets outside the ROM get labels
+    for &addr in &[0x200, 0x203, 0x210] {
+        assert_eq!(disassembly.label(addr), None, "{:#06X}", addr);
+    }
+
+    assert_eq!(disassembly.to_string(), concat!(
+        "    CALL L0206                                          ; 0200\n",
+        "    JP L020A                                            ; 0202\n",
+        "    db 0x12, 0x03                                       ; 0204\n",
+        "L0206:\n",
+        "    LD V0, 0x01                                         ; 0206\n",
+        "    RET                                                 ; 0208\n",
+        "L020A:\n",
+        "    JP V0, L020E                                        ; 020A\n",
+        "    db 0x00, 0xFD                                       ; 020C\n",
+        "L020E:\n",
+        "    JP 0x0210                                           ; 020E\n",
+    ));
+}
@@ -0,0 +1,39 @@
+extern crate chip8;
+extern crate png;
+
+use chip8::dump;
+
+/// Two rows of four pixels with every colour in both orders.
+const FB: [u8; 8] = [0, 1, 2, 3, 3, 2, 1, 0];
+
+/// Decode a PNG, returning its size and pixels.
+fn decode_png(bytes: &[u8]) -> (u32, u32, Vec<u8>) {
+    let decoder = png::Decoder::new(bytes);
+    let mut reader = decoder.read_info().unwrap();
+    let mut pixels = vec![0x0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).unwrap();
+    assert_eq!(info.color_type, png::ColorType::Grayscale);
+    assert_eq!(info.bit_depth, png::BitDepth::Eight);
+    pixels.truncate(info.buffer_size());
+    (info.width, info.height, pixels)
+}
+
+#[test]
+fn ascii() {
+    assert_eq!(dump::ascii(&FB, 4), "_███\n███_\n");
+}
+
+#[test]
+fn pbm() {
+    let mut out = Vec::new();
+    dump::write_pbm(&FB, 4, 2, &mut out).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "P1\n4 2\n0 1 1 1\n1 1 1 0\n");
+}
+
+#[test]
+fn png() {
+    let mut out = Vec::new();
+    dump::write_png(&FB, 4, 2, &mut out).unwrap();
+    assert_eq!(decode_png(&out),
+               (4, 2, vec![0x00, 0xFF, 0xAA, 0x55, 0x55, 0xAA, 0xFF, 0x00]));
+}
@@ -0,0 +1,68 @@
+extern crate chip8;
+
+use chip8::filter::{self, Filter, FilterMode};
+use chip8::palette::{Palette, Rgb};
+
+const OFF: Rgb = [0x00, 0x00, 0x00];
+const ON: Rgb = [0xFF, 0xFF, 0xFF];
+
+fn palette() -> Palette {
+    Palette::parse("000000,FFFFFF,FF0000,00FF00").unwrap()
+}
+
+#[test]
+fn looks_up_modes() {
+    for &name in filter::MODES {
+        assert!(FilterMode::from_name(name).is_some(), "{}", name);
+    }
+    assert_eq!(FilterMode::from_name("blur"), None);
+}
+
+#[test]
+fn off_shows_each_frame() {
+    let mut filter = Filter::default();
+    filter.apply(&[0x1, 0x0], &palette());
+    assert_eq!(filter.apply(&[0x0, 0x2], &palette()),
+               &[OFF, [0xFF, 0x00, 0x00]]);
+}
+
+#[test]
+fn or_combines_last_two_frames() {
+    let mut filter = Filter::new(FilterMode::Or, 0.25);
+    assert_eq!(filter.apply(&[0x1, 0x0, 0x0], &palette()), &[ON, OFF, OFF]);
+    assert_eq!(filter.apply(&[0x0, 0x1, 0x0], &palette()), &[ON, ON, OFF]);
+    assert_eq!(filter.apply(&[0x0, 0x0, 0x0], &palette()), &[OFF, ON, OFF]);
+
+    // Bitplanes lit in either frame are combined
+    filter.apply(&[0x1, 0x0, 0x0], &palette());
+    assert_eq!(filter.apply(&[0x2, 0x0, 0x0], &palette())[0],
+               [0x00, 0xFF, 0x00]);
+}
+
+#[test]
+fn blend_dims_previous_frame() {
+    let mut filter = Filter::new(FilterMode::Blend, 0.5);
+    filter.apply(&[0x1, 0x1], &palette());
+    assert_eq!(filter.apply(&[0x0, 0x1], &palette()),
+               &[[0x80, 0x80, 0x80], ON]);
+    assert_eq!(filter.apply(&[0x0, 0x1], &palette()), &[OFF, ON]);
+}
+
+#[test]
+fn decay_fades_out() {
+    let mut filter = Filter::new(FilterMode::Decay, 0.5);
+    filter.apply(&[0x1], &palette());
+    let fades: Vec<u8> = (0..3)
+        .map(|_| filter.apply(&[0x0], &palette())[0][0])
+        .collect();
+    assert_eq!(fades, [0x80, 0x40, 0x20]);
+    assert_eq!(filter.apply(&[0x1], &palette()), &[ON]);
+}
+
+#[test]
+fn resolution_change_starts_afresh() {
+    let mut filter = Filter::new(FilterMode::Or, 0.5);
+    filter.apply(&[0x1, 0x1], &palette());
+    assert_eq!(filter.apply(&[0x0, 0x0, 0x0, 0x0], &palette()),
+               &[OFF, OFF, OFF, OFF]);
+}
@@ -0,0 +1,161 @@
+extern crate png;
+
+use std::env;
+use std::fs::{self, File};
+use std::path::PathBuf;
+use std::process::{self, Command, Output};
+
+/// Run chip8-headless with `args`.
+fn headless(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_chip8-headless"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn rejects_too_many_frames() {
+    let output = headless(&["--frames", "18446744073709551615", "IBMLOGO"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("--frames is too many"),
+            "{}", stderr(&output));
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn rejects_debug_with_scripted_run() {
+    for args in &[["--play", "run.movie"], ["--keys", "0:+5"],
+                  ["--frames", "10"], ["--cycles", "10"]] {
+        let output = headless(&["--debug", args[0], args[1], "IBMLOGO"]);
+        assert_eq!(output.status.code(), Some(1), "{:?}", args);
+        assert!(stderr(&output).contains("--debug can't be used with"),
+                "{}", stderr(&output));
+    }
+}
+
+/// A temporary directory for one test's files, removed when dropped.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> TempDir {
+        let dir = env::temp_dir()
+            .join(format!("chip8-headless-{}-{}", name, process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        TempDir(dir)
+    }
+
+    fn path(&self, file: &str) -> String {
+        self.0.join(file).to_str().unwrap().to_string()
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+#[test]
+fn dumps_ascii() {
+    let output = headless(&["--frames", "30", "IBMLOGO"]);
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert_eq!(stdout(&output),
+               include_str!("golden/ibmlogo.txt"));
+    assert!(stderr(&output).contains("pc: 0x0228"), "{}", stderr(&output));
+}
+
+#[test]
+fn dumps_pbm() {
+    let output = headless(&["--frames", "30", "--dump", "pbm", "IBMLOGO"]);
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert_eq!(stdout(&output),
+               include_str!("golden/ibmlogo.pbm"));
+}
+
+#[test]
+fn dumps_png_to_file() {
+    let dir = TempDir::new("png");
+    let file = dir.path("ibmlogo.png");
+    let output = headless(&["--frames", "30", "--dump", "png", "--output",
+                            &file, "IBMLOGO"]);
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert!(output.stdout.is_empty());
+
+    let decoder = png::Decoder::new(File::open(&file).unwrap());
+    let mut reader = decoder.read_info().unwrap();
+    let mut pixels = vec![0x0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).unwrap();
+    assert_eq!((info.width, info.height), (64, 32));
+    let expected: Vec<u8> = include_str!("golden/ibmlogo.txt").chars()
+        .filter(|&c| c != '\n')
+        .map(|c| if c == '█' { 0xFF } else { 0x00 })
+        .collect();
+    assert_eq!(&pixels[..info.buffer_size()], &expected[..]);
+}
+
+#[test]
+fn stops_after_cycles() {
+    // The first instruction clears the screen, so nothing is drawn yet
+    let output = headless(&["--cycles", "1", "IBMLOGO"]);
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert!(stdout(&output).lines().all(|line| line == "_".repeat(64)));
+    assert!(stderr(&output).contains("pc: 0x0202"), "{}", stderr(&output));
+}
+
+#[test]
+fn scripted_keys() {
+    // LD V1, K; LD F, V1; DRW V0, V0, 5; JP 0x206
+    let dir = TempDir::new("keys");
+    let rom = dir.path("key.ch8");
+    fs::write(&rom, [0xF1, 0x0A, 0xF1, 0x29, 0xD0, 0x05, 0x12, 0x06])
+        .unwrap();
+
+    let output = headless(&["--frames", "10", "--keys", "2:+5,4:-5",
+                            "--no-database", &rom]);
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert!(stderr(&output).contains("v[1]: 0x05"), "{}", stderr(&output));
+    assert_eq!(stdout(&output).lines().next(),
+               Some(format!("████{}", "_".repeat(60))).as_deref());
+
+    // Without the key the program is still waiting
+    let output = headless(&["--frames", "10", "--no-database", &rom]);
+    assert!(stderr(&output).contains("key_wait: Some"), "{}",
+            stderr(&output));
+}
+
+#[test]
+fn faults_exit_with_status_2() {
+    // RET
+    let dir = TempDir::new("fault");
+    let rom = dir.path("fault.ch8");
+    fs::write(&rom, [0x00, 0xEE]).unwrap();
+
+    let output = headless(&["--no-database", &rom]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("0x0200: Stack underflow"), "{}",
+            stderr(&output));
+}
+
+#[test]
+fn saves_and_loads_state() {
+    let dir = TempDir::new("state");
+    let state = dir.path("ibmlogo.state");
+    let output = headless(&["--cycles", "5", "--save-state", &state,
+                            "IBMLOGO"]);
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+
+    // Resuming for the rest of the frames draws the same logo
+    let output = headless(&["--frames", "30", "--load-state", &state,
+                            "IBMLOGO"]);
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert_eq!(stdout(&output),
+               include_str!("golden/ibmlogo.txt"));
+}
@@ -0,0 +1,103 @@
+extern crate chip8;
+
+use chip8::Instruction;
+use chip8::instruction::DecodeError;
+
+#[test]
+fn decodes_each_instruction_form() {
+    let cases = [
+        (0x00C3, Instruction::ScdNib(0x3)),
+        (0x00E0, Instruction::Cls),
+        (0x00EE, Instruction::Ret),
+        (0x00FB, Instruction::Scr),
+        (0x00FC, Instruction::Scl),
+        (0x00FD, Instruction::Exit),
+        (0x00FE, Instruction::Low),
+        (0x00FF, Instruction::High),
+        (0x1234, Instruction::JpAddr(0x234)),
+        (0x2345, Instruction::CallAddr(0x345)),
+        (0x3A12, Instruction::SeVxByte(0xA, 0x12)),
+        (0x4B34, Instruction::SneVxByte(0xB, 0x34)),
+        (0x5120, Instruction::SeVxVy(0x1, 0x2)),
+        (0x5312, Instruction::LdIndexVxVy(0x3, 0x1)),
+        (0x5133, Instruction::LdVxVyIndex(0x1, 0x3)),
+        (0x6C56, Instruction::LdVxByte(0xC, 0x56)),
+        (0x7D78, Instruction::AddVxByte(0xD, 0x78)),
+        (0x8120, Instruction::LdVxVy(0x1, 0x2)),
+        (0x8121, Instruction::OrVxVy(0x1, 0x2)),
+        (0x8122, Instruction::AndVxVy(0x1, 0x2)),
+        (0x8123, Instruction::XorVxVy(0x1, 0x2)),
+        (0x8124, Instruction::AddVxVy(0x1, 0x2)),
+        (0x8125, Instruction::SubVxVy(0x1, 0x2)),
+        (0x8126, Instruction::ShrVx(0x1, 0x2)),
+        (0x8127, Instruction::SubnVxVy(0x1, 0x2)),
+        (0x812E, Instruction::ShlVx(0x1, 0x2)),
+        (0x9120, Instruction::SneVxVy(0x1, 0x2)),
+        (0xA456, Instruction::LdIndexAddr(0x456)),
+        (0xB567, Instruction::JpV0Addr(0x567)),
+        (0xCE0F, Instruction::RndVxByte(0xE, 0x0F)),
+        (0xD125, Instruction::DrwVxVyNib(0x1, 0x2, 0x5)),
+        (0xE19E, Instruction::SkpVx(0x1)),
+        (0xE2A1, Instruction::SknpVx(0x2)),
+        (0xF301, Instruction::PlaneNib(0x3)),
+        (0xF002, Instruction::LdPatternIndex),
+        (0xF407, Instruction::LdVxDt(0x4)),
+        (0xF50A, Instruction::LdVxKey(0x5)),
+        (0xF615, Instruction::LdDtVx(0x6)),
+        (0xF718, Instruction::LdStVx(0x7)),
+        (0xF81E, Instruction::AddIndexVx(0x8)),
+        (0xF929, Instruction::LdIndexVxSprite(0x9)),
+        (0xFA30, Instruction::LdIndexVxBigSprite(0xA)),
+        (0xFB33, Instruction::LdBcdVx(0xB)),
+        (0xFC3A, Instruction::LdPitchVx(0xC)),
+        (0xFD55, Instruction::LdIndexImmVx(0xD)),
+        (0xFE65, Instruction::LdVxIndexImm(0xE)),
+        (0xF775, Instruction::LdRplVx(0x7)),
+        (0xF785, Instruction::LdVxRpl(0x7)),
+    ];
+    for &(word, instruction) in cases.iter() {
+        assert_eq!(Instruction::decode(word), Ok(instruction), "{:#06X}", word);
+    }
+}
+
+#[test]
+fn rejects_unknown_opcodes() {
+    for &word in [0x0000, 0x0123, 0x00E1, 0x5121, 0x8128, 0x9121, 0xE19F,
+                  0xF100, 0xF102, 0xF0FF].iter() {
+        assert_eq!(Instruction::decode(word),
+                   Err(DecodeError::UnknownOpcode(word)), "{:#06X}", word);
+    }
+}
+
+#[test]
+fn long_index_load_needs_its_operand() {
+    assert_eq!(Instruction::decode(0xF000), Err(DecodeError::Truncated));
+    assert_eq!(Instruction::decode_bytes(&[0xF0, 0x00, 0x12]),
+               Err(DecodeError::Truncated));
+    assert_eq!(Instruction::decode_bytes(&[0xF0, 0x00, 0x12, 0x34]),
+               Ok(Instruction::LdIndexLong(0x1234)));
+    assert_eq!(Instruction::LdIndexLong(0x1234).encode(),
+               [0xF0, 0x00, 0x12, 0x34]);
+    assert_eq!(Instruction::LdIndexLong(0x1234).size(), 4);
+}
+
+#[test]
+fn encode_inverts_decode_for_every_word() {
+    for word in 0..=0xFFFF {
+        if let Ok(instruction) = Instruction::decode(word) {
+            let bytes = [(word >> 8) as u8, word as u8];
+            assert_eq!(instruction.encode(), bytes, "{}", instruction);
+            assert_eq!(instruction.size(), 2);
+        }
+    }
+}
+
+#[test]
+fn mnemonics_use_classic_syntax() {
+    assert_eq!(Instruction::LdVxByte(0x0, 0x10).to_string(), "LD V0, 0x10");
+    assert_eq!(Instruction::DrwVxVyNib(0xA, 0xB, 6).to_string(),
+               "DRW VA, VB, 6");
+    assert_eq!(Instruction::LdIndexVxVy(0x1, 0x3).to_string(),
+               "LD [I], V1-V3");
+    assert_eq!(Instruction::CallAddr(0x2D4).to_string(), "CALL 0x02D4");
+}
@@ -0,0 +1,80 @@
+extern crate chip8;
+
+use chip8::{Chip8, Keypad, Quirks};
+
+#[test]
+fn press_and_release() {
+    let mut keypad = Keypad::new();
+    assert_eq!(keypad, Keypad::default());
+    keypad.press(0x1);
+    keypad.press(0xF);
+    keypad.press(0x1);
+    assert!(keypad.is_pressed(0x1) && keypad.is_pressed(0xF));
+    assert!(!keypad.is_pressed(0x2));
+    assert_eq!(keypad.pressed_keys().collect::<Vec<_>>(), [0x1, 0xF]);
+
+    keypad.release(0x1);
+    keypad.release(0x2);
+    assert_eq!(keypad.pressed_keys().collect::<Vec<_>>(), [0xF]);
+    keypad.release_all();
+    assert_eq!(keypad.pressed_keys().count(), 0);
+}
+
+#[test]
+fn ignores_keys_past_f() {
+    let mut keypad = Keypad::new();
+    keypad.press(0x10);
+    keypad.press(0xFF);
+    assert_eq!(keypad, Keypad::new());
+    assert!(!keypad.is_pressed(0x10));
+    keypad.release(0x10);
+
+    let mut chip8 = Chip8::new(Quirks::default());
+    chip8.press_key(0x10);
+    assert_eq!(chip8.keypad(), &Keypad::new());
+}
+
+#[test]
+fn bits_round_trip() {
+    let mut keypad = Keypad::new();
+    keypad.press(0x0);
+    keypad.press(0x5);
+    keypad.press(0xF);
+    assert_eq!(keypad.bits(), 0x8021);
+    assert_eq!(Keypad::from_bits(0x8021), keypad);
+    assert_eq!(Keypad::from_bits(0x0), Keypad::new());
+    assert_eq!(Keypad::from_bits(0xFFFF).pressed_keys().count(), 16);
+}
+
+#[test]
+fn machine_keypad_events() {
+    let mut chip8 = Chip8::new(Quirks::default());
+    chip8.press_key(0x3);
+    chip8.press_key(0xA);
+    assert!(chip8.is_key_pressed(0x3) && chip8.is_key_pressed(0xA));
+    chip8.release_key(0x3);
+    assert!(!chip8.is_key_pressed(0x3));
+    assert_eq!(chip8.keypad().bits(), 1 << 0xA);
+
+    // Only keys that change state are pressed or released
+    chip8.set_keypad(&Keypad::from_bits(1 << 0x3 | 1 << 0x4));
+    assert_eq!(chip8.keypad().bits(), 1 << 0x3 | 1 << 0x4);
+    chip8.set_keypad(&Keypad::new());
+    assert_eq!(chip8.keypad(), &Keypad::new());
+}
+
+#[test]
+fn set_keypad_completes_key_wait() {
+    // LD V2, K
+    let mut chip8 = Chip8::new(Quirks::default());
+    chip8.load_rom(&[0xF2, 0x0A]).unwrap();
+    chip8.execute_cycle().unwrap();
+    assert!(chip8.key_wait().is_some());
+
+    chip8.set_keypad(&Keypad::from_bits(1 << 0xC));
+    assert!(chip8.key_wait().is_some());
+    chip8.set_keypad(&Keypad::new());
+    assert_eq!(chip8.key_wait(), None);
+    assert_eq!(chip8.registers()[0x2], 0xC);
+    assert_eq!(chip8.pc(), 0x202);
+}
@@ -0,0 +1,95 @@
+extern crate chip8;
+
+use chip8::{Chip8, Quirks};
+use chip8::font::Font;
+use chip8::keypad::Keypad;
+use chip8::movie::{Movie, MovieError, MAGIC, VERSION};
+
+/// RND V0, 0x0F; SKP V1; ADD V2, 1; LD F, V0; DRW V3, V4, 5; JP 0x200
+const ROM: &[u8] = &[0xC0, 0x0F, 0xE1, 0x9E, 0x72, 0x01, 0xF0, 0x29, 0xD3,
+                     0x45, 0x12, 0x00];
+
+/// Record `frames` frames of a run with a non-default seed, speed, quirks
+/// and font, returning the movie and the machine it ran on.
+fn record(frames: usize) -> (Movie, Chip8) {
+    let mut chip8 = Chip8::new(Quirks::vip());
+    chip8.set_seed(3);
+    chip8.set_instructions_per_frame(13);
+    chip8.load_font(&Font::vip());
+    chip8.load_rom(ROM).unwrap();
+
+    let mut movie = Movie::new(&chip8);
+    for frame in 0..frames {
+        let mut keys = Keypad::new();
+        if frame % 3 == 0 {
+            keys.press(0x0);
+        }
+        chip8.set_keypad(&keys);
+        movie.record(&keys);
+        chip8.run_frame().unwrap();
+    }
+    (movie, chip8)
+}
+
+#[test]
+fn round_trip() {
+    let (movie, _) = record(40);
+    let bytes = movie.to_bytes();
+    assert_eq!(Movie::from_bytes(&bytes).unwrap(), movie);
+
+    let empty = Movie::new(&Chip8::new(Quirks::default()));
+    assert_eq!(Movie::from_bytes(&empty.to_bytes()).unwrap(), empty);
+}
+
+#[test]
+fn replays_run() {
+    let (movie, recorded) = record(60);
+    let movie = Movie::from_bytes(&movie.to_bytes()).unwrap();
+
+    // A machine with default settings takes everything from the movie
+    let mut chip8 = Chip8::new(Quirks::default());
+    chip8.load_rom(ROM).unwrap();
+    movie.start(&mut chip8).unwrap();
+    for frame in 0..movie.len() {
+        chip8.set_keypad(&movie.frame(frame).unwrap());
+        chip8.run_frame().unwrap();
+    }
+    assert_eq!(movie.frame(movie.len()), None);
+    assert_eq!(chip8.font(), Font::vip());
+    assert_eq!(chip8.save_state(), recorded.save_state());
+}
+
+#[test]
+fn rejects_other_rom() {
+    let (movie, _) = record(1);
+    let mut chip8 = Chip8::new(Quirks::default());
+    chip8.load_rom(&[0x12, 0x00]).unwrap();
+    match movie.start(&mut chip8) {
+        Err(MovieError::RomMismatch) => {},
+        result => panic!("expected MovieError::RomMismatch, got {:?}",
+                         result),
+    }
+}
+
+#[test]
+fn rejects_bad_files() {
+    let (movie, _) = record(2);
+    let bytes = movie.to_bytes();
+
+    let mut bad = bytes.clone();
+    bad[0] ^= 0xFF;
+    assert!(matches!(Movie::from_bytes(&bad), Err(MovieError::BadMagic)));
+
+    let mut bad = bytes.clone();
+    bad[MAGIC.len()..MAGIC.len() + 2]
+        .copy_from_slice(&(VERSION + 1).to_be_bytes());
+    assert!(matches!(Movie::from_bytes(&bad),
+                     Err(MovieError::UnsupportedVersion(_))));
+
+    for len in MAGIC.len()..bytes.len() - 4 {
+        assert!(matches!(Movie::from_bytes(&bytes[..len]),
+                         Err(MovieError::Corrupt)), "{} bytes", len);
+    }
+    assert!(matches!(Movie::from_bytes(&bytes[..bytes.len() - 1]),
+                     Err(MovieError::Corrupt)));
+}
@@ -0,0 +1,133 @@
+extern crate chip8;
+
+use chip8::{Chip8, Quirks};
+use chip8::asm::AsmError;
+use chip8::octo::{self, OctoError};
+
+/// Compile `source` and run it until it halts with `exit`, returning the
+/// registers.
+fn run(source: &str) -> [u8; 16] {
+    let mut chip8 = Chip8::new(Quirks::default());
+    chip8.load_rom(&octo::compile(source).unwrap()).unwrap();
+    for _ in 0..1000 {
+        if chip8.word_at(chip8.pc()) == Some(0x00FD) {
+            return *chip8.registers();
+        }
+        chip8.execute_cycle().unwrap();
+    }
+    panic!("program didn't exit");
+}
+
+#[test]
+fn compiles_statements() {
+    let source = "
+        :const SPEED 4
+        :alias x v1
+        : sprite-data 0x18 0b00111100 -1
+        : main
+            x := SPEED      # 0x205
+            x += 1
+            v2 -= 1
+            v2 >>= x
+            v3 := random 0x0F
+            i := sprite-data
+            sprite x v2 3
+            later
+            i := long later
+            ;
+        : later
+            return
+    ";
+    assert_eq!(octo::compile(source).unwrap(),
+               [0x12, 0x05, 0x18, 0x3C, 0xFF, 0x61, 0x04, 0x71, 0x01, 0x72,
+                0xFF, 0x82, 0x16, 0xC3, 0x0F, 0xA2, 0x02, 0xD1, 0x23, 0x22,
+                0x1B, 0xF0, 0x00, 0x02, 0x1B, 0x00, 0xEE, 0x00, 0xEE]);
+}
+
+#[test]
+fn conditions() {
+    // Each comparison sets a register to whether it holds
+    let source = "
+        : main
+            v0 := 3  v1 := 5
+            va := 0  if v0 < v1 then va := 1
+            vb := 0  if v0 > v1 then vb := 1
+            vc := 0  if v1 <= 5 then vc := 1
+            vd := 0  if v0 >= 4 then vd := 1
+            ve := 0  if v0 != 3 then ve := 1
+            v2 := 0  if v0 -key then v2 := 1
+            exit
+    ";
+    let registers = run(source);
+    assert_eq!(&registers[0xA..0xF], &[1, 0, 1, 0, 0]);
+    assert_eq!(registers[0x2], 1);
+}
+
+#[test]
+fn blocks_and_loops() {
+    let source = "
+        : main
+            v0 := 0
+            v1 := 0
+            loop
+                v0 += 1
+                if v0 == 2 begin
+                    v1 += 10
+                else
+                    v1 += 1
+                end
+                while v0 != 5
+            again
+            exit
+    ";
+    let registers = run(source);
+    assert_eq!(registers[0x0], 5);
+    assert_eq!(registers[0x1], 14);
+}
+
+/// Compile `source`, which has a syntax error, and return the error.
+fn syntax_error(source: &str) -> AsmError {
+    match octo::compile(source) {
+        Err(OctoError::Syntax(err)) => err,
+        result => panic!("expected a syntax error, got {:?}", result),
+    }
+}
+
+#[test]
+fn errors_report_line_numbers() {
+    assert_eq!(syntax_error(": main\n\nv0 := 300"), AsmError {
+        file: "<input>".to_string(),
+        line: 3,
+        message: "value 300 doesn't fit in a byte".to_string(),
+    });
+    assert_eq!(syntax_error(": main\nnowhere").to_string(),
+               "<input>:2: undefined label nowhere");
+    assert_eq!(syntax_error("v0 := 1").message, "no main label");
+    assert_eq!(syntax_error(": main\nloop\nv0 += 1").line, 2);
+    assert_eq!(syntax_error(": main\n: main").line, 2);
+}
+
+#[test]
+fn reports_unsupported_features() {
+    let cases = [
+        (":macro twice { v0 += 1 }", ":macro"),
+        (":calc SPEED { 2 * 3 }", ":calc"),
+        (":next target v0 := 1", ":next"),
+        (":unpack 0xA main", ":unpack"),
+        (":stringmode hex \"0123\" { v0 := VALUE }", ":stringmode"),
+        ("\"hello\"", "strings"),
+        (":byte { 2 * 3 }", ":byte { expression }"),
+        ("scroll-up 4", "scroll-up"),
+        (":assert \"fits\" { HERE < 0x1000 }", ":assert"),
+    ];
+    for &(statement, feature) in &cases {
+        let source = format!(": main\n  v0 := 1\n  {}\n", statement);
+        assert_eq!(octo::compile(&source), Err(OctoError::Unsupported {
+            line: 3,
+            feature: feature.to_string(),
+        }), "{}", statement);
+    }
+
+    let err = octo::compile(": main\n:macro m { }").unwrap_err();
+    assert_eq!(err.to_string(), "<input>:2: unsupported Octo feature :macro");
+}
@@ -0,0 +1,34 @@
+extern crate chip8;
+
+use chip8::palette::{self, Palette};
+
+#[test]
+fn parses_presets() {
+    for &name in palette::PRESETS {
+        assert_eq!(Palette::parse(name), Ok(Palette::preset(name).unwrap()));
+    }
+    assert_eq!(Palette::parse("classic"), Ok(Palette::default()));
+}
+
+#[test]
+fn parses_colour_lists() {
+    let palette = Palette::parse("#102030,405060,#708090,A0b0C0").unwrap();
+    assert_eq!(palette.colours, [[0x10, 0x20, 0x30], [0x40, 0x50, 0x60],
+                                 [0x70, 0x80, 0x90], [0xA0, 0xB0, 0xC0]]);
+    assert_eq!(palette.colour(0x2), [0x70, 0x80, 0x90]);
+
+    // The second bitplane is blended from the two colours given
+    let palette = Palette::parse("000000, #FFFFFF").unwrap();
+    assert_eq!(palette.colours, [[0x00, 0x00, 0x00], [0xFF, 0xFF, 0xFF],
+                                 [0x7F, 0x7F, 0x7F], [0xFF, 0xFF, 0xFF]]);
+}
+
+#[test]
+fn rejects_bad_palettes() {
+    for &text in &["", "mauve", "FFFFFF", "000000,FFFFFF,FF0000",
+                   "000000,FFFFFF,FF0000,00FF00,0000FF", "000000,FFFFF",
+                   "000000,FFFFFFF", "000000,GGGGGG", "000000,+FFFFF",
+                   "000000,##FFFFFF", "000000,", "000000,ＦＦＦ"] {
+        assert!(Palette::parse(text).is_err(), "{:?}", text);
+    }
+}
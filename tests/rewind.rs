@@ -0,0 +1,93 @@
+extern crate chip8;
+
+use chip8::{Chip8, Quirks};
+use chip8::rewind::{apply_delta, encode_delta, Rewind};
+
+fn round_trip(old: &[u8], new: &[u8]) -> Vec<u8> {
+    let delta = encode_delta(old, new);
+    assert_eq!(apply_delta(&delta, new), old);
+    delta
+}
+
+#[test]
+fn encodes_identical_states() {
+    let state = vec![0x5A; 5000];
+    assert!(round_trip(&state, &state).len() < 8);
+    round_trip(&[], &[]);
+}
+
+#[test]
+fn encodes_resized_states() {
+    let short: Vec<u8> = (0..300).map(|i| i as u8).collect();
+    let mut long = short.clone();
+    long.extend((0..200).map(|i| (i * 3) as u8));
+    long[10] ^= 0xFF;
+
+    // The state grew since the older one, and shrank
+    round_trip(&short, &long);
+    round_trip(&long, &short);
+    round_trip(&[], &long);
+    round_trip(&long, &[]);
+}
+
+#[test]
+fn encodes_long_runs() {
+    // Unchanged and changed runs either side of each varint length
+    let mut old = vec![0x0; 40000];
+    let new = old.clone();
+    let mut i = 0;
+    for &(same, changed) in &[(0, 1), (127, 1), (128, 127), (129, 128),
+                              (16383, 200), (16384, 1)] {
+        i += same;
+        for byte in &mut old[i..i + changed] {
+            *byte = 0xFF;
+        }
+        i += changed;
+    }
+    assert!(i < old.len());
+    round_trip(&old, &new);
+    round_trip(&new, &old);
+}
+
+#[test]
+fn rewinds_resolution_change() {
+    // HIGH; LOW
+    let mut chip8 = Chip8::new(Quirks::default());
+    chip8.load_rom(&[0x00, 0xFF, 0x00, 0xFE]).unwrap();
+    let mut rewind = Rewind::default();
+    let mut states = Vec::new();
+    for _ in 0..3 {
+        states.push(chip8.save_state());
+        rewind.push(&chip8);
+        let _ = chip8.execute_cycle();
+    }
+
+    while let Some(state) = states.pop() {
+        assert!(rewind.pop(&mut chip8));
+        assert_eq!(chip8.save_state(), state);
+    }
+    assert!(rewind.is_empty());
+}
+
+#[test]
+fn stays_in_budget() {
+    // ADD V1, 1; LD I, 0x300; LD [I], V1; JP 0x200
+    let mut chip8 = Chip8::new(Quirks::default());
+    chip8.load_rom(&[0x71, 0x01, 0xA3, 0x00, 0xF1, 0x55, 0x12, 0x00])
+        .unwrap();
+    let budget = chip8.save_state().len() + 1000;
+    let mut rewind = Rewind::new(budget);
+    for _ in 0..500 {
+        rewind.push(&chip8);
+        chip8.execute_cycle().unwrap();
+        assert!(rewind.memory_used() <= budget);
+    }
+    let len = rewind.len();
+    assert!(len > 1 && len < 500);
+
+    for _ in 0..len {
+        assert!(rewind.pop(&mut chip8));
+    }
+    assert!(!rewind.pop(&mut chip8));
+    assert_eq!(rewind.memory_used(), 0);
+}
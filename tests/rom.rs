@@ -0,0 +1,116 @@
+extern crate chip8;
+
+use std::env;
+use std::fs;
+use std::io::{self, Cursor, Read};
+use std::process;
+
+use chip8::{Chip8, Quirks, RomError};
+use chip8::cartridge::CartridgeError;
+use chip8::chip8::{CLASSIC_MEMORY_SIZE, DEFAULT_LOAD_ADDRESS, MEMORY_SIZE};
+
+#[test]
+fn loads_at_load_address() {
+    let mut chip8 = Chip8::new(Quirks::default());
+    chip8.load_rom(&[0x12, 0x34]).unwrap();
+    let start = DEFAULT_LOAD_ADDRESS as usize;
+    assert_eq!(&chip8.memory()[start..start + 2], &[0x12, 0x34]);
+    assert_eq!(chip8.pc(), DEFAULT_LOAD_ADDRESS);
+
+    let mut chip8 = Chip8::new(Quirks::default());
+    chip8.set_load_address(0x600);
+    chip8.load_rom(&[0x12, 0x34]).unwrap();
+    assert_eq!(&chip8.memory()[0x600..0x602], &[0x12, 0x34]);
+    assert_eq!(chip8.pc(), 0x600);
+}
+
+#[test]
+fn loads_from_reader() {
+    let mut chip8 = Chip8::new(Quirks::default());
+    chip8.load_rom_from_reader(Cursor::new(vec![0x00, 0xE0])).unwrap();
+    assert_eq!(chip8.word_at(DEFAULT_LOAD_ADDRESS), Some(0x00E0));
+}
+
+#[test]
+fn rejects_empty_rom() {
+    let mut chip8 = Chip8::new(Quirks::default());
+    match chip8.load_rom(&[]) {
+        Err(RomError::Empty) => {},
+        result => panic!("expected RomError::Empty, got {:?}", result),
+    }
+}
+
+#[test]
+fn rejects_rom_past_end_of_memory() {
+    let max = CLASSIC_MEMORY_SIZE - DEFAULT_LOAD_ADDRESS as usize;
+    let mut chip8 = Chip8::new(Quirks::default());
+    chip8.load_rom(&vec![0xAA; max]).unwrap();
+    assert_eq!(chip8.memory()[CLASSIC_MEMORY_SIZE - 1], 0xAA);
+
+    let mut chip8 = Chip8::new(Quirks::default());
+    let rom = vec![0xAA; max + 1];
+    match chip8.load_rom_from_reader(Cursor::new(rom)) {
+        Err(RomError::TooLarge { size, max: limit }) => {
+            assert_eq!((size, limit), (max + 1, max));
+        },
+        result => panic!("expected RomError::TooLarge, got {:?}", result),
+    }
+    assert!(chip8.memory().iter().all(|&byte| byte != 0xAA));
+}
+
+#[test]
+fn extended_memory_fits_xo_chip_roms() {
+    let max = MEMORY_SIZE - DEFAULT_LOAD_ADDRESS as usize;
+    let mut chip8 = Chip8::new(Quirks::default());
+    chip8.set_extended_memory(true);
+    chip8.load_rom(&vec![0xAA; max]).unwrap();
+    assert_eq!(chip8.memory()[MEMORY_SIZE - 1], 0xAA);
+    match chip8.load_rom(&vec![0xAA; max + 1]) {
+        Err(RomError::TooLarge { max: limit, .. }) => assert_eq!(limit, max),
+        result => panic!("expected RomError::TooLarge, got {:?}", result),
+    }
+
+    // Only .xo8 files get extended memory
+    let dir = env::temp_dir().join(format!("chip8-rom-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let rom = vec![0xAA; CLASSIC_MEMORY_SIZE];
+    fs::write(dir.join("big.xo8"), &rom).unwrap();
+    fs::write(dir.join("big.ch8"), &rom).unwrap();
+    let mut xo_chip = Chip8::new(Quirks::default());
+    let xo_result = xo_chip.load_rom_file(dir.join("big.xo8"));
+    let mut classic = Chip8::new(Quirks::default());
+    let classic_result = classic.load_rom_file(dir.join("big.ch8"));
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert!(xo_result.is_ok());
+    assert!(xo_chip.extended_memory());
+    match classic_result {
+        Err(RomError::TooLarge { .. }) => {},
+        result => panic!("expected RomError::TooLarge, got {:?}", result),
+    }
+}
+
+#[test]
+fn stops_reading_endless_input() {
+    let mut chip8 = Chip8::new(Quirks::default());
+    match chip8.load_rom_from_reader(io::repeat(0xAA)) {
+        Err(RomError::TooLarge { .. }) => {},
+        result => panic!("expected RomError::TooLarge, got {:?}", result),
+    }
+
+    let cartridge = Cursor::new(b"GIF89a").chain(io::repeat(0x0));
+    match chip8.load_rom_from_reader(cartridge) {
+        Err(RomError::Cartridge(CartridgeError::TooLarge)) => {},
+        result => panic!("expected CartridgeError::TooLarge, got {:?}",
+                         result),
+    }
+}
+
+#[test]
+fn reports_missing_file() {
+    let mut chip8 = Chip8::new(Quirks::default());
+    match chip8.load_rom_file("no such rom.ch8") {
+        Err(RomError::Io(_)) => {},
+        result => panic!("expected RomError::Io, got {:?}", result),
+    }
+}
@@ -0,0 +1,100 @@
+extern crate chip8;
+
+use std::fs;
+use std::ptr;
+
+use chip8::{Chip8, Quirks};
+use chip8::palette::Palette;
+use chip8::romdb::Database;
+use chip8::state;
+
+const PLATFORMS: &str = r#"[
+    {
+        "id": "originalChip8",
+        "name": "Cosmac VIP CHIP-8",
+        "defaultTickrate": 15,
+        "quirks": {"shift": false, "memoryLeaveIUnchanged": false,
+                   "wrap": false, "jump": false, "logic": true}
+    }
+]"#;
+
+#[test]
+fn knows_bundled_roms() {
+    // Parsed once and shared
+    let database = Database::bundled();
+    assert!(ptr::eq(database, Database::bundled()));
+    for &file in &["IBMLOGO", "PONG", "INVADERS", "TICTAC", "BLINKY",
+                   "PICTURE"] {
+        let hash = state::hash_rom(&fs::read(file).unwrap());
+        assert!(database.lookup(&hash).is_some(), "{} is unknown", file);
+    }
+}
+
+#[test]
+fn configures_known_rom() {
+    let mut chip8 = Chip8::new(Quirks::default());
+    chip8.load_rom(&fs::read("BLINKY").unwrap()).unwrap();
+    let info = Database::bundled().lookup(chip8.rom_hash()).unwrap();
+    assert_eq!(info.title, "Blinky");
+    assert_eq!(info.platform.as_deref(), Some("Superchip"));
+    assert!(info.keys.contains(&("up".to_string(), 0x3)));
+
+    info.apply(&mut chip8);
+    assert_eq!(chip8.instructions_per_frame(), 50);
+    assert_eq!(chip8.quirks(), Quirks::schip());
+}
+
+#[test]
+fn applies_rom_overrides() {
+    let programs = r##"[{
+        "title": "Test",
+        "authors": ["Someone"],
+        "roms": {
+            "da39a3ee5e6b4b0d3255bfef95601890afd80709": {
+                "platforms": ["originalChip8"],
+                "quirkyPlatforms": {
+                    "originalChip8": {"shift": true,
+                                      "memoryIncrementByX": true}
+                },
+                "colors": {"pixels": ["#000000", "#FFFFFF"]}
+            }
+        }
+    }]"##;
+    let database = Database::from_json(programs, PLATFORMS).unwrap();
+    let info = database.lookup(&state::hash_rom(&[])).unwrap();
+    assert_eq!(info.authors, vec!["Someone".to_string()]);
+    assert_eq!(info.instructions_per_frame, Some(15));
+    assert_eq!(info.quirks, Some(Quirks {
+        shift: true,
+        load_store_x: true,
+        ..Quirks::vip()
+    }));
+    assert_eq!(info.palette, Some(Palette::parse("000000,FFFFFF").unwrap()));
+}
+
+#[test]
+fn chip48_increments_index_by_x() {
+    let platforms = r#"[{
+        "id": "chip48",
+        "quirks": {"shift": true, "memoryIncrementByX": true,
+                   "memoryLeaveIUnchanged": false, "wrap": false,
+                   "jump": true, "logic": false}
+    }]"#;
+    let programs = r#"[{
+        "title": "Test",
+        "roms": {
+            "da39a3ee5e6b4b0d3255bfef95601890afd80709": {
+                "platforms": ["chip48"]
+            }
+        }
+    }]"#;
+    let database = Database::from_json(programs, platforms).unwrap();
+    let info = database.lookup(&state::hash_rom(&[])).unwrap();
+    assert_eq!(info.quirks, Some(Quirks::chip48()));
+}
+
+#[test]
+fn unknown_rom() {
+    let hash = state::hash_rom(&[0x12, 0x00]);
+    assert_eq!(Database::bundled().lookup(&hash), None);
+}
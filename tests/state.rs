@@ -0,0 +1,97 @@
+extern crate chip8;
+
+use chip8::{Chip8, Quirks};
+use chip8::state::{StateError, MAGIC, VERSION};
+
+/// LD V2, 0x20; LD DT, V2; RND V0, 0xFF; ADD V1, 1; LD I, 0x300;
+/// LD [I], V1; JP 0x204
+const ROM: &[u8] = &[0x62, 0x20, 0xF2, 0x15, 0xC0, 0xFF, 0x71, 0x01, 0xA3,
+                     0x00, 0xF1, 0x55, 0x12, 0x04];
+
+fn machine() -> Chip8 {
+    let mut chip8 = Chip8::new(Quirks::default());
+    chip8.set_seed(7);
+    chip8.load_rom(ROM).unwrap();
+    chip8
+}
+
+fn run(chip8: &mut Chip8, cycles: usize) {
+    for _ in 0..cycles {
+        chip8.execute_cycle().unwrap();
+    }
+    chip8.tick_timers();
+}
+
+#[test]
+fn round_trip() {
+    let mut chip8 = machine();
+    run(&mut chip8, 25);
+    chip8.press_key(0x7);
+    let state = chip8.save_state();
+
+    let mut loaded = machine();
+    loaded.load_state(&state).unwrap();
+    assert_eq!(loaded.save_state(), state);
+    assert_eq!(loaded.pc(), chip8.pc());
+    assert_eq!(loaded.registers(), chip8.registers());
+    assert_eq!(loaded.delay_timer(), chip8.delay_timer());
+
+    // Both machines go on to do the same, random numbers included
+    for _ in 0..10 {
+        run(&mut chip8, 5);
+        run(&mut loaded, 5);
+        assert_eq!(loaded.save_state(), chip8.save_state());
+    }
+}
+
+#[test]
+fn rejects_other_rom() {
+    let state = machine().save_state();
+    let mut chip8 = Chip8::new(Quirks::default());
+    chip8.load_rom(&[0x12, 0x00]).unwrap();
+    match chip8.load_state(&state) {
+        Err(StateError::RomMismatch) => {},
+        result => panic!("expected StateError::RomMismatch, got {:?}",
+                         result),
+    }
+}
+
+#[test]
+fn rejects_bad_header() {
+    let mut state = machine().save_state();
+    state[0] ^= 0xFF;
+    match machine().load_state(&state) {
+        Err(StateError::BadMagic) => {},
+        result => panic!("expected StateError::BadMagic, got {:?}", result),
+    }
+
+    let mut state = machine().save_state();
+    state[MAGIC.len()..MAGIC.len() + 2]
+        .copy_from_slice(&(VERSION + 1).to_be_bytes());
+    match machine().load_state(&state) {
+        Err(StateError::UnsupportedVersion(version)) => {
+            assert_eq!(version, VERSION + 1);
+        },
+        result => panic!("expected StateError::UnsupportedVersion, got {:?}",
+                         result),
+    }
+}
+
+#[test]
+fn rejects_truncated_state() {
+    let mut chip8 = machine();
+    run(&mut chip8, 10);
+    let before = chip8.save_state();
+
+    // Every header length, then a sample of the rest
+    let lens = (0..64).chain((64..before.len()).step_by(97))
+        .chain(before.len() - 16..before.len());
+    for len in lens {
+        assert!(chip8.load_state(&before[..len]).is_err(), "{} bytes", len);
+        assert_eq!(chip8.save_state(), before);
+    }
+
+    let mut longer = before.clone();
+    longer.push(0x0);
+    assert!(chip8.load_state(&longer).is_err());
+}
@@ -0,0 +1,82 @@
+extern crate chip8;
+
+use chip8::{Chip8, Quirks};
+use chip8::timing::{self, DEFAULT_INSTRUCTIONS_PER_SECOND};
+
+/// LD V0, 0x10; LD DT, V0; LD ST, V0; then ADD V1, 0x1 in a loop.
+const ROM: [u8; 10] = [0x60, 0x10, 0xF0, 0x15, 0xF0, 0x18, 0x71, 0x01,
+                       0x12, 0x06];
+
+/// A machine running `ROM` at `per_frame` instructions per frame, with its
+/// timers set.
+fn timed(per_frame: u32) -> Chip8 {
+    let mut chip8 = Chip8::new(Quirks::default());
+    chip8.set_instructions_per_frame(per_frame);
+    chip8.load_rom(&ROM).unwrap();
+    for _ in 0..3 {
+        chip8.execute_cycle().unwrap();
+    }
+    chip8
+}
+
+#[test]
+fn converts_speed_to_instructions_per_frame() {
+    assert_eq!(timing::instructions_per_frame(
+                   DEFAULT_INSTRUCTIONS_PER_SECOND), 10);
+    assert_eq!(timing::instructions_per_frame(1000), 17);
+    assert_eq!(timing::instructions_per_frame(29), 1);
+    assert_eq!(timing::instructions_per_frame(0), 1);
+}
+
+#[test]
+fn timers_tick_once_per_frame() {
+    for &per_frame in &[1, 3, 10, 50] {
+        let mut chip8 = timed(per_frame);
+        for frame in 1..6 {
+            chip8.run_frame().unwrap();
+            assert_eq!(chip8.delay_timer(), 0x10 - frame as u8,
+                       "DT after {} frames at {}", frame, per_frame);
+            assert_eq!(chip8.sound_timer(), 0x10 - frame as u8,
+                       "ST after {} frames at {}", frame, per_frame);
+
+            // The loop starts with the ADD, so odd counts run one more
+            let adds = (frame * per_frame).div_ceil(2);
+            assert_eq!(chip8.registers()[0x1] as u32, adds,
+                       "V1 after {} frames at {}", frame, per_frame);
+        }
+    }
+}
+
+#[test]
+fn timers_stop_at_zero() {
+    let mut chip8 = timed(10);
+    assert!(chip8.sound_active());
+    for _ in 0..0x20 {
+        chip8.run_frame().unwrap();
+    }
+    assert_eq!((chip8.delay_timer(), chip8.sound_timer()), (0, 0));
+    assert!(!chip8.sound_active());
+}
+
+#[test]
+fn speed_sets_instructions_per_frame() {
+    let mut chip8 = timed(1);
+    chip8.set_instructions_per_second(1200);
+    assert_eq!(chip8.instructions_per_frame(), 20);
+    chip8.run_frame().unwrap();
+    assert_eq!(chip8.registers()[0x1], 10);
+    assert_eq!(chip8.delay_timer(), 0xF);
+
+    chip8.set_instructions_per_frame(0);
+    assert_eq!(chip8.instructions_per_frame(), 1);
+}
+
+#[test]
+fn fault_skips_timer_tick() {
+    // LD V0, 0x10; LD DT, V0; RET
+    let mut chip8 = Chip8::new(Quirks::default());
+    chip8.set_instructions_per_frame(10);
+    chip8.load_rom(&[0x60, 0x10, 0xF0, 0x15, 0x00, 0xEE]).unwrap();
+    assert!(chip8.run_frame().is_err());
+    assert_eq!(chip8.delay_timer(), 0x10);
+}